@@ -0,0 +1,110 @@
+//! Client for the Finger protocol, which answers a query about a user with
+//! plain text.
+//!
+//! https://www.rfc-editor.org/rfc/rfc1288
+
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use percent_encoding::percent_decode_str;
+use thiserror::Error;
+use url::Url;
+
+/// MIME type responses are served with, so that they are shown as
+/// preformatted text. Finger itself has no MIME types.
+pub const MIME: &str = "text/x-finger";
+
+const DEFAULT_PORT: u16 = 79;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Error, Debug)]
+pub enum FingerClientError {
+    #[error("URL does not contain a host")]
+    NoHostError,
+    #[error("URL scheme of {0} is not supported")]
+    UnsupportedSchemeError(String),
+    #[error("Could not open the TCP connection: {0}")]
+    IoError(#[from] io::Error),
+    #[error("Url could not be parsed: {0}")]
+    UrlParseError(#[from] url::ParseError),
+}
+
+type Result<T, E = FingerClientError> = core::result::Result<T, E>;
+
+pub struct Response {
+    pub url: Url,
+    pub body: Vec<u8>,
+}
+
+/// Client performing Finger queries, each on its own connection.
+#[derive(Clone)]
+pub struct FingerClient {
+    timeout: Duration,
+}
+
+impl Default for FingerClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FingerClient {
+    pub fn new() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// How long connecting, or waiting on the server while it is silent,
+    /// may take before giving up.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Queries the user a `finger://` URL points to, written either as
+    /// `finger://host/user` or `finger://user@host`. Without a user, the
+    /// host lists the users logged in.
+    pub fn fetch(&self, url: &str) -> Result<Response> {
+        let mut url = Url::parse(url)?;
+        url.set_fragment(None);
+        if url.scheme() != "finger" {
+            return Err(FingerClientError::UnsupportedSchemeError(url.to_string()));
+        }
+        let host = url.host_str().ok_or(FingerClientError::NoHostError)?;
+        let port = url.port().unwrap_or(DEFAULT_PORT);
+
+        let user = match url.username() {
+            "" => url.path().trim_start_matches('/'),
+            user => user,
+        };
+        let query = percent_decode_str(user).decode_utf8_lossy();
+
+        let mut stream = self.connect(host, port)?;
+        stream.write_all(format!("{}\r\n", query).as_bytes())?;
+
+        let mut body = Vec::new();
+        stream.read_to_end(&mut body)?;
+
+        Ok(Response { url, body })
+    }
+
+    fn connect(&self, host: &str, port: u16) -> Result<TcpStream> {
+        let mut error = None;
+        for address in (host, port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, self.timeout) {
+                Ok(socket) => {
+                    socket.set_read_timeout(Some(self.timeout))?;
+                    socket.set_write_timeout(Some(self.timeout))?;
+                    return Ok(socket);
+                }
+                Err(failure) => error = Some(failure),
+            }
+        }
+
+        Err(error.map_or(FingerClientError::NoHostError, Into::into))
+    }
+}
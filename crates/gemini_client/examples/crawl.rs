@@ -0,0 +1,41 @@
+//! Follows the links of a capsule from a page, without leaving its host,
+//! printing every page reached along with its title:
+//! `cargo run -p gemini_client --example crawl -- URL [LIMIT]`.
+
+use std::{env, process::ExitCode};
+
+use gemini_client::{Document, GeminiClient, crawl::Crawler, gemtext};
+
+/// Pages fetched when no limit is given.
+const DEFAULT_LIMIT: usize = 50;
+
+fn main() -> ExitCode {
+    let mut arguments = env::args().skip(1);
+    let start = arguments.next();
+    let limit = arguments.next().map(|limit| limit.parse());
+    let (start, limit) = match (start, limit.unwrap_or(Ok(DEFAULT_LIMIT))) {
+        (Some(start), Ok(limit)) => (start, limit),
+        _ => {
+            eprintln!("Usage: crawl URL [LIMIT]");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let crawl = Crawler::new(GeminiClient::new())
+        .depth(usize::MAX)
+        .limit(limit)
+        .crawl([start]);
+
+    for page in crawl {
+        match page.response {
+            Ok(response) if response.mime() == gemtext::MIME => {
+                let document = Document::parse(&String::from_utf8_lossy(&response.body));
+                println!("{} {}", page.url, document.title().unwrap_or("Untitled"));
+            }
+            Ok(response) => println!("{} ({})", page.url, response.mime()),
+            Err(error) => println!("{} ({})", page.url, error),
+        }
+    }
+
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,54 @@
+//! Fetches a URL, printing its header on the standard error and its body on
+//! the standard output: `cargo run -p gemini_client --example fetch -- URL`.
+
+use std::{
+    env,
+    io::{self, Write},
+    process::ExitCode,
+};
+
+use gemini_client::GeminiClient;
+
+fn main() -> ExitCode {
+    let Some(url) = env::args().nth(1) else {
+        eprintln!("Usage: fetch URL");
+        return ExitCode::FAILURE;
+    };
+
+    let response = match GeminiClient::new().fetch(&url) {
+        Ok(response) => response,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for redirect in &response.redirects {
+        eprintln!("Redirected from {} to {}", redirect.from, redirect.to);
+    }
+    eprintln!("20 {}", response.meta);
+
+    // Text is decoded from its charset, anything else is written as it is
+    let written = if response.mime().starts_with("text/") {
+        response
+            .text()
+            .map_err(|error| error.to_string())
+            .and_then(|text| {
+                io::stdout()
+                    .write_all(text.as_bytes())
+                    .map_err(|error| error.to_string())
+            })
+    } else {
+        io::stdout()
+            .write_all(&response.body)
+            .map_err(|error| error.to_string())
+    };
+
+    match written {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
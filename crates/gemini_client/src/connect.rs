@@ -0,0 +1,117 @@
+//! Connection attempts racing over every address of a host, Happy Eyeballs
+//! style: a slow or broken address only delays the next one by a moment
+//! instead of a whole timeout.
+//!
+//! https://www.rfc-editor.org/rfc/rfc8305
+
+use std::{
+    collections::VecDeque,
+    io,
+    net::{SocketAddr, TcpStream},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long an attempt runs alone before the next address is tried too.
+const ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Connects to whichever address answers first, returning it along with the
+/// socket. Attempts that lose the race are dropped once they finish.
+pub(crate) fn connect(
+    addresses: Vec<SocketAddr>,
+    timeout: Duration,
+) -> io::Result<(TcpStream, SocketAddr)> {
+    let mut pending = interleave(addresses);
+    let deadline = Instant::now() + timeout;
+    let (sender, receiver) = mpsc::channel();
+    let mut running = 0;
+    let mut error = None;
+
+    loop {
+        if let Some(address) = pending.pop_front() {
+            let sender = sender.clone();
+            thread::spawn(move || {
+                let _ = sender.send((address, TcpStream::connect_timeout(&address, timeout)));
+            });
+            running += 1;
+        } else if running == 0 {
+            return Err(error.unwrap_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "the host has no address")
+            }));
+        }
+
+        // The next address is tried when this one fails or takes too long
+        let wait = if pending.is_empty() {
+            deadline.saturating_duration_since(Instant::now())
+        } else {
+            ATTEMPT_DELAY
+        };
+        match receiver.recv_timeout(wait) {
+            Ok((address, Ok(socket))) => return Ok((socket, address)),
+            Ok((_, Err(failure))) => {
+                running -= 1;
+                error = Some(failure);
+            }
+            Err(RecvTimeoutError::Timeout) if pending.is_empty() => {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// Alternates between IPv6 and IPv4 addresses, starting with the family the
+/// resolver listed first.
+fn interleave(addresses: Vec<SocketAddr>) -> VecDeque<SocketAddr> {
+    let first_is_ipv6 = addresses.first().is_some_and(SocketAddr::is_ipv6);
+    let (preferred, other): (Vec<_>, Vec<_>) = addresses
+        .into_iter()
+        .partition(|address| address.is_ipv6() == first_is_ipv6);
+
+    let mut preferred = preferred.into_iter();
+    let mut other = other.into_iter();
+    let mut ordered = VecDeque::new();
+    loop {
+        match (preferred.next(), other.next()) {
+            (None, None) => return ordered,
+            (first, second) => {
+                ordered.extend(first);
+                ordered.extend(second);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn alternates_address_families() {
+        let addresses = ["[::1]:1", "[::2]:1", "[::3]:1", "10.0.0.1:1", "10.0.0.2:1"]
+            .map(|address| address.parse::<SocketAddr>().unwrap());
+        let ordered = interleave(addresses.to_vec());
+
+        assert_eq!(
+            Vec::from(ordered),
+            [0, 3, 1, 4, 2].map(|index| addresses[index])
+        );
+    }
+
+    #[test]
+    fn connects_past_a_refusing_address() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let open = listener.local_addr().unwrap();
+        // Nothing listens on the address once that listener is dropped
+        let closed = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let (_, address) = connect(vec![closed, open], Duration::from_secs(5)).unwrap();
+        assert_eq!(address, open);
+    }
+}
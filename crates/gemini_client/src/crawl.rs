@@ -0,0 +1,394 @@
+//! Crawler following the gemtext links of capsules from seed URLs, for
+//! indexers and the like. It is polite about it: a single request at a time
+//! per host, spaced out, within what their robots.txt allows.
+//!
+//! https://geminiprotocol.net/docs/companion/robots.gmi
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{
+        Arc, Mutex, PoisonError,
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use url::Url;
+
+use crate::{
+    GeminiClient, GeminiClientError, Response, Result, RobotsPolicy,
+    gemtext::{self, Document},
+    normalize,
+    robots::host_key,
+};
+
+const DEFAULT_DEPTH: usize = 3;
+const DEFAULT_THREADS: usize = 4;
+const DEFAULT_DELAY: Duration = Duration::from_secs(1);
+const DEFAULT_MAX_BODY: usize = 1024 * 1024;
+/// Times a page is requested again when its host asks to slow down.
+const SLOW_DOWN_RETRIES: usize = 3;
+
+pub struct Crawler {
+    client: GeminiClient,
+    depth: usize,
+    threads: usize,
+    delay: Duration,
+    max_body: usize,
+    limit: Option<usize>,
+    other_hosts: bool,
+    robots: RobotsPolicy,
+}
+
+/// Page reached by a crawl.
+pub struct Page {
+    pub url: Url,
+    /// Links followed from a seed to reach the page, 0 for the seeds.
+    pub depth: usize,
+    /// Response to the request, its body cut short after
+    /// [`Crawler::max_body`] bytes.
+    pub response: Result<Response>,
+}
+
+impl Crawler {
+    /// Crawler requesting pages with the client, whose configuration and
+    /// pinned certificates are shared by the threads of the crawl.
+    pub fn new(client: GeminiClient) -> Self {
+        Self {
+            robots: RobotsPolicy::new(client.clone()),
+            client,
+            depth: DEFAULT_DEPTH,
+            threads: DEFAULT_THREADS,
+            delay: DEFAULT_DELAY,
+            max_body: DEFAULT_MAX_BODY,
+            limit: None,
+            other_hosts: false,
+        }
+    }
+
+    /// Links followed from the seeds, 3 by default. With 0 only the seeds
+    /// are fetched.
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Requests running at once, to different hosts, 4 by default.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Time between two requests to the same host, a second by default.
+    /// Hosts asking to slow down with status 44 are left alone for as long
+    /// as they ask.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Bytes of a body read, the rest of larger ones is never downloaded.
+    /// 1 MiB by default.
+    pub fn max_body(mut self, bytes: usize) -> Self {
+        self.max_body = bytes;
+        self
+    }
+
+    /// Pages fetched before the crawl ends, unlimited by default.
+    pub fn limit(mut self, pages: usize) -> Self {
+        self.limit = Some(pages);
+        self
+    }
+
+    /// Whether links to hosts other than those of the seeds are followed,
+    /// which they are not by default.
+    pub fn other_hosts(mut self, follow: bool) -> Self {
+        self.other_hosts = follow;
+        self
+    }
+
+    /// Rules of robots.txt the crawl follows, those for the `indexer`
+    /// agent by default. Crawls sharing a policy fetch the robots.txt of
+    /// each host once.
+    pub fn robots(mut self, robots: RobotsPolicy) -> Self {
+        self.robots = robots;
+        self
+    }
+
+    /// Starts crawling from the seeds, skipping those that are not Gemini
+    /// URLs. The pages come out of the crawl as they are fetched, and
+    /// dropping it stops the crawl.
+    pub fn crawl<S: AsRef<str>>(self, seeds: impl IntoIterator<Item = S>) -> Crawl {
+        let (jobs, receiver) = mpsc::channel();
+        let (sender, done) = mpsc::channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for _ in 0..self.threads {
+            let (client, robots) = (self.client.clone(), self.robots.clone());
+            let (receiver, sender) = (receiver.clone(), sender.clone());
+            let max_body = self.max_body;
+            thread::spawn(move || work(client, robots, max_body, receiver, sender));
+        }
+
+        let mut crawl = Crawl {
+            crawler: self,
+            jobs,
+            done,
+            queue: VecDeque::new(),
+            seen: HashSet::new(),
+            hosts: HashMap::new(),
+            seed_hosts: HashSet::new(),
+            running: 0,
+            started: 0,
+        };
+
+        for seed in seeds {
+            let Ok(url) = Url::parse(seed.as_ref()) else {
+                continue;
+            };
+            if let Some(key) = host_key(&url) {
+                crawl.seed_hosts.insert(key);
+                crawl.enqueue(url, 0);
+            }
+        }
+
+        crawl
+    }
+}
+
+/// Crawl in progress, an iterator over the pages it reaches.
+pub struct Crawl {
+    crawler: Crawler,
+    jobs: Sender<Job>,
+    done: Receiver<Done>,
+    queue: VecDeque<Visit>,
+    seen: HashSet<Url>,
+    /// What is known of every host met, by `host:port`.
+    hosts: HashMap<String, Host>,
+    seed_hosts: HashSet<String>,
+    /// Requests running, robots.txt included.
+    running: usize,
+    /// Pages requested, counted against the limit.
+    started: usize,
+}
+
+struct Visit {
+    url: Url,
+    depth: usize,
+    /// Times the host asked to slow down instead of answering.
+    attempts: usize,
+}
+
+#[derive(Default)]
+struct Host {
+    /// Whether a request to the host is running.
+    busy: bool,
+    /// When the host can be requested again.
+    ready: Option<Instant>,
+}
+
+enum Job {
+    Robots(String, Url),
+    Page(Visit),
+}
+
+enum Done {
+    /// Whether the robots.txt of the host was fetched, or how long it asks
+    /// to slow down.
+    Robots(String, Result<(), Duration>),
+    Page(Visit, Box<Result<Response>>),
+}
+
+impl Crawl {
+    fn enqueue(&mut self, url: Url, depth: usize) {
+        let url = normalize(&url);
+        let followed = url.scheme() == "gemini"
+            && host_key(&url)
+                .is_some_and(|key| self.crawler.other_hosts || self.seed_hosts.contains(&key));
+
+        if followed && self.seen.insert(url.clone()) {
+            self.queue.push_back(Visit {
+                url,
+                depth,
+                attempts: 0,
+            });
+        }
+    }
+
+    /// Whether pages are left to request.
+    fn pending(&self) -> bool {
+        !self.queue.is_empty() && self.crawler.limit.is_none_or(|limit| self.started < limit)
+    }
+
+    /// Sends the queued pages whose host is ready to the threads, fetching
+    /// the robots.txt of the hosts met for the first time instead.
+    fn dispatch(&mut self) {
+        let now = Instant::now();
+        let mut index = 0;
+
+        while self.running < self.crawler.threads && index < self.queue.len() && self.pending() {
+            let Some(key) = host_key(&self.queue[index].url) else {
+                self.queue.remove(index);
+                continue;
+            };
+            let host = self.hosts.entry(key.clone()).or_default();
+            if host.busy || host.ready.is_some_and(|ready| ready > now) {
+                index += 1;
+                continue;
+            }
+
+            let job = if self.crawler.robots.knows(&self.queue[index].url) {
+                let Some(visit) = self.queue.remove(index) else {
+                    break;
+                };
+                if !self.crawler.robots.allows(&visit.url) {
+                    continue;
+                }
+                self.started += 1;
+                Job::Page(visit)
+            } else {
+                // The page waits for the rules of its host
+                index += 1;
+                Job::Robots(key, self.queue[index - 1].url.clone())
+            };
+
+            host.busy = true;
+            self.running += 1;
+            // The threads only stop once the crawl is dropped
+            let _ = self.jobs.send(job);
+        }
+    }
+
+    /// Earliest time a queued page could be requested by a thread left
+    /// idle, its host waiting out the delay.
+    fn next_ready(&self) -> Option<Instant> {
+        if self.running == self.crawler.threads || !self.pending() {
+            return None;
+        }
+
+        self.queue
+            .iter()
+            .filter_map(|visit| self.hosts.get(&host_key(&visit.url)?))
+            .filter(|host| !host.busy)
+            .filter_map(|host| host.ready)
+            .min()
+    }
+
+    /// Takes the outcome of a request into account, returning the page it
+    /// fetched if it is one for the caller.
+    fn record(&mut self, done: Done) -> Option<Page> {
+        self.running -= 1;
+        let now = Instant::now();
+        let key = match &done {
+            Done::Robots(key, _) => key.clone(),
+            Done::Page(visit, _) => host_key(&visit.url)?,
+        };
+        let host = self.hosts.entry(key).or_default();
+        host.busy = false;
+        host.ready = Some(now + self.crawler.delay);
+
+        match done {
+            Done::Robots(_, Ok(())) => None,
+            Done::Robots(_, Err(wait)) => {
+                host.ready = Some(now + wait.max(self.crawler.delay));
+                None
+            }
+            Done::Page(mut visit, response)
+                if visit.attempts < SLOW_DOWN_RETRIES
+                    && let Err(GeminiClientError::SlowDown { wait, .. }) = *response =>
+            {
+                host.ready = Some(now + wait.max(self.crawler.delay));
+                visit.attempts += 1;
+                self.started -= 1;
+                self.queue.push_front(visit);
+                None
+            }
+            Done::Page(visit, response) => {
+                let response = *response;
+                if let Ok(response) = &response
+                    && visit.depth < self.crawler.depth
+                    && response.mime() == gemtext::MIME
+                {
+                    let document = Document::parse(&String::from_utf8_lossy(&response.body));
+                    for (link, _) in document.links() {
+                        // Links are relative to where the page was served from
+                        if let Ok(link) = response.url.join(link) {
+                            self.enqueue(link, visit.depth + 1);
+                        }
+                    }
+                }
+
+                Some(Page {
+                    url: visit.url,
+                    depth: visit.depth,
+                    response,
+                })
+            }
+        }
+    }
+}
+
+impl Iterator for Crawl {
+    type Item = Page;
+
+    fn next(&mut self) -> Option<Page> {
+        loop {
+            self.dispatch();
+            let ready = self.next_ready();
+
+            // Only hosts waiting out their delay are left, if any
+            if self.running == 0 {
+                thread::sleep(ready?.saturating_duration_since(Instant::now()));
+                continue;
+            }
+
+            let done = match ready {
+                Some(ready) => match self
+                    .done
+                    .recv_timeout(ready.saturating_duration_since(Instant::now()))
+                {
+                    Ok(done) => done,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => return None,
+                },
+                None => self.done.recv().ok()?,
+            };
+
+            if let Some(page) = self.record(done) {
+                return Some(page);
+            }
+        }
+    }
+}
+
+/// Runs the requests sent by the crawl until it is dropped.
+fn work(
+    client: GeminiClient,
+    robots: RobotsPolicy,
+    max_body: usize,
+    jobs: Arc<Mutex<Receiver<Job>>>,
+    done: Sender<Done>,
+) {
+    loop {
+        let job = jobs.lock().unwrap_or_else(PoisonError::into_inner).recv();
+        let Ok(job) = job else {
+            return;
+        };
+
+        let outcome = match job {
+            Job::Robots(key, url) => match robots.load(&url) {
+                Err(GeminiClientError::SlowDown { wait, .. }) => Done::Robots(key, Err(wait)),
+                _ => Done::Robots(key, Ok(())),
+            },
+            Job::Page(visit) => {
+                let response = client.peek(visit.url.as_str(), None, max_body);
+                Done::Page(visit, Box::new(response))
+            }
+        };
+
+        if done.send(outcome).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Parser for the gemtext markup language.
+//!
+//! https://geminiprotocol.net/docs/gemtext-specification.gmi
+
+pub const MIME: &str = "text/gemini";
+
+/// A single gemtext line. Documents keep one line per source line so that
+/// positions can be mapped back to the original text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Line {
+    Text(String),
+    Link {
+        url: String,
+        label: Option<String>,
+    },
+    Heading {
+        level: u8,
+        text: String,
+    },
+    ListItem(String),
+    Quote(String),
+    /// Line opening or closing a preformatted block, with the alt text of
+    /// opening lines.
+    PreformatToggle {
+        alt: Option<String>,
+    },
+    Preformatted(String),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Document {
+    pub lines: Vec<Line>,
+}
+
+impl Document {
+    pub fn parse(text: &str) -> Self {
+        let mut lines = Vec::new();
+        let mut preformatted = false;
+
+        for line in text.lines() {
+            if let Some(alt) = line.strip_prefix("```") {
+                let alt = alt.trim();
+                lines.push(Line::PreformatToggle {
+                    alt: (!preformatted && !alt.is_empty()).then(|| alt.to_owned()),
+                });
+                preformatted = !preformatted;
+            } else if preformatted {
+                lines.push(Line::Preformatted(line.to_owned()));
+            } else {
+                lines.push(parse_line(line));
+            }
+        }
+
+        Self { lines }
+    }
+
+    /// Document for a non-gemtext text response, where every line is plain
+    /// text.
+    pub fn plain(text: &str) -> Self {
+        Self {
+            lines: text
+                .lines()
+                .map(|line| Line::Text(line.to_owned()))
+                .collect(),
+        }
+    }
+
+    /// Document for text whose layout matters, shown as a single
+    /// preformatted block.
+    pub fn preformatted(text: &str) -> Self {
+        Self {
+            lines: text
+                .lines()
+                .map(|line| Line::Preformatted(line.to_owned()))
+                .collect(),
+        }
+    }
+
+    /// Text of the first heading, which gemtext documents use as their title.
+    pub fn title(&self) -> Option<&str> {
+        self.lines.iter().find_map(|line| match line {
+            Line::Heading { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn links(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Link { url, label } => Some((url.as_str(), label.as_deref())),
+            _ => None,
+        })
+    }
+}
+
+fn parse_line(line: &str) -> Line {
+    if let Some(link) = line.strip_prefix("=>") {
+        let link = link.trim();
+        let (url, label) = link
+            .split_once(char::is_whitespace)
+            .map_or((link, None), |(url, label)| (url, Some(label.trim())));
+
+        if !url.is_empty() {
+            return Line::Link {
+                url: url.to_owned(),
+                label: label.filter(|label| !label.is_empty()).map(str::to_owned),
+            };
+        }
+    }
+
+    for level in (1..=3).rev() {
+        let marker = &"###"[..level];
+        if let Some(text) = line.strip_prefix(marker) {
+            return Line::Heading {
+                level: level as u8,
+                text: text.trim().to_owned(),
+            };
+        }
+    }
+
+    if let Some(item) = line.strip_prefix("* ") {
+        return Line::ListItem(item.trim().to_owned());
+    }
+
+    if let Some(quote) = line.strip_prefix('>') {
+        return Line::Quote(quote.trim().to_owned());
+    }
+
+    Line::Text(line.to_owned())
+}
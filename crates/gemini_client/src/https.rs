@@ -0,0 +1,131 @@
+//! Minimal HTTPS client for text/gemini content mirrored on the web.
+//!
+//! Only plain `GET` requests are made: no cookies, no compression and a new
+//! connection per request. Certificates are always verified strictly.
+
+use std::{
+    io::{self, BufRead, Read, Write},
+    net::TcpStream,
+};
+
+use rustls::{ClientConfig, ClientConnection, StreamOwned};
+use url::Url;
+
+use crate::{GeminiClientError, Outcome, Result, TlsPolicy, verification};
+
+pub(crate) const DEFAULT_PORT: u16 = 443;
+const ACCEPT: &str = "text/gemini, text/plain;q=0.8, text/*;q=0.5";
+
+pub fn client_config() -> ClientConfig {
+    // Only fails on invalid client certificates, and there is none
+    let mut config = verification::client_config(TlsPolicy::Strict, None).unwrap();
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    config
+}
+
+/// Requests the URL over the connection opened to its host.
+pub(crate) fn get(
+    mut stream: StreamOwned<ClientConnection, TcpStream>,
+    url: &Url,
+) -> Result<Outcome> {
+    let host = url.host_str().ok_or(GeminiClientError::NoHostError)?;
+    let mut target = url.path().to_owned();
+    if let Some(query) = url.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+    let authority = match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_owned(),
+    };
+
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nAccept: {}\r\nConnection: close\r\n\r\n",
+        target, authority, ACCEPT
+    );
+    stream.write_all(request.as_bytes())?;
+
+    let mut status_line = String::new();
+    stream.read_line(&mut status_line)?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .ok_or_else(|| GeminiClientError::RequestError(String::from("Invalid HTTP response")))?
+        .parse::<u16>()?;
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_owned()));
+        }
+    }
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    match status {
+        200..=299 => {
+            let mut body = Vec::new();
+            // Plenty of servers close the connection without a TLS
+            // close_notify once the body is sent
+            match stream.read_to_end(&mut body) {
+                Err(error) if error.kind() != io::ErrorKind::UnexpectedEof => {
+                    return Err(error.into());
+                }
+                _ => {}
+            }
+
+            if header("transfer-encoding").is_some_and(|encoding| encoding.contains("chunked")) {
+                body = dechunk(&body)?;
+            } else if let Some(length) = header("content-length").and_then(|l| l.parse().ok()) {
+                body.truncate(length);
+            }
+
+            let meta = header("content-type").unwrap_or("application/octet-stream");
+            Ok(Outcome::Response(meta.to_owned(), body))
+        }
+        300..=399 => {
+            let location = header("location").ok_or_else(|| {
+                GeminiClientError::RequestError(String::from("Redirect without a location"))
+            })?;
+            Ok(Outcome::Redirect(
+                url.join(location)?,
+                status == 301 || status == 308,
+            ))
+        }
+        _ => Err(GeminiClientError::RequestError(
+            status_line.trim().to_owned(),
+        )),
+    }
+}
+
+/// Decodes a body sent with `Transfer-Encoding: chunked`.
+fn dechunk(mut data: &[u8]) -> Result<Vec<u8>> {
+    let invalid = || GeminiClientError::RequestError(String::from("Invalid chunked body"));
+    let mut body = Vec::new();
+
+    loop {
+        let line_end = data
+            .windows(2)
+            .position(|window| window == b"\r\n")
+            .ok_or_else(invalid)?;
+        let size = std::str::from_utf8(&data[..line_end]).map_err(|_| invalid())?;
+        let size = size.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16).map_err(|_| invalid())?;
+
+        data = &data[line_end + 2..];
+        if size == 0 {
+            return Ok(body);
+        }
+
+        body.extend_from_slice(data.get(..size).ok_or_else(invalid)?);
+        data = data.get(size + 2..).unwrap_or_default();
+    }
+}
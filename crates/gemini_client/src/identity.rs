@@ -0,0 +1,70 @@
+//! Client certificates, which capsules ask for with the 6X statuses to tell
+//! visitors apart.
+//!
+//! https://geminiprotocol.net/docs/protocol-specification.gmi#client-certificates
+
+use std::sync::Arc;
+
+use rcgen::{CertificateParams, DnType, KeyPair};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject};
+
+use crate::{Certificate, GeminiClientError, Result};
+
+/// A named self-signed client certificate along with its private key.
+#[derive(Clone, Debug)]
+pub struct Identity {
+    pub name: String,
+    pub certificate: Certificate,
+    /// Certificate and private key, PEM encoded.
+    pem: String,
+    der: CertificateDer<'static>,
+    key: Arc<PrivateKeyDer<'static>>,
+}
+
+impl Identity {
+    /// Generates a new self-signed certificate with the name as its common
+    /// name.
+    pub fn generate(name: &str) -> Result<Self> {
+        let generate = || -> Result<String, rcgen::Error> {
+            let key = KeyPair::generate()?;
+            let mut params = CertificateParams::new(Vec::new())?;
+            params.distinguished_name.push(DnType::CommonName, name);
+            let certificate = params.self_signed(&key)?;
+
+            Ok(format!("{}{}", certificate.pem(), key.serialize_pem()))
+        };
+
+        let pem =
+            generate().map_err(|error| GeminiClientError::IdentityError(error.to_string()))?;
+        Self::from_pem(name, pem)
+    }
+
+    /// Reads an identity from the PEM encoded certificate and private key.
+    pub fn from_pem(name: &str, pem: String) -> Result<Self> {
+        let invalid = |error: rustls::pki_types::pem::Error| {
+            GeminiClientError::IdentityError(error.to_string())
+        };
+        let der = CertificateDer::from_pem_slice(pem.as_bytes()).map_err(invalid)?;
+        let key = PrivateKeyDer::from_pem_slice(pem.as_bytes()).map_err(invalid)?;
+
+        Ok(Self {
+            name: name.to_owned(),
+            certificate: Certificate::from_der(&der),
+            pem,
+            der,
+            key: Arc::new(key),
+        })
+    }
+
+    pub fn to_pem(&self) -> &str {
+        &self.pem
+    }
+
+    pub(crate) fn chain(&self) -> Vec<CertificateDer<'static>> {
+        vec![self.der.clone()]
+    }
+
+    pub(crate) fn key(&self) -> PrivateKeyDer<'static> {
+        self.key.clone_key()
+    }
+}
@@ -0,0 +1,82 @@
+//! Host name resolution, replaceable to look hosts up elsewhere than in the
+//! system resolver.
+
+use std::{
+    collections::HashMap,
+    io,
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+};
+
+/// Turns a host name into the addresses connections are attempted to, see
+/// [`GeminiClientBuilder::resolver`](crate::GeminiClientBuilder::resolver).
+pub trait Resolver: Send + Sync {
+    fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>>;
+}
+
+/// Resolver of the operating system, as used by `TcpStream::connect`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        Ok((host, port).to_socket_addrs()?.collect())
+    }
+}
+
+/// Fixed addresses for some hosts, like a hosts file, the others being
+/// looked up by the system resolver.
+#[derive(Clone, Debug, Default)]
+pub struct StaticResolver {
+    hosts: HashMap<String, Vec<IpAddr>>,
+}
+
+impl StaticResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolves the host to the address, along with the others given for it.
+    pub fn host(mut self, host: &str, address: IpAddr) -> Self {
+        self.hosts
+            .entry(host.to_ascii_lowercase())
+            .or_default()
+            .push(address);
+        self
+    }
+}
+
+impl Resolver for StaticResolver {
+    fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        match self.hosts.get(&host.to_ascii_lowercase()) {
+            Some(addresses) => Ok(addresses
+                .iter()
+                .map(|&address| SocketAddr::new(address, port))
+                .collect()),
+            None => SystemResolver.resolve(host, port),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn static_hosts_take_precedence() {
+        let resolver = StaticResolver::new()
+            .host("Example.org", "192.0.2.1".parse().unwrap())
+            .host("example.org", "2001:db8::1".parse().unwrap());
+
+        assert_eq!(
+            resolver.resolve("example.org", 1965).unwrap(),
+            [
+                "192.0.2.1:1965".parse::<SocketAddr>().unwrap(),
+                "[2001:db8::1]:1965".parse().unwrap()
+            ]
+        );
+        assert_eq!(
+            resolver.resolve("127.0.0.1", 1965).unwrap(),
+            ["127.0.0.1:1965".parse::<SocketAddr>().unwrap()]
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use url::Url;
+
+use crate::{DEFAULT_PORT, GeminiClient, GeminiClientError, Result};
+
+const DEFAULT_USER_AGENT: &str = "indexer";
+/// Longest robots.txt read, the rest is ignored.
+const MAX_ROBOTS_LENGTH: usize = 64 * 1024;
+
+/// Rules of the robots.txt of capsules, fetched once per host and kept,
+/// that automated clients follow. Clones share the rules fetched.
+///
+/// https://geminiprotocol.net/docs/companion/robots.gmi
+#[derive(Clone)]
+pub struct RobotsPolicy {
+    client: GeminiClient,
+    user_agent: String,
+    /// Path prefixes disallowed on each host, by `host:port`.
+    rules: Arc<Mutex<HashMap<String, Vec<String>>>>,
+}
+
+impl RobotsPolicy {
+    pub fn new(client: GeminiClient) -> Self {
+        Self {
+            client,
+            user_agent: String::from(DEFAULT_USER_AGENT),
+            rules: Arc::default(),
+        }
+    }
+
+    /// Virtual user agent the rules are followed for, along with those for
+    /// every agent: `indexer` by default, `archiver`, `researcher` and
+    /// `webproxy` being the others.
+    pub fn user_agent(mut self, agent: impl Into<String>) -> Self {
+        self.user_agent = agent.into();
+        self
+    }
+
+    /// Refuses the URL with [`GeminiClientError::Disallowed`] when the
+    /// robots.txt of its host disallows it, fetching it the first time.
+    /// Hosts without one allow everything.
+    pub fn check(&self, url: &str) -> Result<()> {
+        let url = Url::parse(url)?;
+        if !self.knows(&url) {
+            self.load(&url)?;
+        }
+
+        if self.allows(&url) {
+            Ok(())
+        } else {
+            Err(GeminiClientError::Disallowed(url.to_string()))
+        }
+    }
+
+    /// Whether the rules of the host of the URL were fetched already.
+    pub fn knows(&self, url: &Url) -> bool {
+        host_key(url).is_some_and(|key| self.rules().contains_key(&key))
+    }
+
+    /// Whether the rules known for the host of the URL allow it, which
+    /// unknown ones do.
+    pub fn allows(&self, url: &Url) -> bool {
+        let Some(key) = host_key(url) else {
+            return true;
+        };
+
+        self.rules().get(&key).is_none_or(|disallowed| {
+            !disallowed
+                .iter()
+                .any(|path| url.path().starts_with(path.as_str()))
+        })
+    }
+
+    /// Fetches the robots.txt of the host of the URL, keeping its rules.
+    /// Only fails when the host asks to slow down, the request being worth
+    /// trying again.
+    pub fn load(&self, url: &Url) -> Result<()> {
+        let key = host_key(url).ok_or(GeminiClientError::NoHostError)?;
+        let mut robots = url.clone();
+        robots.set_path("/robots.txt");
+        robots.set_query(None);
+
+        let disallowed = match self.client.peek(robots.as_str(), None, MAX_ROBOTS_LENGTH) {
+            Ok(response) if response.mime().starts_with("text/") => {
+                parse(&String::from_utf8_lossy(&response.body), &self.user_agent)
+            }
+            Err(error @ GeminiClientError::SlowDown { .. }) => return Err(error),
+            _ => Vec::new(),
+        };
+        self.rules().insert(key, disallowed);
+
+        Ok(())
+    }
+
+    fn rules(&self) -> MutexGuard<'_, HashMap<String, Vec<String>>> {
+        self.rules.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Hosts are told apart by their port too, each one having its robots.txt.
+pub(crate) fn host_key(url: &Url) -> Option<String> {
+    Some(format!(
+        "{}:{}",
+        url.host_str()?,
+        url.port().unwrap_or(DEFAULT_PORT)
+    ))
+}
+
+/// Path prefixes robots.txt disallows to the virtual user agent, in the
+/// groups naming it or every agent.
+fn parse(robots: &str, user_agent: &str) -> Vec<String> {
+    let mut disallowed = Vec::new();
+    // A group starts with consecutive User-agent lines
+    let (mut applies, mut in_agents) = (false, false);
+
+    for line in robots.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let Some((field, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+
+        match field.trim().to_ascii_lowercase().as_str() {
+            "user-agent" => {
+                applies = (applies && in_agents)
+                    || value == "*"
+                    || value.eq_ignore_ascii_case(user_agent);
+                in_agents = true;
+            }
+            "disallow" => {
+                in_agents = false;
+                if applies && !value.is_empty() {
+                    disallowed.push(value.to_owned());
+                }
+            }
+            _ => in_agents = false,
+        }
+    }
+
+    disallowed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_apply_to_their_agents() {
+        let robots = "\
+User-agent: archiver
+User-agent: indexer
+Disallow: /private # not for search engines
+
+User-agent: webproxy
+Disallow: /
+
+User-agent: *
+Disallow: /cgi-bin
+";
+
+        assert_eq!(parse(robots, "indexer"), ["/private", "/cgi-bin"]);
+        assert_eq!(parse(robots, "researcher"), ["/cgi-bin"]);
+    }
+}
@@ -0,0 +1,154 @@
+//! SOCKS5 client without authentication, enough to tunnel connections
+//! through a proxy like Tor.
+//!
+//! https://www.rfc-editor.org/rfc/rfc1928
+
+use std::{
+    io::{self, Read, Write},
+    net::{IpAddr, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use crate::{GeminiClientError, Result};
+
+const VERSION: u8 = 5;
+const NO_AUTHENTICATION: u8 = 0;
+const CONNECT: u8 = 1;
+const IPV4: u8 = 1;
+const DOMAIN_NAME: u8 = 3;
+const IPV6: u8 = 4;
+
+/// Opens a connection to `host` through the proxy at `proxy`, given as
+/// `host:port`. Host names are sent as they are, for the proxy to resolve
+/// them, so that no DNS request leaks around it and `.onion` addresses
+/// work.
+pub(crate) fn connect(proxy: &str, host: &str, port: u16, timeout: Duration) -> Result<TcpStream> {
+    let mut error = None;
+    let mut socket = None;
+    for address in proxy.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, timeout) {
+            Ok(connected) => {
+                socket = Some(connected);
+                break;
+            }
+            Err(failure) => error = Some(failure),
+        }
+    }
+    let Some(mut socket) = socket else {
+        return Err(error.map_or(GeminiClientError::NoHostError, GeminiClientError::from));
+    };
+    socket.set_read_timeout(Some(timeout))?;
+    socket.set_write_timeout(Some(timeout))?;
+
+    socket.write_all(&[VERSION, 1, NO_AUTHENTICATION])?;
+    let mut choice = [0; 2];
+    socket.read_exact(&mut choice)?;
+    if choice != [VERSION, NO_AUTHENTICATION] {
+        return Err(GeminiClientError::ProxyError(String::from(
+            "it requires an authentication method that is not supported",
+        )));
+    }
+
+    let mut request = vec![VERSION, CONNECT, 0];
+    match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            request.push(IPV4);
+            request.extend(ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            request.push(IPV6);
+            request.extend(ip.octets());
+        }
+        Err(_) => {
+            let length = u8::try_from(host.len()).map_err(|_| GeminiClientError::NoHostError)?;
+            request.extend([DOMAIN_NAME, length]);
+            request.extend(host.as_bytes());
+        }
+    }
+    request.extend(port.to_be_bytes());
+    socket.write_all(&request)?;
+
+    let mut reply = [0; 4];
+    socket.read_exact(&mut reply)?;
+    if reply[1] != 0 {
+        return Err(GeminiClientError::ProxyError(String::from(reply_message(
+            reply[1],
+        ))));
+    }
+
+    // The address the proxy connected from is of no use, but it has to be
+    // read past
+    let length = match reply[3] {
+        IPV4 => 4,
+        IPV6 => 16,
+        DOMAIN_NAME => {
+            let mut length = [0];
+            socket.read_exact(&mut length)?;
+            length[0] as usize
+        }
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unknown address type in the proxy reply",
+            )
+            .into());
+        }
+    };
+    socket.read_exact(&mut vec![0; length + 2])?;
+
+    Ok(socket)
+}
+
+fn reply_message(code: u8) -> &'static str {
+    match code {
+        2 => "the connection is not allowed by its rules",
+        3 => "the network is unreachable",
+        4 => "the host is unreachable",
+        5 => "the host refused the connection",
+        6 => "the connection timed out",
+        7 => "the command is not supported",
+        8 => "the address type is not supported",
+        _ => "it failed to connect",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread};
+
+    use super::*;
+
+    #[test]
+    fn sends_host_names_for_the_proxy_to_resolve() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy = listener.local_addr().unwrap().to_string();
+
+        let server = thread::spawn(move || {
+            let (mut client, _) = listener.accept().unwrap();
+            let mut greeting = [0; 3];
+            client.read_exact(&mut greeting).unwrap();
+            client.write_all(&[VERSION, NO_AUTHENTICATION]).unwrap();
+
+            let host = b"example.onion";
+            let mut request = vec![0; 5 + host.len() + 2];
+            client.read_exact(&mut request).unwrap();
+            client
+                .write_all(&[VERSION, 0, 0, IPV4, 0, 0, 0, 0, 0, 0])
+                .unwrap();
+            client.write_all(b"tunnelled").unwrap();
+
+            (greeting, request)
+        });
+
+        let mut socket = connect(&proxy, "example.onion", 1965, Duration::from_secs(5)).unwrap();
+        let mut data = String::new();
+        socket.read_to_string(&mut data).unwrap();
+        let (greeting, request) = server.join().unwrap();
+
+        assert_eq!(greeting, [VERSION, 1, NO_AUTHENTICATION]);
+        assert_eq!(request[..5], [VERSION, CONNECT, 0, DOMAIN_NAME, 13]);
+        assert_eq!(&request[5..18], b"example.onion");
+        assert_eq!(request[18..], 1965u16.to_be_bytes());
+        assert_eq!(data, "tunnelled");
+    }
+}
@@ -0,0 +1,72 @@
+//! Titan, the upload counterpart of Gemini: same transport and responses,
+//! with the content sent after a request line carrying its size and MIME
+//! type as path parameters.
+//!
+//! gemini://transjovian.org/titan/page/The%20Titan%20Specification
+
+use url::Url;
+
+/// Content sent to a `titan://` URL.
+#[derive(Clone, Debug)]
+pub struct Upload {
+    pub mime: String,
+    /// Token some endpoints ask for before accepting uploads.
+    pub token: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// Request line with the parameters of the upload, followed by its content.
+/// The client identification, when there is one, goes along with the token.
+pub(crate) fn request(url: &Url, upload: &Upload, client_id: Option<&str>) -> Vec<u8> {
+    let mut parameters = format!(";size={};mime={}", upload.body.len(), upload.mime);
+    if let Some(token) = &upload.token {
+        parameters.push_str(&format!(";token={}", token));
+    }
+    if let Some(client_id) = client_id {
+        // Parameters are separated by these, they can't appear in values
+        let client_id = client_id.replace(';', "%3B").replace('=', "%3D");
+        parameters.push_str(&format!(";client={}", client_id));
+    }
+
+    let mut target = url.clone();
+    target.set_path(&format!("{}{}", url.path(), parameters));
+
+    let mut request = format!("{}\r\n", target).into_bytes();
+    request.extend_from_slice(&upload.body);
+    request
+}
+
+/// Gemini URL of a page an upload redirected to. Relative redirects resolve
+/// against the `titan://` URL, while the page is read over Gemini.
+pub(crate) fn gemini_url(url: &Url) -> Url {
+    let mut url = url.clone();
+    if url.scheme() == "titan" {
+        // Both schemes are non-special, switching between them never fails
+        let _ = url.set_scheme("gemini");
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_carries_the_parameters_and_content() {
+        let upload = Upload {
+            mime: String::from("text/gemini"),
+            token: Some(String::from("hunter2")),
+            body: b"# Hello\n".to_vec(),
+        };
+        let url = Url::parse("titan://example.org/wiki/Hello?ignored").unwrap();
+
+        assert_eq!(
+            request(&url, &upload, None),
+            b"titan://example.org/wiki/Hello;size=8;mime=text/gemini;token=hunter2?ignored\r\n# Hello\n"
+        );
+        assert!(
+            request(&url, &upload, Some("dioscuri 0.1; a=b"))
+                .starts_with(b"titan://example.org/wiki/Hello;size=8;mime=text/gemini;token=hunter2;client=dioscuri%200.1%3B%20a%3Db?")
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Trust on first use: the certificate a host presents the first time is
+//! pinned, and a different one showing up later is reported instead of
+//! silently accepted.
+//!
+//! https://geminiprotocol.net/docs/protocol-specification.gmi#tls-server-certificate-validation
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rustls::pki_types::{CertificateDer, pem::PemObject};
+use sha2::{Digest, Sha256};
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use crate::{GeminiClientError, Result};
+
+/// Details of a server certificate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Certificate {
+    /// SHA-256 of the DER encoded certificate, as colon separated hex.
+    pub fingerprint: String,
+    pub subject: String,
+    pub issuer: String,
+    /// Unix timestamps bounding the validity of the certificate.
+    pub not_before: i64,
+    pub not_after: i64,
+}
+
+impl Certificate {
+    pub fn from_der(der: &[u8]) -> Self {
+        let fingerprint = Sha256::digest(der)
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<_>>()
+            .join(":");
+
+        match X509Certificate::from_der(der) {
+            Ok((_, certificate)) => Self {
+                fingerprint,
+                subject: certificate.subject().to_string(),
+                issuer: certificate.issuer().to_string(),
+                not_before: certificate.validity().not_before.timestamp(),
+                not_after: certificate.validity().not_after.timestamp(),
+            },
+            // Pinning only needs the fingerprint, a certificate rustls
+            // accepted but x509-parser doesn't understand is still usable
+            Err(_) => Self {
+                fingerprint,
+                subject: String::new(),
+                issuer: String::new(),
+                not_before: 0,
+                not_after: 0,
+            },
+        }
+    }
+
+    /// Reads the first certificate of a PEM file, like the one a capsule is
+    /// about to be served with.
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        let der = CertificateDer::from_pem_slice(pem.as_bytes())
+            .map_err(|error| GeminiClientError::CertificateError(error.to_string()))?;
+        Ok(Self::from_der(&der))
+    }
+
+    pub fn expired(&self) -> bool {
+        self.not_after < now()
+    }
+}
+
+/// Answer to a host presenting a certificate other than the pinned one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trust {
+    /// Accepts the certificate until the client is dropped.
+    Once,
+    /// Pins the certificate in place of the previous one.
+    Permanently,
+}
+
+/// Certificates pinned per host, persisted as
+/// `{host}\t{fingerprint}\t{not_before}\t{not_after}\t{subject}\t{issuer}`
+/// lines when a file is given.
+#[derive(Default)]
+pub(crate) struct KnownHosts {
+    path: Option<PathBuf>,
+    pinned: HashMap<String, Certificate>,
+    /// Certificates accepted once, which override the pinned ones.
+    accepted: HashMap<String, Certificate>,
+}
+
+impl KnownHosts {
+    pub(crate) fn load(path: Option<PathBuf>) -> Self {
+        let pinned = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(6, '\t');
+                let host = fields.next()?.to_owned();
+                let certificate = Certificate {
+                    fingerprint: fields.next()?.to_owned(),
+                    not_before: fields.next()?.parse().ok()?,
+                    not_after: fields.next()?.parse().ok()?,
+                    subject: fields.next().unwrap_or_default().to_owned(),
+                    issuer: fields.next().unwrap_or_default().to_owned(),
+                };
+
+                Some((host, certificate))
+            })
+            .collect();
+
+        Self {
+            path,
+            pinned,
+            accepted: HashMap::new(),
+        }
+    }
+
+    /// Certificate the host is trusted with.
+    pub(crate) fn get(&self, host: &str) -> Option<&Certificate> {
+        self.accepted.get(host).or_else(|| self.pinned.get(host))
+    }
+
+    /// Whether the host is trusted with a certificate accepted once, over
+    /// the pinned one.
+    pub(crate) fn accepted_once(&self, host: &str) -> bool {
+        self.accepted.contains_key(host)
+    }
+
+    /// Checks the certificate presented by the host, pinning it when the
+    /// host is new or its pinned certificate has expired. Returns the pinned
+    /// certificate when it doesn't match.
+    pub(crate) fn verify(
+        &mut self,
+        host: &str,
+        certificate: &Certificate,
+    ) -> io::Result<Option<Certificate>> {
+        match self.get(host) {
+            Some(trusted) if trusted.fingerprint == certificate.fingerprint => Ok(None),
+            Some(trusted) if !trusted.expired() => Ok(Some(trusted.clone())),
+            _ => {
+                self.trust(host, certificate.clone(), Trust::Permanently)?;
+                Ok(None)
+            }
+        }
+    }
+
+    pub(crate) fn trust(
+        &mut self,
+        host: &str,
+        certificate: Certificate,
+        trust: Trust,
+    ) -> io::Result<()> {
+        match trust {
+            Trust::Once => {
+                self.accepted.insert(host.to_owned(), certificate);
+                Ok(())
+            }
+            Trust::Permanently => {
+                self.accepted.remove(host);
+                self.pinned.insert(host.to_owned(), certificate);
+                self.save()
+            }
+        }
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let mut hosts = self.pinned.iter().collect::<Vec<_>>();
+        hosts.sort_by_key(|(host, _)| *host);
+
+        let text = hosts
+            .into_iter()
+            .map(|(host, certificate)| {
+                format!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\n",
+                    host,
+                    certificate.fingerprint,
+                    certificate.not_before,
+                    certificate.not_after,
+                    certificate.subject.replace('\t', " "),
+                    certificate.issuer.replace('\t', " ")
+                )
+            })
+            .collect::<String>();
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64)
+}
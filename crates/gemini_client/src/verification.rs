@@ -1,11 +1,127 @@
-use std::sync::Arc;
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+    sync::{Arc, LazyLock},
+};
 
 use rustls::{
-    DigitallySignedStruct, RootCertStore, SignatureScheme,
-    client::{WebPkiServerVerifier, danger},
-    pki_types,
+    ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+    client::{
+        WebPkiServerVerifier,
+        danger::{self, ServerCertVerifier},
+    },
+    pki_types::{self, CertificateDer, ServerName, UnixTime},
 };
 
+use crate::Identity;
+
+/// How server certificates are verified, before being checked against the
+/// pinned ones.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TlsPolicy {
+    /// Regular WebPKI verification against the bundled root certificates.
+    Strict,
+    /// WebPKI verification that accepts certificates from unknown issuers,
+    /// which covers the self-signed certificates most capsules use.
+    #[default]
+    AllowUnknownIssuer,
+    /// Trust on first use alone: any certificate is accepted the first
+    /// time, even expired or for other names, and has to match after.
+    Tofu,
+    /// Any certificate, even one other than the pinned certificate, the
+    /// connection being reported as [`TlsStatus::Unverified`] then.
+    AcceptAll,
+}
+
+/// How the certificate of a host came to be trusted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsStatus {
+    /// Issued by an authority of the bundled root certificates.
+    Trusted,
+    /// Self-signed or from an unknown issuer, matching the certificate
+    /// pinned on first use.
+    Pinned,
+    /// Other than the pinned certificate, accepted once in its place.
+    AcceptedOnce,
+    /// Other than the pinned certificate, let through by
+    /// [`TlsPolicy::AcceptAll`].
+    Unverified,
+}
+
+impl FromStr for TlsPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strict" => Ok(Self::Strict),
+            "allow-unknown-issuer" => Ok(Self::AllowUnknownIssuer),
+            "tofu" => Ok(Self::Tofu),
+            "accept-all" => Ok(Self::AcceptAll),
+            _ => Err(format!("unknown TLS policy {:?}", s)),
+        }
+    }
+}
+
+impl Display for TlsPolicy {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::Strict => "strict",
+            Self::AllowUnknownIssuer => "allow-unknown-issuer",
+            Self::Tofu => "tofu",
+            Self::AcceptAll => "accept-all",
+        })
+    }
+}
+
+/// Configuration verifying servers with the policy, presenting the identity
+/// to them if one is given.
+pub fn client_config(
+    policy: TlsPolicy,
+    identity: Option<&Identity>,
+) -> Result<ClientConfig, rustls::Error> {
+    let builder = match policy {
+        TlsPolicy::Strict => ClientConfig::builder().with_root_certificates(root_store()),
+        TlsPolicy::AllowUnknownIssuer => ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(AllowUnknownIssuerVerification::new()),
+        TlsPolicy::Tofu | TlsPolicy::AcceptAll => ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AnyCertificateVerification)),
+    };
+
+    match identity {
+        Some(identity) => builder.with_client_auth_cert(identity.chain(), identity.key()),
+        None => Ok(builder.with_no_client_auth()),
+    }
+}
+
+/// Whether the chain presented by the host verifies against the bundled
+/// root certificates, rather than being accepted for its unknown issuer.
+pub(crate) fn issued_by_authority(chain: &[CertificateDer<'_>], host: &str) -> bool {
+    let (Some((end_entity, intermediates)), Ok(name)) =
+        (chain.split_first(), ServerName::try_from(host.to_owned()))
+    else {
+        return false;
+    };
+
+    WEBPKI
+        .verify_server_cert(end_entity, intermediates, &name, &[], UnixTime::now())
+        .is_ok()
+}
+
+/// WebPKI verifier against the bundled root certificates, shared by the
+/// checks made besides those of the connections.
+static WEBPKI: LazyLock<Arc<WebPkiServerVerifier>> =
+    LazyLock::new(|| WebPkiServerVerifier::builder(root_store()).build().unwrap());
+
+fn root_store() -> Arc<RootCertStore> {
+    Arc::new(RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    })
+}
+
+/// Verifier of [`TlsPolicy::AllowUnknownIssuer`], for rustls
+/// configurations built outside of the client.
 #[derive(Debug)]
 pub struct AllowUnknownIssuerVerification {
     inner: Arc<WebPkiServerVerifier>,
@@ -13,10 +129,7 @@ pub struct AllowUnknownIssuerVerification {
 
 impl AllowUnknownIssuerVerification {
     pub fn new() -> Arc<Self> {
-        let roots = Arc::new(RootCertStore {
-            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
-        });
-        let inner = WebPkiServerVerifier::builder(roots).build().unwrap();
+        let inner = WebPkiServerVerifier::builder(root_store()).build().unwrap();
         Arc::new(Self { inner })
     }
 }
@@ -68,3 +181,44 @@ impl danger::ServerCertVerifier for AllowUnknownIssuerVerification {
         self.inner.supported_verify_schemes()
     }
 }
+
+/// Verifier of [`TlsPolicy::Tofu`] and [`TlsPolicy::AcceptAll`], taking any
+/// certificate and leaving its trust to the pinned ones. The handshake is
+/// still checked to be signed by the certificate presented.
+#[derive(Debug)]
+struct AnyCertificateVerification;
+
+impl danger::ServerCertVerifier for AnyCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &pki_types::CertificateDer<'_>,
+        _intermediates: &[pki_types::CertificateDer<'_>],
+        _server_name: &pki_types::ServerName<'_>,
+        _ocsp: &[u8],
+        _now: pki_types::UnixTime,
+    ) -> Result<danger::ServerCertVerified, rustls::Error> {
+        Ok(danger::ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &pki_types::CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<danger::HandshakeSignatureValid, rustls::Error> {
+        WEBPKI.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &pki_types::CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<danger::HandshakeSignatureValid, rustls::Error> {
+        WEBPKI.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        WEBPKI.supported_verify_schemes()
+    }
+}
@@ -0,0 +1,250 @@
+//! Requests against a local `gemini_server`, covering what only shows up
+//! over a real connection: TLS, redirects and misbehaving servers.
+
+use std::{
+    env, fs, process,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use gemini_client::{
+    GeminiClient, GeminiClientError, HostSettings, SecurityError, TlsPolicy, TlsStatus,
+};
+use gemini_server::{GeminiServer, Handler, Reply, Request, Script, StaticFiles};
+use url::Url;
+
+fn serve(script: Script) -> String {
+    let address = GeminiServer::builder(script)
+        .bind("127.0.0.1:0")
+        .unwrap()
+        .spawn()
+        .unwrap();
+
+    format!("gemini://localhost:{}", address.port())
+}
+
+#[test]
+fn fetches_gemtext() {
+    let capsule = serve(Script::new().reply("/", Reply::gemtext("# Hello\n")));
+    let response = GeminiClient::new().fetch(&format!("{}/", capsule)).unwrap();
+
+    assert_eq!(response.mime(), "text/gemini");
+    assert_eq!(response.text().unwrap(), "# Hello\n");
+}
+
+#[test]
+fn follows_redirects() {
+    let capsule = serve(
+        Script::new()
+            .reply("/old", Reply::new(31, "/new"))
+            .reply("/new", Reply::gemtext("Moved\n")),
+    );
+    let response = GeminiClient::new()
+        .fetch(&format!("{}/old", capsule))
+        .unwrap();
+
+    assert_eq!(response.url.path(), "/new");
+    assert_eq!(response.redirects.len(), 1);
+    assert!(response.redirects[0].permanent);
+}
+
+#[test]
+fn measures_the_final_request() {
+    let capsule = serve(
+        Script::new()
+            .reply("/old", Reply::new(30, "/new"))
+            .reply("/new", Reply::success("text/plain", "Moved\n")),
+    );
+    let response = GeminiClient::new()
+        .fetch(&format!("{}/old", capsule))
+        .unwrap();
+    let metrics = response.metrics.unwrap();
+
+    assert_eq!(metrics.status, 20);
+    assert_eq!(metrics.size, 6);
+    assert!(metrics.handshake > Duration::ZERO);
+    assert!(metrics.total >= metrics.connect + metrics.handshake + metrics.first_byte);
+    // The test server certificate is self-signed
+    assert_eq!(metrics.tls, Some(TlsStatus::Pinned));
+}
+
+#[test]
+fn reports_error_statuses() {
+    let capsule = serve(Script::new());
+    let error = GeminiClient::new()
+        .fetch(&format!("{}/missing", capsule))
+        .err()
+        .unwrap();
+
+    assert!(matches!(error, GeminiClientError::RequestError(meta) if meta == "Not found"));
+}
+
+#[test]
+fn leaves_hosts_slowing_down_alone() {
+    let capsule = serve(
+        Script::new()
+            .reply("/busy", Reply::new(44, "60"))
+            .reply("/", Reply::gemtext("# Hello\n")),
+    );
+    let client = GeminiClient::new();
+
+    let error = client.fetch(&format!("{}/busy", capsule)).err().unwrap();
+    assert!(matches!(
+        error,
+        GeminiClientError::SlowDown { wait, .. } if wait == Duration::from_secs(60)
+    ));
+
+    // The host is not asked again until the wait is over
+    let error = client.fetch(&format!("{}/", capsule)).err().unwrap();
+    assert!(matches!(error, GeminiClientError::SlowDown { .. }));
+    assert!(client.cooldown("localhost").unwrap() > Duration::from_secs(55));
+}
+
+#[test]
+fn settings_apply_to_their_hosts() {
+    // Each server has a certificate of its own for localhost
+    let first = serve(Script::new().reply("/", Reply::gemtext("First\n")));
+    let second = serve(
+        Script::new()
+            .reply("/", Reply::gemtext("Second\n"))
+            .reply("/old", Reply::new(31, "/")),
+    );
+    let client = GeminiClient::new();
+
+    client.fetch(&format!("{}/", first)).unwrap();
+    let error = client.fetch(&format!("{}/", second)).err().unwrap();
+    assert!(matches!(
+        error,
+        GeminiClientError::CertificateChanged { .. }
+    ));
+
+    let port = second.rsplit(':').next().unwrap().parse().unwrap();
+    client.set_host_settings(
+        "localhost",
+        HostSettings {
+            tls_policy: Some(TlsPolicy::AcceptAll),
+            port: Some(port),
+            follow_redirects: false,
+            ..HostSettings::default()
+        },
+    );
+    let response = client.fetch("gemini://localhost/").unwrap();
+    assert_eq!(response.metrics.unwrap().tls, Some(TlsStatus::Unverified));
+    assert_eq!(response.text().unwrap(), "Second\n");
+    let error = client.fetch("gemini://localhost/old").err().unwrap();
+    assert!(matches!(error, GeminiClientError::Redirected(url) if url.path() == "/"));
+
+    client.set_host_settings("localhost", HostSettings::default());
+    assert_eq!(
+        client.tls_policy("localhost"),
+        TlsPolicy::AllowUnknownIssuer
+    );
+}
+
+#[test]
+fn requests_many_urls_within_the_host_limit() {
+    let (running, most) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+    let (counter, peak) = (running.clone(), most.clone());
+    let address = GeminiServer::builder(move |request: &Request| {
+        let now = counter.fetch_add(1, Ordering::SeqCst) + 1;
+        peak.fetch_max(now, Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(100));
+        counter.fetch_sub(1, Ordering::SeqCst);
+        Reply::gemtext(request.url.path())
+    })
+    .bind("127.0.0.1:0")
+    .unwrap()
+    .spawn()
+    .unwrap();
+    let capsule = format!("gemini://localhost:{}", address.port());
+
+    let mut urls = (0..6)
+        .map(|page| format!("{}/{}", capsule, page))
+        .collect::<Vec<_>>();
+    urls.insert(2, String::from("not a URL"));
+    let results = GeminiClient::builder()
+        .connections_per_host(2)
+        .build()
+        .request_many(&urls);
+
+    assert_eq!(results.len(), 7);
+    assert!(results[2].is_err());
+    let pages = results
+        .into_iter()
+        .filter_map(|result| result.ok()?.text().ok())
+        .collect::<Vec<_>>();
+    assert_eq!(pages, ["/0", "/1", "/2", "/3", "/4", "/5"]);
+    assert_eq!(most.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn refuses_hostile_headers() {
+    let capsule = serve(
+        Script::new()
+            .reply("/nul", Reply::Raw(b"20 text/gemini\0\r\nbody".to_vec()))
+            .reply(
+                "/long",
+                Reply::Raw([b"20 ".as_slice(), &[b'a'; 2048]].concat()),
+            ),
+    );
+    let client = GeminiClient::new();
+    let refused = |path| match client.fetch(&format!("{}{}", capsule, path)) {
+        Err(GeminiClientError::Security(error)) => Some(error),
+        _ => None,
+    };
+
+    assert_eq!(refused("/nul"), Some(SecurityError::NulByte));
+    assert_eq!(refused("/long"), Some(SecurityError::OversizedHeader));
+}
+
+#[test]
+fn serves_static_files() {
+    let root = env::temp_dir().join(format!("gemini_server-{}", process::id()));
+    let outside = env::temp_dir().join(format!("gemini_server-{}-outside", process::id()));
+    fs::create_dir_all(root.join("log")).unwrap();
+    fs::create_dir_all(&outside).unwrap();
+    fs::write(outside.join("secret.gmi"), "# Secret\n").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&outside, root.join("elsewhere")).unwrap();
+    fs::write(root.join("index.gmi"), "# Capsule\n=> log/\n").unwrap();
+    fs::write(root.join("log/first.gmi"), "# First post\n").unwrap();
+    fs::write(root.join("log/.draft.gmi"), "# Draft\n").unwrap();
+
+    let address = GeminiServer::builder(StaticFiles::new(&root))
+        .bind("127.0.0.1:0")
+        .unwrap()
+        .spawn()
+        .unwrap();
+    let capsule = format!("gemini://localhost:{}", address.port());
+    let client = GeminiClient::new();
+
+    let index = client.fetch(&format!("{}/", capsule)).unwrap();
+    let listing = client.fetch(&format!("{}/log", capsule)).unwrap();
+    let post = client.fetch(&format!("{}/log/first.gmi", capsule)).unwrap();
+    let hidden = client.fetch(&format!("{}/log/.draft.gmi", capsule));
+    let linked = client.fetch(&format!("{}/elsewhere/secret.gmi", capsule));
+    // The client would resolve the dots before sending the request
+    let escaping = StaticFiles::new(&root).handle(&Request {
+        url: Url::parse(&format!(
+            "gemini://localhost/log/%2e%2e%2f%2e%2e%2f{}/secret.gmi",
+            outside.file_name().unwrap().to_str().unwrap()
+        ))
+        .unwrap(),
+        peer: address,
+    });
+    fs::remove_dir_all(&root).unwrap();
+    fs::remove_dir_all(&outside).unwrap();
+
+    assert_eq!(index.text().unwrap(), "# Capsule\n=> log/\n");
+    assert_eq!(listing.url.path(), "/log/");
+    assert!(listing.text().unwrap().contains("=> first.gmi first.gmi"));
+    assert_eq!(post.text().unwrap(), "# First post\n");
+    assert!(hidden.is_err());
+    #[cfg(unix)]
+    assert!(matches!(linked, Err(GeminiClientError::RequestError(meta)) if meta == "Not found"));
+    assert!(matches!(escaping, Reply::Response { status: 51, .. }));
+}
@@ -0,0 +1,71 @@
+//! Crawls of a local `gemini_server`, checking which links are followed
+//! and which URLs robots.txt disallows.
+
+use std::{collections::HashSet, time::Duration};
+
+use gemini_client::{GeminiClient, GeminiClientError, RobotsPolicy, crawl::Crawler};
+use gemini_server::{GeminiServer, Reply, Script};
+
+#[test]
+fn follows_links_robots_txt_allows() {
+    let script = Script::new()
+        .reply(
+            "/",
+            Reply::gemtext(
+                "# Home\n=> /a A\n=> /private/b B\n=> gemini://elsewhere.example/ Away\n",
+            ),
+        )
+        .reply("/a", Reply::gemtext("=> / Home\n=> /a/deeper Deeper\n"))
+        .reply("/a/deeper", Reply::gemtext("=> /too-deep Too deep\n"))
+        .reply(
+            "/robots.txt",
+            Reply::success("text/plain", "User-agent: indexer\nDisallow: /private\n"),
+        );
+    let address = GeminiServer::builder(script)
+        .bind("127.0.0.1:0")
+        .unwrap()
+        .spawn()
+        .unwrap();
+    let capsule = format!("gemini://localhost:{}", address.port());
+
+    let paths = Crawler::new(GeminiClient::new())
+        .depth(2)
+        .delay(Duration::ZERO)
+        .crawl([format!("{}/", capsule)])
+        .map(|page| {
+            assert!(page.response.is_ok());
+            (page.url.path().to_owned(), page.depth)
+        })
+        .collect::<HashSet<_>>();
+
+    assert_eq!(
+        paths,
+        HashSet::from([
+            (String::from("/"), 0),
+            (String::from("/a"), 1),
+            (String::from("/a/deeper"), 2),
+        ])
+    );
+}
+
+#[test]
+fn robots_policy_refuses_disallowed_urls() {
+    let script = Script::new().reply(
+        "/robots.txt",
+        Reply::success("text/plain", "User-agent: archiver\nDisallow: /\n"),
+    );
+    let address = GeminiServer::builder(script)
+        .bind("127.0.0.1:0")
+        .unwrap()
+        .spawn()
+        .unwrap();
+    let page = format!("gemini://localhost:{}/page", address.port());
+
+    let indexer = RobotsPolicy::new(GeminiClient::new());
+    assert!(indexer.check(&page).is_ok());
+    let archiver = RobotsPolicy::new(GeminiClient::new()).user_agent("archiver");
+    assert!(matches!(
+        archiver.check(&page),
+        Err(GeminiClientError::Disallowed(url)) if url == page
+    ));
+}
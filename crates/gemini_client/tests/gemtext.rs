@@ -0,0 +1,182 @@
+//! Parser output for the pages in `tests/fixtures`, a corpus of anonymized
+//! real-world gemtext. Each test pins down how the odd parts of a page are
+//! parsed, so that changes to the parser show up here first.
+
+use gemini_client::gemtext::{Document, Line};
+
+fn parse(name: &str) -> Document {
+    let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+    let text = std::fs::read_to_string(&path).unwrap_or_else(|error| panic!("{}: {}", path, error));
+
+    Document::parse(&text)
+}
+
+fn text(text: &str) -> Line {
+    Line::Text(text.to_owned())
+}
+
+fn heading(level: u8, text: &str) -> Line {
+    Line::Heading {
+        level,
+        text: text.to_owned(),
+    }
+}
+
+fn link(url: &str, label: Option<&str>) -> Line {
+    Line::Link {
+        url: url.to_owned(),
+        label: label.map(str::to_owned),
+    }
+}
+
+fn quote(text: &str) -> Line {
+    Line::Quote(text.to_owned())
+}
+
+fn preformatted(text: &str) -> Line {
+    Line::Preformatted(text.to_owned())
+}
+
+#[test]
+fn huge_link_list() {
+    let document = parse("links.gmi");
+
+    assert_eq!(document.lines.len(), 258);
+    assert_eq!(document.title(), Some("Aggregator"));
+    assert_eq!(document.links().count(), 242);
+    assert_eq!(
+        document
+            .links()
+            .filter(|(_, label)| label.is_none())
+            .count(),
+        7
+    );
+
+    // Tabs and runs of spaces separate the URL from the label just like a
+    // single space does, and never end up in either
+    assert!(document.links().all(|(url, label)| {
+        !url.contains(char::is_whitespace)
+            && label.is_none_or(|label| label.trim() == label && !label.contains('\t'))
+    }));
+
+    assert_eq!(
+        document.lines[5],
+        link(
+            "gemini://tilde.example.org/~user0/reading-cameras-on.gmi",
+            Some("2024-03-14 user0 - reading cameras on")
+        )
+    );
+    assert_eq!(
+        document.lines[256],
+        link("/submit", Some("Submit your feed"))
+    );
+
+    let headings = document
+        .lines
+        .iter()
+        .filter(|line| matches!(line, Line::Heading { .. }))
+        .count();
+    assert_eq!(headings, 7);
+}
+
+#[test]
+fn nested_quotes() {
+    let document = parse("quotes.gmi");
+
+    assert_eq!(
+        document.lines,
+        [
+            heading(1, "Re: Re: On replying in gemlogs"),
+            text(""),
+            quote("I don't think threads work on a medium without comments."),
+            // Only the first marker is a quote marker, the nesting stays in
+            // the text
+            quote("> But you can always reply on your own capsule."),
+            quote(">> And that is exactly the kind of friction that keeps things calm."),
+            quote("> Some people prefer spaces between the markers."),
+            quote(""),
+            text(""),
+            text("Quoting a quote is fine with me:"),
+            text(""),
+            quote("indented quote with leading spaces"),
+            quote("* not a list item inside a quote"),
+            quote("=> not a link either"),
+            quote("# nor a heading"),
+            text(""),
+            quote("Last line of the quote, no trailing newline"),
+        ]
+    );
+}
+
+#[test]
+fn mixed_width_scripts() {
+    let document = parse("scripts.gmi");
+
+    assert_eq!(document.lines.len(), 28);
+    assert_eq!(document.title(), Some("多言語のテスト · Multilingual test"));
+
+    // Text is kept as written, without normalizing combining marks or
+    // splitting joined emoji
+    assert_eq!(
+        document.lines[15],
+        Line::ListItem(String::from("e\u{301} (e + combining acute) vs é"))
+    );
+    assert_eq!(
+        document.lines[16],
+        Line::ListItem(String::from(
+            "👩\u{200d}👩\u{200d}👧 family with zero-width joiners"
+        ))
+    );
+    assert_eq!(
+        document.lines[18],
+        link(
+            "gemini://例え.example/ページ",
+            Some("国際化ドメインへのリンク")
+        )
+    );
+    assert_eq!(
+        document.lines[23..],
+        [
+            Line::PreformatToggle {
+                alt: Some(String::from("ascii art"))
+            },
+            preformatted("┌──────┐"),
+            preformatted("│ 箱 □ │"),
+            preformatted("└──────┘"),
+            Line::PreformatToggle { alt: None },
+        ]
+    );
+}
+
+#[test]
+fn broken_syntax() {
+    let document = parse("broken.gmi");
+
+    assert_eq!(
+        document.lines,
+        [
+            heading(1, "Broken syntax"),
+            // CRLF line endings leave nothing behind
+            text(""),
+            text("This file uses CRLF line endings."),
+            heading(1, "No space after the marker"),
+            // Headings have three levels at most, extra markers are text
+            heading(3, "# Four hashes"),
+            text("*No space after the list marker"),
+            Line::ListItem(String::new()),
+            // Links without a URL are plain text
+            text("=>"),
+            text("=>    "),
+            link("no-space-link", None),
+            link("gemini://tabs.example/", Some("Tab separated")),
+            text("   => indented, not a link"),
+            // An unclosed preformatted block runs to the end of the page
+            Line::PreformatToggle { alt: None },
+            preformatted("unclosed preformatted block"),
+            preformatted("=> gemini://inside.example/ not a link"),
+            preformatted("# not a heading"),
+            preformatted(""),
+            preformatted("  trailing spaces and a tab\t"),
+        ]
+    );
+}
@@ -0,0 +1,109 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use percent_encoding::percent_decode_str;
+
+use crate::{Handler, Reply, Request};
+
+const INDEX_FILE: &str = "index.gmi";
+
+/// Files of a directory, served at their path under it. Directories serve
+/// their `index.gmi`, or a listing of their entries without one.
+#[derive(Clone, Debug)]
+pub struct StaticFiles {
+    root: PathBuf,
+}
+
+impl StaticFiles {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl Handler for StaticFiles {
+    fn handle(&self, request: &Request) -> Reply {
+        let Ok(root) = self.root.canonicalize() else {
+            return Reply::new(40, "The capsule directory is missing");
+        };
+
+        let path = request.url.path();
+        let relative = percent_decode_str(path).decode_utf8_lossy();
+        // Hidden files are no more served than they are listed
+        if relative.split('/').any(|segment| segment.starts_with('.')) {
+            return Reply::not_found();
+        }
+        // Symbolic links may point anywhere, only what ends up under the
+        // root is served
+        let Some(target) = root
+            .join(relative.trim_start_matches('/'))
+            .canonicalize()
+            .ok()
+            .filter(|target| target.starts_with(&root))
+        else {
+            return Reply::not_found();
+        };
+
+        if !target.is_dir() {
+            return match fs::read(&target) {
+                Ok(body) => Reply::success(mime_type(&target), body),
+                Err(_) => Reply::not_found(),
+            };
+        }
+
+        // Links in the index resolve against the directory only with the
+        // trailing slash
+        if !path.ends_with('/') {
+            let mut url = request.url.clone();
+            url.set_path(&format!("{}/", path));
+            return Reply::new(31, url.as_str());
+        }
+
+        match fs::read(target.join(INDEX_FILE)) {
+            Ok(body) => Reply::success("text/gemini", body),
+            Err(_) => Reply::gemtext(listing(&target, path)),
+        }
+    }
+}
+
+fn listing(dir: &Path, path: &str) -> String {
+    let mut names = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+            let dir = entry.file_type().ok()?.is_dir();
+
+            (!name.starts_with('.')).then(|| if dir { format!("{}/", name) } else { name })
+        })
+        .collect::<Vec<_>>();
+    names.sort();
+
+    let mut page = format!("# Index of {}\n\n", path);
+    for name in names {
+        page.push_str(&format!(
+            "=> {} {}\n",
+            percent_encoding::utf8_percent_encode(&name, percent_encoding::CONTROLS)
+                .to_string()
+                .replace(' ', "%20"),
+            name
+        ));
+    }
+    page
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("gmi" | "gemini") => "text/gemini",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("md") => "text/markdown; charset=utf-8",
+        Some("xml" | "atom") => "application/atom+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("gpub") => "application/gpub+zip",
+        _ => "application/octet-stream",
+    }
+}
@@ -0,0 +1,275 @@
+//! Small Gemini server answering from a directory of gemtext or from
+//! scripted replies, to test clients against real connections and to host
+//! a capsule.
+//!
+//! https://geminiprotocol.net/docs/protocol-specification.gmi
+
+mod files;
+mod script;
+
+pub use files::StaticFiles;
+pub use script::Script;
+
+use std::{
+    io::{self, BufRead, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use rcgen::{CertificateParams, KeyPair};
+use rustls::{
+    ServerConfig, ServerConnection, StreamOwned,
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+};
+use thiserror::Error;
+use url::Url;
+
+pub const DEFAULT_PORT: u16 = 1965;
+/// Longest request line: a URL of at most 1024 bytes and CRLF.
+const MAX_REQUEST_LENGTH: usize = 1024 + 2;
+/// How long a client may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum GeminiServerError {
+    #[error("Could not generate the certificate: {0}")]
+    CertificateError(#[from] rcgen::Error),
+    #[error("Could not read the certificate: {0}")]
+    PemError(#[from] rustls::pki_types::pem::Error),
+    #[error("Could not create the server configuration: {0}")]
+    RustlsError(#[from] rustls::Error),
+    #[error("Could not open the listener: {0}")]
+    IoError(#[from] io::Error),
+}
+
+type Result<T, E = GeminiServerError> = core::result::Result<T, E>;
+
+pub struct Request {
+    pub url: Url,
+    pub peer: SocketAddr,
+}
+
+#[derive(Clone, Debug)]
+pub enum Reply {
+    Response {
+        status: u8,
+        meta: String,
+        body: Vec<u8>,
+    },
+    /// Bytes sent as they are, header included, to misbehave on purpose.
+    Raw(Vec<u8>),
+}
+
+impl Reply {
+    /// Reply without a body, for statuses other than 20.
+    pub fn new(status: u8, meta: impl Into<String>) -> Self {
+        Self::Response {
+            status,
+            meta: meta.into(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn success(mime: impl Into<String>, body: impl Into<Vec<u8>>) -> Self {
+        Self::Response {
+            status: 20,
+            meta: mime.into(),
+            body: body.into(),
+        }
+    }
+
+    pub fn gemtext(text: impl Into<String>) -> Self {
+        Self::success("text/gemini", text.into())
+    }
+
+    pub fn not_found() -> Self {
+        Self::new(51, "Not found")
+    }
+
+    fn status(&self) -> Option<u8> {
+        match self {
+            Self::Response { status, .. } => Some(*status),
+            Self::Raw(_) => None,
+        }
+    }
+}
+
+/// Answers the requests, each one from the thread of its connection.
+pub trait Handler: Send + Sync {
+    fn handle(&self, request: &Request) -> Reply;
+}
+
+impl<F: Fn(&Request) -> Reply + Send + Sync> Handler for F {
+    fn handle(&self, request: &Request) -> Reply {
+        self(request)
+    }
+}
+
+pub struct GeminiServerBuilder {
+    handler: Arc<dyn Handler>,
+    hostnames: Vec<String>,
+    certificate: Option<String>,
+    log: bool,
+}
+
+impl GeminiServerBuilder {
+    /// Names the generated certificate is valid for, `localhost` and its
+    /// addresses by default.
+    pub fn hostnames(mut self, hostnames: &[&str]) -> Self {
+        self.hostnames = hostnames.iter().map(|&name| name.to_owned()).collect();
+        self
+    }
+
+    /// Certificate and private key, PEM encoded, presented instead of a
+    /// generated one. Capsules need to keep theirs for clients pinning it.
+    pub fn certificate(mut self, pem: impl Into<String>) -> Self {
+        self.certificate = Some(pem.into());
+        self
+    }
+
+    /// Whether every request is logged on the standard error, as
+    /// `{peer} {status} {url}` lines.
+    pub fn log(mut self, log: bool) -> Self {
+        self.log = log;
+        self
+    }
+
+    pub fn bind(self, address: impl ToSocketAddrs) -> Result<GeminiServer> {
+        let pem = match self.certificate {
+            Some(pem) => pem,
+            None => {
+                let key = KeyPair::generate()?;
+                let certificate = CertificateParams::new(self.hostnames)?.self_signed(&key)?;
+                format!("{}{}", certificate.pem(), key.serialize_pem())
+            }
+        };
+
+        let certificates =
+            CertificateDer::pem_slice_iter(pem.as_bytes()).collect::<Result<Vec<_>, _>>()?;
+        let key = PrivateKeyDer::from_pem_slice(pem.as_bytes())?;
+        let config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certificates, key)?;
+
+        Ok(GeminiServer {
+            listener: TcpListener::bind(address)?,
+            config: Arc::new(config),
+            handler: self.handler,
+            log: self.log,
+        })
+    }
+}
+
+pub struct GeminiServer {
+    listener: TcpListener,
+    config: Arc<ServerConfig>,
+    handler: Arc<dyn Handler>,
+    log: bool,
+}
+
+impl GeminiServer {
+    pub fn builder(handler: impl Handler + 'static) -> GeminiServerBuilder {
+        GeminiServerBuilder {
+            handler: Arc::new(handler),
+            hostnames: vec![
+                String::from("localhost"),
+                String::from("127.0.0.1"),
+                String::from("::1"),
+            ],
+            certificate: None,
+            log: false,
+        }
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Answers connections until the listener fails, each one in a thread
+    /// of its own.
+    pub fn serve(self) -> io::Result<()> {
+        loop {
+            let (socket, peer) = self.listener.accept()?;
+            let config = self.config.clone();
+            let handler = self.handler.clone();
+            let log = self.log;
+
+            thread::spawn(move || {
+                let _ = respond(config, handler.as_ref(), socket, peer, log);
+            });
+        }
+    }
+
+    /// Serves from a background thread, for tests, returning the address
+    /// it listens on.
+    pub fn spawn(self) -> io::Result<SocketAddr> {
+        let address = self.local_addr()?;
+        thread::spawn(move || self.serve());
+        Ok(address)
+    }
+}
+
+fn respond(
+    config: Arc<ServerConfig>,
+    handler: &dyn Handler,
+    socket: TcpStream,
+    peer: SocketAddr,
+    log: bool,
+) -> io::Result<()> {
+    socket.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let connection = ServerConnection::new(config).map_err(io::Error::other)?;
+    let mut stream = StreamOwned::new(connection, socket);
+
+    let mut line = Vec::new();
+    (&mut stream)
+        .take(MAX_REQUEST_LENGTH as u64)
+        .read_until(b'\n', &mut line)?;
+
+    let (reply, url) = match parse_request(&line) {
+        Ok(url) => {
+            let reply = handler.handle(&Request {
+                url: url.clone(),
+                peer,
+            });
+            (reply, url.to_string())
+        }
+        Err(reply) => (reply, String::from_utf8_lossy(&line).trim_end().to_owned()),
+    };
+
+    if log {
+        let status = reply
+            .status()
+            .map_or_else(|| String::from("-"), |status| status.to_string());
+        eprintln!("{} {} {}", peer, status, url);
+    }
+
+    match reply {
+        Reply::Response { status, meta, body } => {
+            stream.write_all(format!("{} {}\r\n", status, meta).as_bytes())?;
+            stream.write_all(&body)?;
+        }
+        Reply::Raw(bytes) => stream.write_all(&bytes)?,
+    }
+
+    stream.conn.send_close_notify();
+    stream.flush()
+}
+
+/// URL of the request line, or the reply refusing it.
+///
+/// https://geminiprotocol.net/docs/protocol-specification.gmi#requests
+fn parse_request(line: &[u8]) -> Result<Url, Reply> {
+    let bad_request = || Reply::new(59, "Bad request");
+
+    let line = line.strip_suffix(b"\r\n").ok_or_else(bad_request)?;
+    let line = str::from_utf8(line).map_err(|_| bad_request())?;
+    let url = Url::parse(line).map_err(|_| bad_request())?;
+
+    if url.scheme() != "gemini" {
+        return Err(Reply::new(53, "Proxy request refused"));
+    }
+
+    Ok(url)
+}
@@ -0,0 +1,87 @@
+use std::{env, fs, process::ExitCode};
+
+use gemini_server::{DEFAULT_PORT, GeminiServer, StaticFiles};
+
+const USAGE: &str = "\
+Usage: gemini_server [OPTIONS] DIR
+
+Serves the gemtext files of DIR, and the listing of directories without an
+index.gmi.
+
+Options:
+      --bind <ADDRESS>       Address to listen on [default: [::]:1965]
+      --certificate <FILE>   PEM file with the certificate and its private key,
+                             otherwise one is generated for --hostname
+      --hostname <NAME>      Name the generated certificate is for
+                             [default: localhost]
+  -h, --help                 Print this help and exit";
+
+fn main() -> ExitCode {
+    let mut dir = None;
+    let mut bind = format!("[::]:{}", DEFAULT_PORT);
+    let mut certificate = None;
+    let mut hostname = String::from("localhost");
+
+    let mut arguments = env::args().skip(1);
+    while let Some(argument) = arguments.next() {
+        let mut value = |option: &str| {
+            arguments
+                .next()
+                .ok_or_else(|| format!("{} requires a value", option))
+        };
+
+        let parsed = match argument.as_str() {
+            "--bind" => value("--bind").map(|value| bind = value),
+            "--certificate" => value("--certificate").map(|value| certificate = Some(value)),
+            "--hostname" => value("--hostname").map(|value| hostname = value),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            option if option.starts_with("--") => Err(format!("Unknown option {}", option)),
+            path => {
+                dir = Some(path.to_owned());
+                Ok(())
+            }
+        };
+
+        if let Err(error) = parsed {
+            eprintln!("{}\n\n{}", error, USAGE);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let Some(dir) = dir else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+
+    let mut builder = GeminiServer::builder(StaticFiles::new(dir))
+        .hostnames(&[&hostname])
+        .log(true);
+    if let Some(path) = certificate {
+        match fs::read_to_string(&path) {
+            Ok(pem) => builder = builder.certificate(pem),
+            Err(error) => {
+                eprintln!("Could not read {}: {}", path, error);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let served = builder
+        .bind(&bind)
+        .map_err(|error| error.to_string())
+        .and_then(|server| {
+            eprintln!("Listening on {}", bind);
+            server.serve().map_err(|error| error.to_string())
+        });
+
+    match served {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
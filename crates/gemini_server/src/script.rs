@@ -0,0 +1,29 @@
+use std::collections::HashMap;
+
+use crate::{Handler, Reply, Request};
+
+/// Canned replies by request path, for tests. Other paths are not found.
+#[derive(Clone, Debug, Default)]
+pub struct Script {
+    replies: HashMap<String, Reply>,
+}
+
+impl Script {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reply(mut self, path: &str, reply: Reply) -> Self {
+        self.replies.insert(path.to_owned(), reply);
+        self
+    }
+}
+
+impl Handler for Script {
+    fn handle(&self, request: &Request) -> Reply {
+        self.replies
+            .get(request.url.path())
+            .cloned()
+            .unwrap_or_else(Reply::not_found)
+    }
+}
@@ -0,0 +1,151 @@
+//! Client for the Gopher protocol, the plain TCP ancestor of Gemini.
+//!
+//! https://www.rfc-editor.org/rfc/rfc1436
+
+pub mod menu;
+
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use percent_encoding::percent_decode_str;
+use thiserror::Error;
+use url::Url;
+
+/// MIME type menus are served with, so that they are told apart from plain
+/// text. Gopher itself has no MIME types, only item types.
+pub const MENU_MIME: &str = "text/x-gopher-menu";
+
+const DEFAULT_PORT: u16 = 70;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Error, Debug)]
+pub enum GopherClientError {
+    #[error("URL does not contain a host")]
+    NoHostError,
+    #[error("URL scheme of {0} is not supported")]
+    UnsupportedSchemeError(String),
+    #[error("Item type {0} is not supported")]
+    UnsupportedItemError(char),
+    #[error("Could not open the TCP connection: {0}")]
+    IoError(#[from] io::Error),
+    #[error("Url could not be parsed: {0}")]
+    UrlParseError(#[from] url::ParseError),
+}
+
+type Result<T, E = GopherClientError> = core::result::Result<T, E>;
+
+pub struct Response {
+    pub url: Url,
+    /// MIME type matching the item type of the URL.
+    pub mime: String,
+    pub body: Vec<u8>,
+}
+
+/// Client performing Gopher requests, each on its own connection.
+#[derive(Clone)]
+pub struct GopherClient {
+    timeout: Duration,
+}
+
+impl Default for GopherClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GopherClient {
+    pub fn new() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// How long connecting, or waiting on the server while it is silent,
+    /// may take before giving up.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Requests the item a `gopher://` URL points to. The query of search
+    /// items (type 7) is sent as the search terms.
+    ///
+    /// https://www.rfc-editor.org/rfc/rfc4266
+    pub fn fetch(&self, url: &str) -> Result<Response> {
+        let mut url = Url::parse(url)?;
+        url.set_fragment(None);
+        if url.scheme() != "gopher" {
+            return Err(GopherClientError::UnsupportedSchemeError(url.to_string()));
+        }
+        let host = url.host_str().ok_or(GopherClientError::NoHostError)?;
+        let port = url.port().unwrap_or(DEFAULT_PORT);
+
+        // The first character of the path is the item type, menus when
+        // there is none
+        let path = percent_decode_str(url.path()).decode_utf8_lossy();
+        let mut chars = path.trim_start_matches('/').chars();
+        let kind = chars.next().unwrap_or('1');
+        let mut selector = chars.as_str().to_owned();
+
+        if kind == '7'
+            && let Some(query) = url.query()
+        {
+            selector.push('\t');
+            selector.push_str(&percent_decode_str(query).decode_utf8_lossy());
+        }
+
+        let mime = mime(kind).ok_or(GopherClientError::UnsupportedItemError(kind))?;
+
+        let mut stream = self.connect(host, port)?;
+        stream.write_all(format!("{}\r\n", selector).as_bytes())?;
+
+        let mut body = Vec::new();
+        stream.read_to_end(&mut body)?;
+
+        // Text ends with a line holding a single dot, which is not part of it
+        if kind == '0' && body.ends_with(b"\r\n.\r\n") {
+            body.truncate(body.len() - 3);
+        }
+
+        Ok(Response {
+            url,
+            mime: mime.to_owned(),
+            body,
+        })
+    }
+
+    fn connect(&self, host: &str, port: u16) -> Result<TcpStream> {
+        let mut error = None;
+        for address in (host, port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, self.timeout) {
+                Ok(socket) => {
+                    socket.set_read_timeout(Some(self.timeout))?;
+                    socket.set_write_timeout(Some(self.timeout))?;
+                    return Ok(socket);
+                }
+                Err(failure) => error = Some(failure),
+            }
+        }
+
+        Err(error.map_or(GopherClientError::NoHostError, Into::into))
+    }
+}
+
+/// MIME type served for an item type, for the types that can be fetched.
+fn mime(kind: char) -> Option<&'static str> {
+    Some(match kind {
+        '0' => "text/plain",
+        '1' | '7' => MENU_MIME,
+        'h' => "text/html",
+        'g' => "image/gif",
+        'I' => "image/jpeg",
+        'p' => "image/png",
+        's' => "audio/basic",
+        'd' => "application/pdf",
+        '4' | '5' | '6' | '9' => "application/octet-stream",
+        _ => return None,
+    })
+}
@@ -0,0 +1,103 @@
+//! Gopher menus, read into the same document model as gemtext so that they
+//! render as link lists.
+//!
+//! https://www.rfc-editor.org/rfc/rfc1436#section-3.8
+
+use gemini_client::gemtext::{Document, Line};
+use url::Url;
+
+/// Reads a menu, one `{type}{display}\t{selector}\t{host}\t{port}` item per
+/// line. Informational and error items become text, every other item a link
+/// to its `gopher://` URL.
+pub fn parse(text: &str) -> Document {
+    let lines = text
+        .lines()
+        .take_while(|line| *line != ".")
+        .map(parse_item)
+        .collect();
+
+    Document { lines }
+}
+
+fn parse_item(line: &str) -> Line {
+    let mut chars = line.chars();
+    let Some(kind) = chars.next() else {
+        return Line::Text(String::new());
+    };
+
+    let mut fields = chars.as_str().split('\t');
+    let display = fields.next().unwrap_or_default().to_owned();
+    let selector = fields.next().unwrap_or_default();
+    let host = fields.next().unwrap_or_default();
+    let port = fields.next().unwrap_or_default().trim();
+
+    if matches!(kind, 'i' | '3') || host.is_empty() {
+        return Line::Text(display);
+    }
+
+    let url = match kind {
+        // Links to other protocols, by the `URL:` selector convention
+        'h' if selector.starts_with("URL:") => Some(selector[4..].to_owned()),
+        '8' | 'T' => Some(format!("telnet://{}:{}", host, port)),
+        _ => item_url(kind, selector, host, port),
+    };
+
+    match url {
+        Some(url) => Line::Link {
+            url,
+            label: (!display.is_empty()).then_some(display),
+        },
+        None => Line::Text(display),
+    }
+}
+
+fn item_url(kind: char, selector: &str, host: &str, port: &str) -> Option<String> {
+    let mut url = Url::parse(&format!("gopher://{}/", host)).ok()?;
+    if port != "70" {
+        url.set_port(Some(port.parse().ok()?)).ok()?;
+    }
+    url.set_path(&format!("{}{}", kind, selector));
+
+    Some(url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items_become_links_and_text() {
+        let menu = "iWelcome\tfake\t(NULL)\t0\r\n\
+                    1Phlog\t/phlog\texample.org\t70\r\n\
+                    0About me\t/about.txt\texample.org\t7070\r\n\
+                    7Search\t/search\texample.org\t70\r\n\
+                    hWeb\tURL:https://example.org/\texample.org\t70\r\n\
+                    3Oops\t\terror.host\t1\r\n\
+                    .\r\n\
+                    iAfter the end\t\t\t\r\n";
+
+        assert_eq!(
+            parse(menu).lines,
+            [
+                Line::Text(String::from("Welcome")),
+                Line::Link {
+                    url: String::from("gopher://example.org/1/phlog"),
+                    label: Some(String::from("Phlog")),
+                },
+                Line::Link {
+                    url: String::from("gopher://example.org:7070/0/about.txt"),
+                    label: Some(String::from("About me")),
+                },
+                Line::Link {
+                    url: String::from("gopher://example.org/7/search"),
+                    label: Some(String::from("Search")),
+                },
+                Line::Link {
+                    url: String::from("https://example.org/"),
+                    label: Some(String::from("Web")),
+                },
+                Line::Text(String::from("Oops")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use color_eyre::Result;
+
+use crate::{dirs, navigation::encode};
+
+const BOOKMARKS_FILE: &str = "bookmarks";
+
+pub struct Bookmark {
+    pub url: String,
+    pub title: String,
+}
+
+/// Bookmarked pages, persisted in the data directory as `{url}\t{title}`
+/// lines.
+#[derive(Default)]
+pub struct Bookmarks {
+    entries: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    pub fn load() -> Self {
+        let entries = dirs::read_data(BOOKMARKS_FILE)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let (url, title) = line.split_once('\t').unwrap_or((line, ""));
+                (!url.is_empty()).then(|| Bookmark {
+                    url: url.to_owned(),
+                    title: title.to_owned(),
+                })
+            })
+            .collect();
+
+        Self { entries }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Bookmark> {
+        self.entries.iter()
+    }
+
+    pub fn contains(&self, url: &str) -> bool {
+        self.entries.iter().any(|entry| entry.url == url)
+    }
+
+    /// Bookmarks the URL, or removes its bookmark if it was already
+    /// bookmarked.
+    pub fn toggle(&mut self, url: &str, title: &str) -> Result<()> {
+        if self.contains(url) {
+            self.entries.retain(|entry| entry.url != url);
+        } else {
+            self.entries.push(Bookmark {
+                url: url.to_owned(),
+                title: title.to_owned(),
+            });
+        }
+
+        self.save()
+    }
+
+    /// Points the bookmark to a URL it moved to permanently.
+    pub fn replace(&mut self, from: &str, to: &str) -> Result<()> {
+        for entry in self.entries.iter_mut().filter(|entry| entry.url == from) {
+            entry.url = to.to_owned();
+        }
+
+        self.save()
+    }
+
+    pub fn remove(&mut self, url: &str) -> Result<()> {
+        self.entries.retain(|entry| entry.url != url);
+        self.save()
+    }
+
+    /// Gemtext page listing the bookmarks, with links to remove them.
+    pub fn to_gemtext(&self) -> String {
+        let mut page = String::from("# Bookmarks\n\n");
+
+        if self.entries.is_empty() {
+            page.push_str("No bookmarks yet. Bookmark a page with the bookmark key.\n");
+        }
+
+        for entry in &self.entries {
+            let title = if entry.title.is_empty() {
+                &entry.url
+            } else {
+                &entry.title
+            };
+            page.push_str(&format!(
+                "=> {} {}\n=> about:bookmarks?delete={} Remove\n\n",
+                entry.url,
+                title,
+                encode(&entry.url)
+            ));
+        }
+
+        page
+    }
+
+    fn save(&self) -> Result<()> {
+        let text = self
+            .entries
+            .iter()
+            .map(|entry| format!("{}\t{}\n", entry.url, entry.title))
+            .collect::<String>();
+
+        dirs::write_data(BOOKMARKS_FILE, &text)
+    }
+}
@@ -0,0 +1,392 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    path::PathBuf,
+    time::Duration,
+};
+
+use chrono::{DateTime, Local};
+use color_eyre::Result;
+use gemini_client::{Response, normalize};
+use url::Url;
+
+use crate::dirs;
+
+const INDEX_FILE: &str = "index";
+const ALIASES_FILE: &str = "aliases";
+/// Most recently used responses also kept in memory.
+const MEMORY_ENTRIES: usize = 16;
+
+/// Which cached responses a page load may use instead of the network.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Lookup {
+    /// Responses stored less than the time to live ago.
+    Fresh,
+    /// Any response, however old, as when going through the history.
+    Any,
+    /// None, the page always comes from the network.
+    Bypass,
+}
+
+struct Entry {
+    url: String,
+    /// Name of the file holding the response in the cache directory.
+    key: String,
+    size: u64,
+    /// Unix timestamp of the time the response was fetched.
+    stored: i64,
+    /// Unix timestamp of the last time the entry was stored or read.
+    last_used: i64,
+}
+
+struct Hot {
+    url: String,
+    meta: String,
+    body: Vec<u8>,
+}
+
+/// Responses kept on disk so pages stay readable offline.
+///
+/// The cache holds at most `budget` bytes: when storing a response pushes it
+/// over, the least recently used entries are evicted first, except for
+/// pinned pages (bookmarks and subscriptions) which are only dropped when
+/// the cache is cleared. The index of entries lives in the cache directory
+/// as `{key}\t{size}\t{stored}\t{last_used}\t{url}` lines, each response in
+/// its own file made of the meta line followed by the body. The most recently
+/// used responses are kept in memory as well, so that going back and forth
+/// between pages does not read them from disk every time.
+///
+/// URLs are looked up normalized, like they are requested. Those answered by
+/// a redirect are aliases of the entry of its target, listed in the cache
+/// URL as it is requested, so that its variants share an entry.
+fn normalized(url: &str) -> String {
+    Url::parse(url).map_or_else(|_| url.to_owned(), |url| normalize(&url).into())
+}
+
+/// directory as `{url}\t{target}` lines.
+pub struct Cache {
+    dir: Option<PathBuf>,
+    budget: u64,
+    /// How long a response is served from the cache for a fresh lookup.
+    ttl: Duration,
+    entries: Vec<Entry>,
+    /// Target of the redirects answering the URLs.
+    aliases: HashMap<String, String>,
+    /// Most recently used last.
+    memory: VecDeque<Hot>,
+}
+
+impl Cache {
+    pub fn load(budget: u64, ttl: Duration) -> Self {
+        let dir = dirs::cache_dir();
+        let entries = dir
+            .as_ref()
+            .and_then(|dir| fs::read_to_string(dir.join(INDEX_FILE)).ok())
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(5, '\t');
+                let key = fields.next()?.to_owned();
+                let size = fields.next()?.parse().ok()?;
+                let stored = fields.next()?.parse().ok()?;
+                let last_used = fields.next()?.parse().ok()?;
+                let url = fields.next()?.to_owned();
+
+                Some(Entry {
+                    url,
+                    key,
+                    size,
+                    stored,
+                    last_used,
+                })
+            })
+            .collect();
+        let aliases = dir
+            .as_ref()
+            .and_then(|dir| fs::read_to_string(dir.join(ALIASES_FILE)).ok())
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let (url, target) = line.split_once('\t')?;
+                Some((url.to_owned(), target.to_owned()))
+            })
+            .collect();
+
+        Self {
+            dir,
+            budget,
+            ttl,
+            entries,
+            aliases,
+            memory: VecDeque::new(),
+        }
+    }
+
+    /// Cached response of the URL allowed by the lookup, if any.
+    pub fn get(&mut self, url: &str, lookup: Lookup) -> Option<Response> {
+        let resolved = self.resolve(url);
+        let url = resolved.as_str();
+        let now = Local::now().timestamp();
+        if lookup == Lookup::Bypass || (lookup == Lookup::Fresh && !self.is_fresh(url)) {
+            return None;
+        }
+        let entry = self.entries.iter_mut().find(|entry| entry.url == url)?;
+
+        entry.last_used = now;
+        let key = entry.key.clone();
+
+        let hot = match self.memory.iter().position(|hot| hot.url == url) {
+            Some(index) => self.memory.remove(index)?,
+            None => {
+                let contents = fs::read(self.dir.as_ref()?.join(key)).ok()?;
+                let end = contents.windows(2).position(|bytes| bytes == b"\r\n")?;
+
+                Hot {
+                    url: url.to_owned(),
+                    meta: String::from_utf8_lossy(&contents[..end]).into_owned(),
+                    body: contents[end + 2..].to_vec(),
+                }
+            }
+        };
+
+        let response = Response {
+            url: Url::parse(url).ok()?,
+            redirects: Vec::new(),
+            meta: hot.meta.clone(),
+            body: hot.body.clone(),
+            metrics: None,
+        };
+        self.remember(hot);
+        // Only the recency of the entry is lost if the index can't be saved
+        let _ = self.save();
+
+        Some(response)
+    }
+
+    /// When the response of the URL was fetched, if it is cached.
+    pub fn stored(&self, url: &str) -> Option<DateTime<Local>> {
+        let resolved = self.resolve(url);
+        let url = resolved.as_str();
+        let entry = self.entries.iter().find(|entry| entry.url == url)?;
+        DateTime::from_timestamp(entry.stored, 0).map(|time| time.with_timezone(&Local))
+    }
+
+    /// Whether a fresh lookup of the URL would use the cache.
+    pub fn is_fresh(&self, url: &str) -> bool {
+        let resolved = self.resolve(url);
+        let url = resolved.as_str();
+        let now = Local::now().timestamp();
+        self.entries.iter().any(|entry| {
+            entry.url == url && now.saturating_sub(entry.stored) <= self.ttl.as_secs() as i64
+        })
+    }
+
+    pub fn usage(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.size).sum()
+    }
+
+    /// Stores the response of the URL, then evicts entries until the cache
+    /// fits its budget again.
+    pub fn store(
+        &mut self,
+        url: &str,
+        meta: &str,
+        body: &[u8],
+        pinned: impl Fn(&str) -> bool,
+    ) -> Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        let requested = normalized(url);
+        let url = requested.as_str();
+
+        let size = (meta.len() + 2 + body.len()) as u64;
+        if self.budget == 0 || (size > self.budget && !pinned(url)) {
+            return Ok(());
+        }
+
+        let key = format!("{:016x}", fnv1a(url.as_bytes()));
+        let mut contents = format!("{}\r\n", meta).into_bytes();
+        contents.extend_from_slice(body);
+
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(&key), contents)?;
+
+        let now = Local::now().timestamp();
+        self.entries.retain(|entry| entry.url != url);
+        self.aliases.remove(url);
+        self.entries.push(Entry {
+            url: url.to_owned(),
+            key,
+            size,
+            stored: now,
+            last_used: now,
+        });
+        self.remember(Hot {
+            url: url.to_owned(),
+            meta: meta.to_owned(),
+            body: body.to_vec(),
+        });
+
+        self.evict(&pinned)?;
+        self.save()
+    }
+
+    /// Records that requests for the URL were answered by a redirect to the
+    /// target, whose entry then answers them too.
+    pub fn alias(&mut self, url: &str, target: &str) -> Result<()> {
+        let (url, target) = (normalized(url), normalized(target));
+        if url == target || self.aliases.get(&url) == Some(&target) {
+            return Ok(());
+        }
+
+        self.aliases.insert(url, target);
+        self.save()
+    }
+
+    /// URL of the entry answering requests for the URL.
+    fn resolve(&self, url: &str) -> String {
+        let url = normalized(url);
+        match self.aliases.get(&url) {
+            Some(target) if !self.entries.iter().any(|entry| entry.url == url) => target.clone(),
+            _ => url,
+        }
+    }
+
+    /// Removes every entry, pinned or not.
+    pub fn clear(&mut self) -> Result<()> {
+        if let Some(dir) = &self.dir {
+            for entry in &self.entries {
+                let _ = fs::remove_file(dir.join(&entry.key));
+            }
+        }
+
+        self.entries.clear();
+        self.aliases.clear();
+        self.memory.clear();
+        self.save()
+    }
+
+    fn remember(&mut self, hot: Hot) {
+        self.memory.retain(|other| other.url != hot.url);
+        self.memory.push_back(hot);
+
+        if self.memory.len() > MEMORY_ENTRIES {
+            self.memory.pop_front();
+        }
+    }
+
+    fn evict(&mut self, pinned: &impl Fn(&str) -> bool) -> Result<()> {
+        while self.usage() > self.budget {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| !pinned(&entry.url))
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(index, _)| index)
+            else {
+                break;
+            };
+
+            let entry = self.entries.remove(oldest);
+            self.memory.retain(|hot| hot.url != entry.url);
+            if let Some(dir) = &self.dir {
+                fs::remove_file(dir.join(&entry.key))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+
+        let text = self
+            .entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{}\t{}\t{}\t{}\t{}\n",
+                    entry.key, entry.size, entry.stored, entry.last_used, entry.url
+                )
+            })
+            .collect::<String>();
+
+        // Aliases of evicted entries go with them
+        let aliases = self
+            .aliases
+            .iter()
+            .filter(|(_, target)| self.entries.iter().any(|entry| entry.url == **target))
+            .map(|(url, target)| format!("{}\t{}\n", url, target))
+            .collect::<String>();
+
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(INDEX_FILE), text)?;
+        fs::write(dir.join(ALIASES_FILE), aliases)?;
+
+        Ok(())
+    }
+
+    /// Gemtext page showing the cache usage and its entries, most recently
+    /// used first.
+    pub fn to_gemtext(&self, pinned: impl Fn(&str) -> bool) -> String {
+        let mut page = String::from("# Cache\n\n");
+
+        page.push_str(&format!(
+            "Using {} of {} in {} pages, {} of them pinned.\n\n",
+            format_size(self.usage()),
+            format_size(self.budget),
+            self.entries.len(),
+            self.entries
+                .iter()
+                .filter(|entry| pinned(&entry.url))
+                .count()
+        ));
+        page.push_str("=> about:cache?clear Clear the cache\n");
+
+        let mut entries = self.entries.iter().collect::<Vec<_>>();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.last_used));
+
+        if !entries.is_empty() {
+            page.push_str("\n## Pages\n");
+        }
+
+        for entry in entries {
+            let used = DateTime::from_timestamp(entry.last_used, 0)
+                .map(|time| {
+                    time.with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_default();
+
+            page.push_str(&format!(
+                "=> {0} {0} · {1} · {2}{3}\n",
+                entry.url,
+                format_size(entry.size),
+                used,
+                if pinned(&entry.url) { " · pinned" } else { "" }
+            ));
+        }
+
+        page
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+/// FNV-1a hash, stable across builds unlike the standard library hasher.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
@@ -0,0 +1,94 @@
+use std::{env, path::PathBuf};
+
+use color_eyre::{Result, eyre::bail};
+use gemini_client::TlsPolicy;
+
+use crate::export::Format;
+
+const USAGE: &str = "\
+Usage: dioscuri [OPTIONS] [URL | FILE | -]
+
+Local files open as file:// URLs, and gemtext piped on the standard input
+(or given as -) is shown as about:stdin.
+
+Options:
+      --dump                         Print the page as received and exit
+      --pretty                       Print gemtext with colors and numbered links,
+                                     for less -R, and exit
+      --reader                       Print the page linearized for screen readers, with
+                                     drawings announced by their alt text, and exit
+      --export <html|md>             Print the page as HTML or Markdown and exit
+      --export-subscriptions <FILE>  Write the subscriptions as OPML and exit
+      --offline                      Read pages from the cache only, without connecting
+      --tls-policy <POLICY>          Verify certificates with strict, allow-unknown-issuer,
+                                     tofu or accept-all instead of the configured policy
+  -h, --help                         Print this help and exit
+
+Settings are read from $XDG_CONFIG_HOME/dioscuri/config.toml";
+
+#[derive(Default)]
+pub struct Args {
+    pub url: Option<String>,
+    pub dump: bool,
+    /// Whether dumped gemtext is rendered with ANSI colors.
+    pub pretty: bool,
+    /// Whether dumped pages are linearized, see [`crate::reader::linearize`].
+    pub reader: bool,
+    pub export: Option<Format>,
+    pub export_subscriptions: Option<PathBuf>,
+    /// Whether pages are read from the cache only, whatever the
+    /// configuration says.
+    pub offline: bool,
+    /// Policy overriding the one of the configuration.
+    pub tls_policy: Option<TlsPolicy>,
+    pub help: bool,
+}
+
+impl Args {
+    pub fn parse() -> Result<Self> {
+        let mut args = Self::default();
+        let mut arguments = env::args().skip(1);
+
+        while let Some(argument) = arguments.next() {
+            match argument.as_str() {
+                "--dump" => args.dump = true,
+                "--pretty" => args.pretty = true,
+                "--reader" => args.reader = true,
+                "--export" => {
+                    let format = match arguments.next().as_deref().map(str::parse) {
+                        Some(Ok(format)) => format,
+                        Some(Err(error)) => bail!("{}\n\n{}", error, USAGE),
+                        None => bail!("--export requires a format\n\n{}", USAGE),
+                    };
+                    args.export = Some(format);
+                }
+                "--export-subscriptions" => {
+                    let Some(path) = arguments.next() else {
+                        bail!("--export-subscriptions requires a file path\n\n{}", USAGE);
+                    };
+                    args.export_subscriptions = Some(PathBuf::from(path));
+                }
+                "--offline" => args.offline = true,
+                "--tls-policy" => {
+                    let policy = match arguments.next().as_deref().map(str::parse) {
+                        Some(Ok(policy)) => policy,
+                        Some(Err(error)) => bail!("{}\n\n{}", error, USAGE),
+                        None => bail!("--tls-policy requires a policy\n\n{}", USAGE),
+                    };
+                    args.tls_policy = Some(policy);
+                }
+                "-h" | "--help" => args.help = true,
+                option if option.starts_with("--") => {
+                    bail!("Unknown option {}\n\n{}", option, USAGE)
+                }
+                _ => args.url = Some(argument),
+            }
+        }
+
+        Ok(args)
+    }
+
+    pub fn usage() -> &'static str {
+        USAGE
+    }
+}
@@ -0,0 +1,64 @@
+use std::{
+    env,
+    io::{self, Write},
+};
+
+use color_eyre::Result;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Copies text to the system clipboard, or through the terminal with the
+/// OSC 52 escape sequence when there is no usable system clipboard (e.g.
+/// over SSH, where the local terminal owns the clipboard).
+pub struct Clipboard {
+    // Kept alive for the whole session, X11 clipboards lose their contents
+    // once their owner goes away
+    system: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        let remote = env::var_os("SSH_CONNECTION").is_some() || env::var_os("SSH_TTY").is_some();
+
+        Self {
+            system: (!remote).then(arboard::Clipboard::new).and_then(Result::ok),
+        }
+    }
+
+    pub fn copy(&mut self, text: &str) -> Result<()> {
+        if let Some(system) = &mut self.system
+            && system.set_text(text).is_ok()
+        {
+            return Ok(());
+        }
+
+        let mut stdout = io::stdout();
+        write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+        stdout.flush()?;
+
+        Ok(())
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (index, &byte)| {
+                group | u32::from(byte) << (16 - 8 * index)
+            });
+
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(BASE64[(group >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
@@ -0,0 +1,52 @@
+use crate::{bookmarks::Bookmarks, visits::Visits};
+
+/// Most suggestions shown under the address bar.
+const MAX_SUGGESTIONS: usize = 8;
+
+pub struct Suggestion {
+    pub url: String,
+    pub title: Option<String>,
+}
+
+/// Suggests URLs for the address being typed: bookmarks first, then the
+/// most recent visits, matching the URL or the title case-insensitively.
+pub fn suggest(query: &str, bookmarks: &Bookmarks, visits: &Visits) -> Vec<Suggestion> {
+    let query = query.trim().to_lowercase();
+
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let bookmarked = bookmarks
+        .iter()
+        .map(|bookmark| (bookmark.url.as_str(), Some(bookmark.title.as_str())));
+    let visited = visits
+        .recent()
+        .map(|visit| (visit.url.as_str(), visit.title.as_deref()));
+
+    let mut suggestions: Vec<Suggestion> = Vec::new();
+
+    for (url, title) in bookmarked.chain(visited) {
+        let title = title.filter(|title| !title.is_empty());
+        let matches = url.to_lowercase().contains(&query)
+            || title.is_some_and(|title| title.to_lowercase().contains(&query));
+
+        if !matches
+            || url.to_lowercase() == query
+            || suggestions.iter().any(|suggestion| suggestion.url == url)
+        {
+            continue;
+        }
+
+        suggestions.push(Suggestion {
+            url: url.to_owned(),
+            title: title.map(str::to_owned),
+        });
+
+        if suggestions.len() == MAX_SUGGESTIONS {
+            break;
+        }
+    }
+
+    suggestions
+}
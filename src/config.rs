@@ -0,0 +1,203 @@
+use std::{collections::HashMap, fmt::Display, fs, io, net::IpAddr, path::PathBuf, str::FromStr};
+
+use color_eyre::{Result, eyre::WrapErr};
+use gemini_client::TlsPolicy;
+use serde::{Deserialize, Deserializer, de};
+use tracing::level_filters::LevelFilter;
+
+use crate::{dirs, filters::Filter, keymap::Keymap, sites::Site, theme::ThemeConfig};
+
+const CONFIG_FILE: &str = "config.toml";
+
+/// User configuration loaded from `config.toml` in the configuration
+/// directory. Every setting is optional and falls back to its default.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// URL shown in the address bar at startup.
+    pub start_url: String,
+    /// Whether the start URL is requested right away instead of waiting in
+    /// edit mode.
+    pub fetch_start_url: bool,
+    /// Gemtext file snippets are clipped into.
+    pub notes_file: Option<PathBuf>,
+    /// Directory downloads are saved to, the XDG download directory by
+    /// default.
+    pub download_dir: Option<PathBuf>,
+    /// Program the rendered page is handed to by the `open_in_pager` key,
+    /// `$PAGER` by default.
+    pub pager: Option<String>,
+    /// Programs handling MIME types, like `text/gemini` or `image/*`, as in
+    /// mailcap: `%s` in the command is the path of a file holding the body,
+    /// which is piped to the program otherwise. The current page is handed to
+    /// its handler with the `open_externally` key, and files that are not
+    /// pages are opened with theirs once saved.
+    pub handlers: HashMap<String, String>,
+    /// Lines scrolled by the arrow keys.
+    pub scroll_step_lines: usize,
+    /// Lines scrolled by a notch of the mouse wheel.
+    pub wheel_scroll_lines: usize,
+    /// Whether the mouse wheel scrolls the other way, like touchpads with
+    /// natural scrolling.
+    pub invert_scroll: bool,
+    pub redirect_limit: usize,
+    /// Search engine queried by `?terms` typed in the address bar.
+    pub search_url: String,
+    /// Search engines queried by `keyword terms` typed in the address bar.
+    pub search_keywords: HashMap<String, String>,
+    /// How long a silent server is waited on, in seconds.
+    pub timeout_secs: u64,
+    /// Longest a request waits for a host that asked to slow down, with
+    /// status 44, in seconds. Requests that would wait longer are refused.
+    pub slow_down_wait_secs: u64,
+    /// Disk space the page cache may use, in megabytes. 0 disables it.
+    pub cache_size_mb: u64,
+    /// How long cached pages are shown instead of fetching them again, in
+    /// seconds. Going through the history uses cached pages of any age.
+    pub cache_ttl_secs: u64,
+    /// Visible links of a page to its own host fetched in the background at
+    /// once, for following them from the cache. 0 disables prefetching.
+    pub prefetch_links: usize,
+    /// Auto-refresh intervals, in seconds, cycled through by the
+    /// `auto_refresh` key.
+    pub refresh_intervals: Vec<u64>,
+    /// How often the subscriptions are checked for new posts, in minutes. 0
+    /// disables the checks.
+    pub feed_interval_mins: u64,
+    /// Whether days are shown as dates only in the feeds and the history,
+    /// rather than along with how long ago they were.
+    pub absolute_dates: bool,
+    /// Terminal width, in columns, under which the UI drops its borders and
+    /// shortens the address bar and instructions. 0 disables the compact
+    /// layout.
+    pub compact_width: u16,
+    /// Widest the body is laid out, in columns, centered in wider
+    /// terminals. 0 uses the whole width.
+    pub max_width: u16,
+    /// Whether a bar under the body shows the host of the page, how its
+    /// certificate was trusted, the status of its response, its MIME type
+    /// and its size.
+    pub status_bar: bool,
+    /// Whether tabs open in reader mode, see [`crate::reader::linearize`].
+    pub reader_mode: bool,
+    /// Program reading pages aloud in reader mode, like `espeak-ng`, given
+    /// their text on its standard input.
+    pub speech_command: Option<String>,
+    /// Whether pages are only read from the cache, without connecting to
+    /// anything.
+    pub offline: bool,
+    #[serde(deserialize_with = "from_str")]
+    pub tls_policy: TlsPolicy,
+    /// SOCKS5 proxy Gemini connections go through, as `host:port`.
+    pub proxy: Option<String>,
+    /// Addresses used for these hosts instead of resolving them, like a
+    /// hosts file.
+    pub hosts: HashMap<String, IpAddr>,
+    /// Identification sent along with Titan uploads, none by default.
+    pub client_id: Option<String>,
+    /// Tokens sent along with Titan uploads, per host.
+    pub titan_tokens: HashMap<String, String>,
+    /// Settings of hosts overriding the general ones, changed on about:site
+    /// too.
+    pub sites: HashMap<String, Site>,
+    /// Most detailed events written to the log: `off`, `error`, `warn`,
+    /// `info`, `debug` or `trace`.
+    #[serde(deserialize_with = "from_str")]
+    pub log_level: LevelFilter,
+    pub theme: ThemeConfig,
+    pub keys: Keymap,
+    pub filters: Vec<Filter>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            start_url: String::from("gemini://geminiprotocol.net/"),
+            fetch_start_url: false,
+            notes_file: None,
+            download_dir: None,
+            pager: None,
+            handlers: HashMap::new(),
+            scroll_step_lines: 1,
+            wheel_scroll_lines: 3,
+            invert_scroll: false,
+            redirect_limit: 5,
+            search_url: String::from("gemini://geminispace.info/search"),
+            search_keywords: HashMap::new(),
+            timeout_secs: 30,
+            slow_down_wait_secs: 2,
+            cache_size_mb: 50,
+            cache_ttl_secs: 300,
+            prefetch_links: 0,
+            refresh_intervals: vec![30, 60, 300, 900],
+            feed_interval_mins: 60,
+            absolute_dates: false,
+            compact_width: 60,
+            max_width: 0,
+            status_bar: true,
+            reader_mode: false,
+            speech_command: None,
+            offline: false,
+            tls_policy: TlsPolicy::default(),
+            proxy: None,
+            hosts: HashMap::new(),
+            client_id: None,
+            titan_tokens: HashMap::new(),
+            sites: HashMap::new(),
+            log_level: LevelFilter::INFO,
+            theme: ThemeConfig::default(),
+            keys: Keymap::default(),
+            filters: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Handler of the MIME type, the one for its whole type (like `image/*`)
+    /// otherwise.
+    pub fn handler(&self, mime: &str) -> Option<&str> {
+        let mime = mime.to_ascii_lowercase();
+        let any_subtype = format!("{}/*", mime.split('/').next().unwrap_or_default());
+        self.handlers
+            .get(&mime)
+            .or_else(|| self.handlers.get(&any_subtype))
+            .map(String::as_str)
+            .filter(|handler| !handler.trim().is_empty())
+    }
+
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(CONFIG_FILE))
+    }
+
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text)
+                .wrap_err_with(|| format!("Invalid configuration file {}", path.display())),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+fn from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = String::deserialize(deserializer)?;
+    value.parse().map_err(de::Error::custom)
+}
+
+pub fn from_str_option<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    from_str(deserializer).map(Some)
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, Local, NaiveDate};
+
+/// Date of a post, from the `YYYY-MM-DD` prefix of a gemtext link label or
+/// of a gemlog file name.
+pub fn parse_date(text: &str) -> Option<NaiveDate> {
+    let date = text.get(..10)?;
+    if !date.char_indices().all(|(index, char)| match index {
+        4 | 7 => char == '-',
+        _ => char.is_ascii_digit(),
+    }) {
+        return None;
+    }
+
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// Local date of an Atom timestamp, which carries its own offset, falling
+/// back to its date as written.
+pub fn parse_timestamp(text: &str) -> Option<NaiveDate> {
+    DateTime::parse_from_rfc3339(text.trim())
+        .map(|time| time.with_timezone(&Local).date_naive())
+        .ok()
+        .or_else(|| parse_date(text.trim()))
+}
+
+/// Heading of the day the posts or visits under it are from: the date,
+/// followed by how long ago it was unless `absolute`.
+pub fn day_heading(date: NaiveDate, absolute: bool) -> String {
+    let formatted = date.format("%Y-%m-%d").to_string();
+    if absolute {
+        return formatted;
+    }
+
+    match relative(date, Local::now().date_naive()) {
+        Some(ago) => format!("{} · {}", formatted, ago),
+        None => formatted,
+    }
+}
+
+fn relative(date: NaiveDate, today: NaiveDate) -> Option<String> {
+    let days = (today - date).num_days();
+    let plural = |count: i64, unit: &str| {
+        format!(
+            "{} {}{} ago",
+            count,
+            unit,
+            if count == 1 { "" } else { "s" }
+        )
+    };
+
+    Some(match days {
+        ..0 => return None,
+        0 => String::from("today"),
+        1 => String::from("yesterday"),
+        2..14 => plural(days, "day"),
+        14..60 => plural(days / 7, "week"),
+        60..730 => plural(days / 30, "month"),
+        _ => plural(days / 365, "year"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_days_relative_to_today() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 20).unwrap();
+        let ago = |y, m, d| relative(NaiveDate::from_ymd_opt(y, m, d).unwrap(), today);
+
+        assert_eq!(ago(2024, 5, 20).as_deref(), Some("today"));
+        assert_eq!(ago(2024, 5, 19).as_deref(), Some("yesterday"));
+        assert_eq!(ago(2024, 5, 18).as_deref(), Some("2 days ago"));
+        assert_eq!(ago(2024, 5, 6).as_deref(), Some("2 weeks ago"));
+        assert_eq!(ago(2024, 2, 1).as_deref(), Some("3 months ago"));
+        assert_eq!(ago(2021, 5, 1).as_deref(), Some("3 years ago"));
+        assert_eq!(ago(2024, 5, 21), None);
+    }
+}
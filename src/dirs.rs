@@ -0,0 +1,87 @@
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+
+use color_eyre::Result;
+
+const APP_NAME: &str = "dioscuri";
+
+/// Directory where persistent application data lives, following the XDG base
+/// directory conventions.
+pub fn data_dir() -> Option<PathBuf> {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::home_dir().map(|home| home.join(".local").join("share")))
+        .map(|dir| dir.join(APP_NAME))
+}
+
+/// Reads a file from the data directory, if it exists.
+pub fn read_data(name: &str) -> Option<String> {
+    fs::read_to_string(data_dir()?.join(name)).ok()
+}
+
+/// Writes a file to the data directory, creating the directory if needed.
+pub fn write_data(name: &str, contents: &str) -> Result<()> {
+    let Some(dir) = data_dir() else {
+        return Ok(());
+    };
+
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(name), contents)?;
+
+    Ok(())
+}
+
+/// Appends to a file in the data directory, creating both if needed.
+pub fn append_data(name: &str, contents: &str) -> Result<()> {
+    let Some(dir) = data_dir() else {
+        return Ok(());
+    };
+
+    fs::create_dir_all(&dir)?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(name))?
+        .write_all(contents.as_bytes())?;
+
+    Ok(())
+}
+
+/// Directory where cached data lives, following the XDG base directory
+/// conventions.
+pub fn cache_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::home_dir().map(|home| home.join(".cache")))
+        .map(|dir| dir.join(APP_NAME))
+}
+
+/// Directory where the user configuration lives, following the XDG base
+/// directory conventions.
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::home_dir().map(|home| home.join(".config")))
+        .map(|dir| dir.join(APP_NAME))
+}
+
+/// Directory where state worth keeping but not worth backing up lives,
+/// like logs, following the XDG base directory conventions.
+pub fn state_dir() -> Option<PathBuf> {
+    env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::home_dir().map(|home| home.join(".local").join("state")))
+        .map(|dir| dir.join(APP_NAME))
+}
+
+/// Directory downloads are saved to unless configured otherwise, the XDG
+/// user directory for downloads.
+pub fn download_dir() -> Option<PathBuf> {
+    env::var_os("XDG_DOWNLOAD_DIR")
+        .map(PathBuf::from)
+        .or_else(|| env::home_dir().map(|home| home.join("Downloads")))
+}
@@ -0,0 +1,373 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+};
+
+use color_eyre::Result;
+use gemini_client::{GeminiClient, Identity};
+use percent_encoding::percent_decode_str;
+use url::Url;
+
+use crate::cache::format_size;
+
+pub const DOWNLOADS_PAGE: &str = "about:downloads";
+/// Downloads running at once, the others wait for their turn.
+const MAX_ACTIVE: usize = 2;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum State {
+    Queued,
+    Active,
+    Completed,
+    Failed(String),
+    Cancelled,
+}
+
+struct Download {
+    /// Number of the download in the session, in links to the page.
+    id: usize,
+    url: String,
+    identity: Option<Identity>,
+    path: PathBuf,
+    state: State,
+    /// Bytes written so far, updated by the thread of the download.
+    received: Arc<AtomicUsize>,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Files fetched in the background into the download directory, listed on
+/// [`DOWNLOADS_PAGE`] for the session.
+pub struct Downloads {
+    client: GeminiClient,
+    dir: PathBuf,
+    downloads: Vec<Download>,
+    next_id: usize,
+    /// Outcomes of the downloads, by id.
+    sender: Sender<(usize, Result<(), String>)>,
+    receiver: Receiver<(usize, Result<(), String>)>,
+}
+
+impl Downloads {
+    pub fn new(client: GeminiClient, dir: PathBuf) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        Self {
+            client,
+            dir,
+            downloads: Vec::new(),
+            next_id: 1,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Queues the download of the URL, returning where the file will be.
+    pub fn queue(&mut self, url: &str, identity: Option<Identity>) -> PathBuf {
+        let path = self.available_path(url);
+        let id = self.new_id();
+        self.downloads.push(Download {
+            id,
+            url: url.to_owned(),
+            identity,
+            path: path.clone(),
+            state: State::Queued,
+            received: Arc::default(),
+            cancelled: Arc::default(),
+        });
+        self.start_queued();
+
+        path
+    }
+
+    /// Saves a body already received, like a response that was not a page,
+    /// as a completed download.
+    pub fn save(&mut self, url: &str, body: &[u8]) -> Result<PathBuf> {
+        let path = self.available_path(url);
+        fs::create_dir_all(&self.dir)?;
+        fs::write(&path, body)?;
+
+        let id = self.new_id();
+        self.downloads.push(Download {
+            id,
+            url: url.to_owned(),
+            identity: None,
+            path: path.clone(),
+            state: State::Completed,
+            received: Arc::new(AtomicUsize::new(body.len())),
+            cancelled: Arc::default(),
+        });
+
+        Ok(path)
+    }
+
+    pub fn cancel(&mut self, id: usize) {
+        let Some(download) = self.get_mut(id) else {
+            return;
+        };
+
+        match download.state {
+            // The thread stops at its next write, and removes the file
+            State::Active => download.cancelled.store(true, Ordering::Relaxed),
+            State::Queued => download.state = State::Cancelled,
+            _ => {}
+        }
+    }
+
+    /// Queues a failed or cancelled download again, from the start.
+    pub fn retry(&mut self, id: usize) {
+        if let Some(download) = self.get_mut(id)
+            && matches!(download.state, State::Failed(_) | State::Cancelled)
+        {
+            download.state = State::Queued;
+            download.received = Arc::default();
+            download.cancelled = Arc::default();
+            self.start_queued();
+        }
+    }
+
+    /// Forgets the downloads that are over, the files staying where they
+    /// are.
+    pub fn clear(&mut self) {
+        self.downloads
+            .retain(|download| matches!(download.state, State::Queued | State::Active));
+    }
+
+    /// Collects the outcomes of the downloads that ended, starting the
+    /// queued ones in their place. Returns the paths of those completed.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let mut completed = Vec::new();
+        while let Ok((id, outcome)) = self.receiver.try_recv() {
+            let Some(download) = self.get_mut(id) else {
+                continue;
+            };
+            download.state = match outcome {
+                Ok(()) => {
+                    completed.push(download.path.clone());
+                    State::Completed
+                }
+                Err(_) if download.cancelled.load(Ordering::Relaxed) => State::Cancelled,
+                Err(error) => State::Failed(error),
+            };
+        }
+        self.start_queued();
+
+        completed
+    }
+
+    /// Downloads queued or running.
+    pub fn pending(&self) -> usize {
+        self.downloads
+            .iter()
+            .filter(|download| matches!(download.state, State::Queued | State::Active))
+            .count()
+    }
+
+    pub fn to_gemtext(&self) -> String {
+        let mut page = format!(
+            "# Downloads\n\nFiles are saved to {}.\n\n",
+            self.dir.display()
+        );
+        if self.downloads.is_empty() {
+            page.push_str("No downloads yet.\n");
+            return page;
+        }
+
+        for download in self.downloads.iter().rev() {
+            let name = download
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default();
+            let received = format_size(download.received.load(Ordering::Relaxed) as u64);
+
+            page.push_str(&format!("## {}\n\n", name));
+            let (status, action) = match &download.state {
+                State::Queued => (String::from("Queued"), Some(("cancel", "Cancel"))),
+                State::Active => (format!("{} received", received), Some(("cancel", "Cancel"))),
+                State::Completed => (
+                    format!("{} saved to {}", received, download.path.display()),
+                    None,
+                ),
+                State::Failed(error) => (format!("Failed: {}", error), Some(("retry", "Retry"))),
+                State::Cancelled => (String::from("Cancelled"), Some(("retry", "Retry"))),
+            };
+            page.push_str(&format!("{}\n=> {}\n", status, download.url));
+            if let Some((action, label)) = action {
+                page.push_str(&format!(
+                    "=> {}?{}={} {}\n",
+                    DOWNLOADS_PAGE, action, download.id, label
+                ));
+            }
+            page.push('\n');
+        }
+        page.push_str(&format!(
+            "=> {}?clear Clear the finished downloads\n",
+            DOWNLOADS_PAGE
+        ));
+
+        page
+    }
+
+    fn start_queued(&mut self) {
+        let active = self
+            .downloads
+            .iter()
+            .filter(|download| download.state == State::Active)
+            .count();
+        let queued = self
+            .downloads
+            .iter()
+            .enumerate()
+            .filter(|(_, download)| download.state == State::Queued)
+            .map(|(index, _)| index)
+            .take(MAX_ACTIVE.saturating_sub(active))
+            .collect::<Vec<_>>();
+
+        for index in queued {
+            self.start(index);
+        }
+    }
+
+    fn start(&mut self, index: usize) {
+        let download = &mut self.downloads[index];
+        let file = fs::create_dir_all(&self.dir).and_then(|()| File::create(&download.path));
+        let mut sink = match file {
+            Ok(file) => Sink {
+                file,
+                received: download.received.clone(),
+                cancelled: download.cancelled.clone(),
+            },
+            Err(error) => {
+                download.state = State::Failed(error.to_string());
+                return;
+            }
+        };
+        download.state = State::Active;
+
+        let client = self.client.clone();
+        let sender = self.sender.clone();
+        let (id, url, identity, path) = (
+            download.id,
+            download.url.clone(),
+            download.identity.clone(),
+            download.path.clone(),
+        );
+        thread::spawn(move || {
+            let outcome = client
+                .download(&url, identity.as_ref(), &mut sink)
+                .map(|_| ())
+                .map_err(|error| error.to_string());
+            if outcome.is_err() {
+                let _ = fs::remove_file(&path);
+            }
+
+            let _ = sender.send((id, outcome));
+        });
+    }
+
+    fn new_id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    fn get_mut(&mut self, id: usize) -> Option<&mut Download> {
+        self.downloads.iter_mut().find(|download| download.id == id)
+    }
+
+    /// Path in the download directory named after the last segment of the
+    /// URL, numbered when a file or another download already has it.
+    fn available_path(&self, url: &str) -> PathBuf {
+        let name = Url::parse(url)
+            .ok()
+            .and_then(|url| {
+                let segment = url.path_segments()?.next_back()?.to_owned();
+                // Control characters would reach the listing of about:downloads
+                // as well as the file name
+                let name = percent_decode_str(&segment)
+                    .decode_utf8_lossy()
+                    .replace(|char: char| char == '/' || char.is_control(), "_");
+                Some(name).filter(|name| !name.is_empty() && !name.starts_with('.'))
+            })
+            .unwrap_or_else(|| String::from("download"));
+
+        let taken = |path: &Path| {
+            path.exists() || self.downloads.iter().any(|download| download.path == path)
+        };
+        let path = self.dir.join(&name);
+        if !taken(&path) {
+            return path;
+        }
+
+        let (stem, extension) = match name.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+            _ => (name.as_str(), String::new()),
+        };
+        (1..)
+            .map(|number| self.dir.join(format!("{}-{}{}", stem, number, extension)))
+            .find(|path| !taken(path))
+            .unwrap_or(path)
+    }
+}
+
+/// File a download is written to, counting the bytes and failing once the
+/// download is cancelled.
+struct Sink {
+    file: File,
+    received: Arc<AtomicUsize>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Write for Sink {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(io::Error::other("Cancelled"));
+        }
+
+        let written = self.file.write(bytes)?;
+        self.received.fetch_add(written, Ordering::Relaxed);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    #[test]
+    fn paths_are_numbered_once_taken() {
+        let dir = env::temp_dir().join(format!("dioscuri-downloads-{}", process::id()));
+        let mut downloads = Downloads::new(GeminiClient::default(), dir.clone());
+
+        let path = downloads
+            .save("gemini://example.org/a/notes.txt", b"notes")
+            .unwrap();
+        assert_eq!(path, dir.join("notes.txt"));
+        assert_eq!(
+            downloads.available_path("gemini://example.org/b/notes.txt"),
+            dir.join("notes-1.txt")
+        );
+        assert_eq!(
+            downloads.available_path("gemini://example.org/"),
+            dir.join("download")
+        );
+        assert_eq!(
+            downloads.available_path("gemini://evil/x%0A=%3E%20about:x%09%1B[2J"),
+            dir.join("x_=> about:x__[2J")
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
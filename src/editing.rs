@@ -0,0 +1,138 @@
+/// Most addresses the history keeps, the oldest being dropped first.
+const MAX_HISTORY: usize = 100;
+
+/// Index of the start of the word before the cursor, words being runs of
+/// letters and digits so that the parts of a URL are told apart.
+pub fn word_start(text: &str, index: usize) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let mut index = index.min(chars.len());
+
+    while index > 0 && !chars[index - 1].is_alphanumeric() {
+        index -= 1;
+    }
+    while index > 0 && chars[index - 1].is_alphanumeric() {
+        index -= 1;
+    }
+
+    index
+}
+
+/// Index of the end of the word after the cursor.
+pub fn word_end(text: &str, index: usize) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let mut index = index.min(chars.len());
+
+    while index < chars.len() && !chars[index].is_alphanumeric() {
+        index += 1;
+    }
+    while index < chars.len() && chars[index].is_alphanumeric() {
+        index += 1;
+    }
+
+    index
+}
+
+/// Removes the characters between the two indices, returning the index
+/// the cursor is left at.
+pub fn remove_range(text: &mut String, from: usize, to: usize) -> usize {
+    let (from, to) = (from.min(to), from.max(to));
+    *text = text
+        .chars()
+        .take(from)
+        .chain(text.chars().skip(to))
+        .collect();
+
+    from
+}
+
+/// Addresses entered in the session, browsed with Up and Down from the
+/// most recent one, like the history of a shell.
+#[derive(Default)]
+pub struct History {
+    entries: Vec<String>,
+    /// Entry shown, none while editing a new address.
+    position: Option<usize>,
+    /// Address being edited before browsing the history.
+    draft: String,
+}
+
+impl History {
+    /// Adds the address after the others, moving it there if it was
+    /// entered before.
+    pub fn push(&mut self, address: &str) {
+        self.position = None;
+        if address.trim().is_empty() {
+            return;
+        }
+
+        self.entries.retain(|entry| entry != address);
+        self.entries.push(address.to_owned());
+        if self.entries.len() > MAX_HISTORY {
+            self.entries.remove(0);
+        }
+    }
+
+    /// Entry before the one shown, keeping the address being edited to
+    /// come back to it.
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            Some(0) => return None,
+            Some(position) => position - 1,
+            None => {
+                self.draft = current.to_owned();
+                self.entries.len().checked_sub(1)?
+            }
+        };
+
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    /// Entry after the one shown, the address being edited after the last.
+    pub fn next(&mut self) -> Option<&str> {
+        let position = self.position? + 1;
+
+        if position == self.entries.len() {
+            self.position = None;
+            return Some(&self.draft);
+        }
+
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    /// Stops browsing, the address shown being edited from now on.
+    pub fn reset(&mut self) {
+        self.position = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_are_parts_of_the_address() {
+        let url = "gemini://example.org/some/page.gmi";
+
+        assert_eq!(word_start(url, url.len()), 31);
+        assert_eq!(word_start(url, 31), 26);
+        assert_eq!(word_end(url, 0), 6);
+        assert_eq!(word_end(url, 6), 16);
+    }
+
+    #[test]
+    fn history_comes_back_to_the_address_edited() {
+        let mut history = History::default();
+        history.push("gemini://a/");
+        history.push("gemini://b/");
+        history.push("gemini://a/");
+
+        assert_eq!(history.previous("gem"), Some("gemini://a/"));
+        assert_eq!(history.previous("gem"), Some("gemini://b/"));
+        assert_eq!(history.previous("gem"), None);
+        assert_eq!(history.next(), Some("gemini://a/"));
+        assert_eq!(history.next(), Some("gem"));
+        assert_eq!(history.next(), None);
+    }
+}
@@ -0,0 +1,302 @@
+use std::{path::Path, str::FromStr};
+
+use gemini_client::gemtext::{Document, Line};
+use url::Url;
+
+/// Formats pages are exported to, for reading them outside of Gemini.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Html,
+    Markdown,
+}
+
+impl Format {
+    /// Format of the file, Markdown for `.md` files and HTML otherwise.
+    pub fn of_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("md" | "markdown") => Self::Markdown,
+            _ => Self::Html,
+        }
+    }
+
+    /// Converts the document, resolving its links against the URL it was
+    /// served from. Links of local files are kept relative.
+    pub fn export(self, document: &Document, base: Option<&Url>) -> String {
+        let blocks = blocks(document, base.filter(|base| base.scheme() != "file"));
+
+        match self {
+            Self::Html => {
+                let title = document
+                    .title()
+                    .map(str::to_owned)
+                    .or_else(|| base.map(Url::to_string))
+                    .unwrap_or_default();
+                let body = blocks.iter().map(html).collect::<String>();
+
+                format!(
+                    "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+                     <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+                     <title>{}</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+                    escape_html(&title),
+                    body
+                )
+            }
+            Self::Markdown => {
+                let blocks = blocks.iter().map(markdown).collect::<Vec<_>>();
+                format!("{}\n", blocks.join("\n\n"))
+            }
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "html" => Ok(Self::Html),
+            "md" | "markdown" => Ok(Self::Markdown),
+            _ => Err(format!("Unknown export format {}, use html or md", format)),
+        }
+    }
+}
+
+/// Lines of the document, with the consecutive list items, quotes and
+/// preformatted lines grouped together.
+enum Block<'a> {
+    Heading(u8, &'a str),
+    Text(&'a str),
+    Link {
+        url: String,
+        label: &'a str,
+    },
+    List(Vec<&'a str>),
+    Quote(Vec<&'a str>),
+    Preformatted {
+        alt: Option<&'a str>,
+        lines: Vec<&'a str>,
+    },
+}
+
+fn blocks<'a>(document: &'a Document, base: Option<&Url>) -> Vec<Block<'a>> {
+    let mut blocks = Vec::new();
+    // Whether the line before can be joined by one of its kind
+    let mut open = false;
+    let mut fenced = false;
+
+    for line in &document.lines {
+        let last = blocks.last_mut().filter(|_| open);
+        open = true;
+
+        match line {
+            Line::ListItem(item) => match last {
+                Some(Block::List(items)) => items.push(item),
+                _ => blocks.push(Block::List(vec![item])),
+            },
+            Line::Quote(quote) => match last {
+                Some(Block::Quote(quotes)) => quotes.push(quote),
+                _ => blocks.push(Block::Quote(vec![quote])),
+            },
+            // Documents made of preformatted text have no toggle lines
+            Line::Preformatted(text) => match last {
+                Some(Block::Preformatted { lines, .. }) => lines.push(text),
+                _ => blocks.push(Block::Preformatted {
+                    alt: None,
+                    lines: vec![text],
+                }),
+            },
+            Line::PreformatToggle { .. } if fenced => {
+                fenced = false;
+                open = false;
+            }
+            Line::PreformatToggle { alt } => {
+                fenced = true;
+                blocks.push(Block::Preformatted {
+                    alt: alt.as_deref(),
+                    lines: Vec::new(),
+                });
+            }
+            Line::Heading { level, text } => {
+                open = false;
+                blocks.push(Block::Heading((*level).clamp(1, 3), text));
+            }
+            Line::Link { url, label } => {
+                open = false;
+                blocks.push(Block::Link {
+                    url: base
+                        .and_then(|base| base.join(url).ok())
+                        .map_or_else(|| url.clone(), |url| url.to_string()),
+                    label: label.as_deref().unwrap_or(url),
+                });
+            }
+            Line::Text(text) => {
+                open = false;
+                if !text.trim().is_empty() {
+                    blocks.push(Block::Text(text));
+                }
+            }
+        }
+    }
+
+    blocks
+}
+
+fn html(block: &Block) -> String {
+    let lines = |lines: &[&str], tag: &str| {
+        lines
+            .iter()
+            .map(|line| format!("<{0}>{1}</{0}>\n", tag, escape_html(line)))
+            .collect::<String>()
+    };
+
+    match block {
+        Block::Heading(level, text) => format!("<h{0}>{1}</h{0}>\n", level, escape_html(text)),
+        Block::Text(text) => format!("<p>{}</p>\n", escape_html(text)),
+        Block::Link { url, label } if !linkable(url) => {
+            format!("<p>{} {}</p>\n", escape_html(label), escape_html(url))
+        }
+        Block::Link { url, label } => format!(
+            "<p><a href=\"{}\">{}</a></p>\n",
+            escape_html(url),
+            escape_html(label)
+        ),
+        Block::List(items) => format!("<ul>\n{}</ul>\n", lines(items, "li")),
+        Block::Quote(quotes) => format!("<blockquote>\n{}</blockquote>\n", lines(quotes, "p")),
+        Block::Preformatted { alt, lines } => format!(
+            "<pre{}>{}</pre>\n",
+            alt.map(|alt| format!(" title=\"{}\"", escape_html(alt)))
+                .unwrap_or_default(),
+            escape_html(&lines.join("\n"))
+        ),
+    }
+}
+
+fn markdown(block: &Block) -> String {
+    let lines = |lines: &[&str], prefix: &str| {
+        lines
+            .iter()
+            .map(|line| format!("{}{}", prefix, escape_markdown(line)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    match block {
+        Block::Heading(level, text) => {
+            format!("{} {}", "#".repeat(*level as usize), escape_markdown(text))
+        }
+        Block::Text(text) => escape_markdown(text),
+        Block::Link { url, label } if !linkable(url) => {
+            format!("{} {}", escape_markdown(label), escape_markdown(url))
+        }
+        Block::Link { url, label } => format!(
+            "[{}]({})",
+            escape_markdown(label),
+            url.replace(' ', "%20")
+                .replace('(', "%28")
+                .replace(')', "%29")
+        ),
+        Block::List(items) => lines(items, "- "),
+        Block::Quote(quotes) => lines(quotes, "> "),
+        Block::Preformatted { alt, lines } => {
+            // The fence is longer than any run of backticks inside
+            let mut fence = String::from("```");
+            while lines.iter().any(|line| line.contains(&fence)) {
+                fence.push('`');
+            }
+            let mut block = format!("{}{}\n", fence, alt.unwrap_or_default());
+            for line in lines {
+                block.push_str(line);
+                block.push('\n');
+            }
+            block.push_str(&fence);
+            block
+        }
+    }
+}
+
+/// Whether the target is kept as a link in the exported file: relative, or
+/// with a scheme that only leads to another document, unlike `javascript:`
+/// which would run in the page.
+fn linkable(url: &str) -> bool {
+    match Url::parse(url) {
+        Ok(url) => matches!(
+            url.scheme(),
+            "gemini" | "gopher" | "finger" | "http" | "https" | "mailto"
+        ),
+        Err(error) => error == url::ParseError::RelativeUrlWithoutBase,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Escapes what Markdown would take for formatting, inline or at the start
+/// of a line.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::new();
+    for char in text.chars() {
+        if matches!(char, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>') {
+            escaped.push('\\');
+        }
+        escaped.push(char);
+    }
+
+    let digits = escaped.chars().take_while(char::is_ascii_digit).count();
+    if escaped.starts_with(['#', '-', '+', '=']) {
+        escaped.insert(0, '\\');
+    } else if digits > 0 && escaped[digits..].starts_with(['.', ')']) {
+        escaped.insert(digits, '\\');
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_are_exported_together() {
+        let document = Document::parse(
+            "# Notes\n* one\n* two\n\n* three\n> quoted <b>\n=> /a Some page\n\
+             ```shell\n$ ls\n```\n1. not a list\n",
+        );
+        let base = Url::parse("gemini://example.org/notes/").unwrap();
+
+        assert_eq!(
+            Format::Markdown.export(&document, Some(&base)),
+            "# Notes\n\n- one\n- two\n\n- three\n\n> quoted \\<b\\>\n\n\
+             [Some page](gemini://example.org/a)\n\n```shell\n$ ls\n```\n\n1\\. not a list\n"
+        );
+        assert!(Format::Html.export(&document, Some(&base)).contains(
+            "<ul>\n<li>one</li>\n<li>two</li>\n</ul>\n<ul>\n<li>three</li>\n</ul>\n\
+             <blockquote>\n<p>quoted &lt;b&gt;</p>\n</blockquote>\n\
+             <p><a href=\"gemini://example.org/a\">Some page</a></p>\n\
+             <pre title=\"shell\">$ ls</pre>\n"
+        ));
+    }
+
+    #[test]
+    fn only_links_to_documents_are_kept() {
+        let document = Document::parse("=> javascript:alert(document.cookie) Win\n=> ../up Up\n");
+
+        assert_eq!(
+            Format::Html
+                .export(&document, None)
+                .split("<body>\n")
+                .nth(1),
+            Some(
+                "<p>Win javascript:alert(document.cookie)</p>\n\
+                 <p><a href=\"../up\">Up</a></p>\n</body>\n</html>\n"
+            )
+        );
+        assert_eq!(
+            Format::Markdown.export(&document, None),
+            "Win javascript:alert(document.cookie)\n\n[Up](../up)\n"
+        );
+    }
+}
@@ -0,0 +1,349 @@
+use std::{
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use color_eyre::Result;
+use gemini_client::{GeminiClient, Response, gemtext::Document};
+use regex::Regex;
+
+use crate::{dates, dirs, subscriptions::Subscriptions};
+
+const POSTS_FILE: &str = "posts";
+const ATOM_MIMES: [&str; 3] = ["application/atom+xml", "application/xml", "text/xml"];
+
+pub struct Post {
+    /// Date of the post, as `YYYY-MM-DD`.
+    pub date: String,
+    pub url: String,
+    /// URL of the subscription the post was found in.
+    pub feed: String,
+    pub title: String,
+    /// Whether the post was found since the timeline was last opened.
+    pub new: bool,
+}
+
+/// Result of checking a subscription: the posts it lists, or why it couldn't
+/// be read.
+type Check = (String, Result<Vec<Post>, String>);
+
+/// Posts of the subscribed capsules, found by fetching their index either as
+/// gemtext following the subscription convention or as an Atom feed, and
+/// persisted in the data directory as `{date}\t{new}\t{url}\t{feed}\t{title}`
+/// lines.
+///
+/// https://geminiprotocol.net/docs/companion/subscription.gmi
+#[derive(Default)]
+pub struct Feeds {
+    posts: Vec<Post>,
+    /// Subscriptions that failed on the last check, with the error.
+    failures: Vec<(String, String)>,
+    last_check: Option<Instant>,
+    /// Results of the check running in the background.
+    pending: Option<Receiver<Vec<Check>>>,
+}
+
+impl Feeds {
+    pub fn load() -> Self {
+        let posts = dirs::read_data(POSTS_FILE)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(5, '\t');
+
+                Some(Post {
+                    date: fields.next()?.to_owned(),
+                    new: fields.next()? == "1",
+                    url: fields.next()?.to_owned(),
+                    feed: fields.next()?.to_owned(),
+                    title: fields.next().unwrap_or_default().to_owned(),
+                })
+            })
+            .collect();
+
+        Self {
+            posts,
+            ..Self::default()
+        }
+    }
+
+    /// Whether the subscriptions are due for a check, every `interval`.
+    pub fn due(&self, interval: Duration) -> bool {
+        self.pending.is_none()
+            && !interval.is_zero()
+            && self
+                .last_check
+                .is_none_or(|checked| checked.elapsed() >= interval)
+    }
+
+    /// Starts fetching every subscription in the background, several at
+    /// once, unless a check is already running.
+    pub fn check(&mut self, client: &GeminiClient, subscriptions: &Subscriptions) {
+        if self.pending.is_some() {
+            return;
+        }
+
+        let client = client.clone();
+        let urls = subscriptions
+            .iter()
+            .map(|subscription| subscription.url.clone())
+            .collect::<Vec<_>>();
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let responses = client.request_many(&urls);
+            let checks = urls
+                .into_iter()
+                .zip(responses)
+                .map(|(url, response)| {
+                    let posts = response
+                        .map_err(|error| error.to_string())
+                        .and_then(|response| read_posts(response, &url));
+                    (url, posts)
+                })
+                .collect();
+
+            let _ = sender.send(checks);
+        });
+
+        self.pending = Some(receiver);
+        self.last_check = Some(Instant::now());
+    }
+
+    /// Merges the results of the background check once it is done,
+    /// returning how many posts it found.
+    pub fn poll(&mut self) -> Option<Result<usize>> {
+        let checks = match self.pending.as_ref()?.try_recv() {
+            Ok(checks) => checks,
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => Vec::new(),
+        };
+        self.pending = None;
+        self.failures.clear();
+
+        let mut found = 0;
+        for (feed, posts) in checks {
+            match posts {
+                Ok(posts) => {
+                    for post in posts {
+                        if !self.posts.iter().any(|known| known.url == post.url) {
+                            self.posts.push(post);
+                            found += 1;
+                        }
+                    }
+                }
+                Err(error) => self.failures.push((feed, error)),
+            }
+        }
+
+        Some(self.save().map(|()| found))
+    }
+
+    pub fn checking(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Gemtext timeline of the posts of the subscriptions, newest first and
+    /// grouped by day, posts not seen yet being marked. Days are labelled
+    /// with how long ago they were, unless `absolute`.
+    pub fn to_gemtext(&self, subscriptions: &Subscriptions, absolute: bool) -> String {
+        let mut page = String::from("# Feeds\n\n");
+
+        if self.checking() {
+            page.push_str("Checking the subscriptions for new posts…\n");
+        } else {
+            page.push_str("=> about:feeds?check Check for new posts\n");
+        }
+
+        for (feed, error) in &self.failures {
+            page.push_str(&format!(
+                "=> {} Could not check {}: {}\n",
+                feed, feed, error
+            ));
+        }
+
+        let mut posts = self
+            .posts
+            .iter()
+            .filter(|post| subscriptions.contains(&post.feed))
+            .collect::<Vec<_>>();
+        posts.sort_by(|a, b| b.date.cmp(&a.date));
+
+        if posts.is_empty() {
+            page.push_str(
+                "\nNo posts yet. Subscribe to a gemlog with the subscribe key, its posts \
+                 show up here.\n",
+            );
+        }
+
+        let mut day = None;
+        for post in posts {
+            if day != Some(&post.date) {
+                let heading = dates::parse_date(&post.date).map_or_else(
+                    || post.date.clone(),
+                    |date| dates::day_heading(date, absolute),
+                );
+                page.push_str(&format!("\n## {}\n", heading));
+                day = Some(&post.date);
+            }
+
+            let feed = subscriptions.title(&post.feed).unwrap_or(&post.feed);
+            page.push_str(&format!(
+                "=> {} {}{} · {}\n",
+                post.url,
+                if post.new { "● " } else { "" },
+                post.title,
+                feed
+            ));
+        }
+
+        page
+    }
+
+    pub fn mark_seen(&mut self) -> Result<()> {
+        if !self.posts.iter().any(|post| post.new) {
+            return Ok(());
+        }
+
+        self.posts.iter_mut().for_each(|post| post.new = false);
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let text = self
+            .posts
+            .iter()
+            .map(|post| {
+                format!(
+                    "{}\t{}\t{}\t{}\t{}\n",
+                    post.date,
+                    if post.new { 1 } else { 0 },
+                    post.url,
+                    post.feed,
+                    post.title.replace(['\t', '\n'], " ")
+                )
+            })
+            .collect::<String>();
+
+        dirs::write_data(POSTS_FILE, &text)
+    }
+}
+
+fn read_posts(response: Response, feed: &str) -> Result<Vec<Post>, String> {
+    let base = response.url.clone();
+
+    // XML declares its own encoding, which is UTF-8 for the feeds out there
+    let posts = if ATOM_MIMES.contains(&response.mime()) {
+        atom_posts(&String::from_utf8_lossy(&response.body))
+    } else {
+        gemtext_posts(&response.text().map_err(|error| error.to_string())?)
+    };
+
+    Ok(posts
+        .into_iter()
+        .filter_map(|(date, url, title)| {
+            // The timeline is an about: page, whose links may run actions
+            let url = base.join(&url).ok().filter(|url| url.scheme() != "about")?;
+            Some(Post {
+                date,
+                url: url.to_string(),
+                feed: feed.to_owned(),
+                title,
+                new: true,
+            })
+        })
+        .collect())
+}
+
+/// Links whose label starts with a `YYYY-MM-DD` date, as the subscription
+/// convention has it, or pointing to a gemlog file named after its date.
+fn gemtext_posts(text: &str) -> Vec<(String, String, String)> {
+    Document::parse(text)
+        .links()
+        .filter_map(|(url, label)| {
+            if let Some(date) = label.and_then(dates::parse_date) {
+                let title = label?[10..]
+                    .trim_start_matches([' ', '-', ':', '–', '—'])
+                    .trim();
+
+                return Some((date.to_string(), url.to_owned(), title.to_owned()));
+            }
+
+            let name = url.trim_end_matches('/').rsplit('/').next()?;
+            let date = dates::parse_date(name)?;
+            let title = label.unwrap_or(name).trim();
+
+            Some((date.to_string(), url.to_owned(), title.to_owned()))
+        })
+        .collect()
+}
+
+fn atom_posts(text: &str) -> Vec<(String, String, String)> {
+    let entry = Regex::new(r"(?s)<entry\b.*?</entry>").unwrap();
+    let title = Regex::new(r"(?s)<title[^>]*>(.*?)</title>").unwrap();
+    let link = Regex::new(r#"<link\b[^>]*\bhref=["']([^"']*)["']"#).unwrap();
+    let date = Regex::new(r"<(?:updated|published)>([^<]+)<").unwrap();
+
+    entry
+        .find_iter(text)
+        .filter_map(|entry| {
+            let entry = entry.as_str();
+            let url = link.captures(entry)?[1].to_owned();
+            let date = dates::parse_timestamp(&date.captures(entry)?[1])?.to_string();
+            let title = title
+                .captures(entry)
+                .map_or_else(|| url.clone(), |title| unescape_xml(&title[1]));
+            // A title spread over lines would add lines to the timeline
+            let title = single_line(&title);
+
+            Some((date, unescape_xml(&url), title))
+        })
+        .collect()
+}
+
+fn unescape_xml(text: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|text| text.strip_suffix("]]>"))
+        .map_or_else(
+            || {
+                text.replace("&lt;", "<")
+                    .replace("&gt;", ">")
+                    .replace("&quot;", "\"")
+                    .replace("&apos;", "'")
+                    .replace("&amp;", "&")
+            },
+            str::to_owned,
+        );
+
+    text.trim().to_owned()
+}
+
+/// Folds runs of whitespace and control characters, newlines included, to a
+/// single space.
+fn single_line(text: &str) -> String {
+    text.split(|char: char| char.is_whitespace() || char.is_control())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atom_titles_stay_on_one_line() {
+        let posts = atom_posts(
+            "<feed><entry>\n<title>Hi\n=> about:site?host=evil.example Read\tmore</title>\n\
+             <link href=\"gemini://example.org/hi.gmi\"/>\n\
+             <updated>2024-05-01T10:00:00Z</updated>\n</entry></feed>",
+        );
+
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].1, "gemini://example.org/hi.gmi");
+        assert_eq!(posts[0].2, "Hi => about:site?host=evil.example Read more");
+    }
+}
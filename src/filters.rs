@@ -0,0 +1,87 @@
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::config::from_str_option;
+
+/// Transformation applied to pages from a host before they are rendered.
+#[derive(Deserialize)]
+pub struct Filter {
+    /// Host the filter applies to, or `*` for every host.
+    pub host: String,
+    /// Removes every line matching the pattern.
+    #[serde(default, deserialize_with = "from_str_option")]
+    pub hide_lines: Option<Regex>,
+    /// Removes the preformatted blocks whose alt text matches the pattern,
+    /// e.g. `""` to match all of them.
+    #[serde(default, deserialize_with = "from_str_option")]
+    pub hide_preformatted: Option<Regex>,
+    /// Removes everything from the first line matching the pattern to the end
+    /// of the page, e.g. navigation footers.
+    #[serde(default, deserialize_with = "from_str_option")]
+    pub hide_from: Option<Regex>,
+}
+
+impl Filter {
+    fn matches_host(&self, host: &str) -> bool {
+        self.host == "*" || self.host.eq_ignore_ascii_case(host)
+    }
+
+    fn apply(&self, body: &str) -> String {
+        let mut lines = Vec::new();
+        let mut hiding_block = false;
+        let mut in_block = false;
+
+        for line in body.lines() {
+            if let Some(pattern) = &self.hide_from
+                && !in_block
+                && pattern.is_match(line)
+            {
+                break;
+            }
+
+            if let Some(alt) = line.strip_prefix("```") {
+                if in_block {
+                    in_block = false;
+                    if hiding_block {
+                        hiding_block = false;
+                        continue;
+                    }
+                } else {
+                    in_block = true;
+                    hiding_block = self
+                        .hide_preformatted
+                        .as_ref()
+                        .is_some_and(|pattern| pattern.is_match(alt.trim()));
+                }
+            }
+
+            if hiding_block {
+                continue;
+            }
+
+            if let Some(pattern) = &self.hide_lines
+                && !in_block
+                && pattern.is_match(line)
+            {
+                continue;
+            }
+
+            lines.push(line);
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Applies every filter configured for the host, returning `None` when no
+/// filter applies to it.
+pub fn apply(filters: &[Filter], host: &str, body: &str) -> Option<String> {
+    let mut matching = filters
+        .iter()
+        .filter(|filter| filter.matches_host(host))
+        .peekable();
+
+    matching.peek()?;
+
+    Some(matching.fold(body.to_owned(), |body, filter| filter.apply(&body)))
+}
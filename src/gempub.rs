@@ -0,0 +1,216 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Cursor, Read},
+    path::Path,
+};
+
+use color_eyre::Result;
+use zip::ZipArchive;
+
+use crate::dirs;
+
+pub const MIME: &str = "application/gpub+zip";
+
+const METADATA_FILE: &str = "metadata.txt";
+const DEFAULT_INDEX: &str = "index.gmi";
+const POSITIONS_FILE: &str = "gempub_positions";
+
+/// An opened Gempub ebook.
+///
+/// https://codeberg.org/oppenlab/gempub
+pub struct Gempub {
+    archive: ZipArchive<Cursor<Vec<u8>>>,
+    metadata: HashMap<String, String>,
+    // The index is always the first chapter, followed by the archive entries
+    // it links to in order
+    chapters: Vec<String>,
+}
+
+impl Gempub {
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::from_bytes(fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+
+        let metadata = read_entry(&mut archive, METADATA_FILE)
+            .map(|text| parse_metadata(&text))
+            .unwrap_or_default();
+
+        let index = metadata
+            .get("index")
+            .map(|index| index.trim_start_matches("./").to_owned())
+            .unwrap_or_else(|| String::from(DEFAULT_INDEX));
+        let base = index.rsplit_once('/').map_or("", |(base, _)| base);
+
+        let mut chapters = vec![index.clone()];
+
+        for line in read_entry(&mut archive, &index)?.lines() {
+            let Some(target) = line
+                .strip_prefix("=>")
+                .and_then(|link| link.split_whitespace().next())
+            else {
+                continue;
+            };
+
+            let Some(path) = join(base, target) else {
+                continue;
+            };
+
+            if archive.index_for_name(&path).is_some() && !chapters.contains(&path) {
+                chapters.push(path);
+            }
+        }
+
+        Ok(Self {
+            archive,
+            metadata,
+            chapters,
+        })
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.metadata.get("title").map(String::as_str)
+    }
+
+    pub fn chapter_count(&self) -> usize {
+        self.chapters.len()
+    }
+
+    /// Chapter a link found in the given chapter points to, if it targets
+    /// one.
+    pub fn resolve(&self, chapter: usize, target: &str) -> Option<usize> {
+        let base = self.chapters[chapter.min(self.chapters.len() - 1)]
+            .rsplit_once('/')
+            .map_or("", |(base, _)| base);
+        let path = join(base, target)?;
+
+        self.chapters.iter().position(|chapter| *chapter == path)
+    }
+
+    pub fn read_chapter(&mut self, chapter: usize) -> Result<String> {
+        let path = self.chapters[chapter.min(self.chapters.len() - 1)].clone();
+        read_entry(&mut self.archive, &path)
+    }
+}
+
+/// Archive path of a relative link target found in a file under `base`.
+fn join(base: &str, target: &str) -> Option<String> {
+    if target.contains("://") || target.starts_with('/') {
+        return None;
+    }
+
+    let mut segments = base
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+
+    for segment in target.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    Some(segments.join("/"))
+}
+
+fn read_entry(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> Result<String> {
+    let mut text = String::new();
+    archive.by_name(name)?.read_to_string(&mut text)?;
+    Ok(text)
+}
+
+fn parse_metadata(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_owned(), value.trim().to_owned()))
+        .collect()
+}
+
+/// Reading position inside a book: the current chapter and the scroll offset
+/// of every chapter visited so far.
+#[derive(Clone, Default)]
+pub struct Position {
+    pub chapter: usize,
+    scrolls: Vec<usize>,
+}
+
+impl Position {
+    pub fn scroll(&self, chapter: usize) -> usize {
+        self.scrolls.get(chapter).copied().unwrap_or_default()
+    }
+
+    pub fn set_scroll(&mut self, chapter: usize, scroll: usize) {
+        if self.scrolls.len() <= chapter {
+            self.scrolls.resize(chapter + 1, 0);
+        }
+        self.scrolls[chapter] = scroll;
+    }
+}
+
+/// Reading positions of every book opened so far, persisted in the data
+/// directory as `{source}\t{chapter}\t{scroll,...}` lines.
+#[derive(Default)]
+pub struct Positions {
+    books: HashMap<String, Position>,
+}
+
+impl Positions {
+    pub fn load() -> Self {
+        let Some(text) = dirs::read_data(POSITIONS_FILE) else {
+            return Self::default();
+        };
+
+        let books = text
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let source = fields.next()?.to_owned();
+                let chapter = fields.next()?.parse().ok()?;
+                let scrolls = fields
+                    .next()
+                    .unwrap_or_default()
+                    .split(',')
+                    .filter_map(|scroll| scroll.parse().ok())
+                    .collect();
+
+                Some((source, Position { chapter, scrolls }))
+            })
+            .collect();
+
+        Self { books }
+    }
+
+    pub fn get(&self, source: &str) -> Position {
+        self.books.get(source).cloned().unwrap_or_default()
+    }
+
+    pub fn set(&mut self, source: String, position: Position) -> Result<()> {
+        self.books.insert(source, position);
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let text = self
+            .books
+            .iter()
+            .map(|(source, position)| {
+                let scrolls = position
+                    .scrolls
+                    .iter()
+                    .map(usize::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",");
+                format!("{}\t{}\t{}\n", source, position.chapter, scrolls)
+            })
+            .collect::<String>();
+
+        dirs::write_data(POSITIONS_FILE, &text)
+    }
+}
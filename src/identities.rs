@@ -0,0 +1,247 @@
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::{Result, eyre::bail};
+use gemini_client::Identity;
+
+use crate::{dirs, navigation::encode};
+
+const IDENTITIES_DIR: &str = "identities";
+const SCOPES_FILE: &str = "scopes";
+
+/// Client certificates, each one stored in the `identities` data directory
+/// as `{name}.pem`, along with the URL prefixes they are presented to as
+/// `{prefix}\t{name}` lines of its `scopes` file.
+#[derive(Default)]
+pub struct Identities {
+    dir: Option<PathBuf>,
+    identities: Vec<Identity>,
+    scopes: Vec<(String, String)>,
+}
+
+impl Identities {
+    pub fn load() -> Self {
+        let Some(dir) = dirs::data_dir().map(|dir| dir.join(IDENTITIES_DIR)) else {
+            return Self::default();
+        };
+
+        let mut identities = fs::read_dir(&dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != "pem" {
+                    return None;
+                }
+                let name = path.file_stem()?.to_str()?.to_owned();
+
+                Identity::from_pem(&name, fs::read_to_string(&path).ok()?).ok()
+            })
+            .collect::<Vec<_>>();
+        identities.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let scopes = fs::read_to_string(dir.join(SCOPES_FILE))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let (prefix, name) = line.split_once('\t')?;
+                Some((prefix.to_owned(), name.to_owned()))
+            })
+            .collect();
+
+        Self {
+            dir: Some(dir),
+            identities,
+            scopes,
+        }
+    }
+
+    /// Identity to present for the URL, the one with the longest matching
+    /// prefix.
+    pub fn for_url(&self, url: &str) -> Option<&Identity> {
+        let (_, name) = self
+            .scopes
+            .iter()
+            .filter(|(prefix, _)| url.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())?;
+
+        self.named(name)
+    }
+
+    pub fn named(&self, name: &str) -> Option<&Identity> {
+        self.identities
+            .iter()
+            .find(|identity| identity.name == name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.identities
+            .iter()
+            .map(|identity| identity.name.as_str())
+    }
+
+    /// Generates a new identity. Names are used as file names, so they are
+    /// limited to letters, digits, `-` and `_`.
+    pub fn create(&mut self, name: &str) -> Result<()> {
+        if !valid_name(name) {
+            bail!("Identity names can only contain letters, digits, - and _");
+        }
+        if self.identities.iter().any(|identity| identity.name == name) {
+            bail!("An identity named {} already exists", name);
+        }
+
+        let identity = Identity::generate(name)?;
+
+        if let Some(dir) = &self.dir {
+            fs::create_dir_all(dir)?;
+            write_private(&dir.join(format!("{}.pem", name)), identity.to_pem())?;
+        }
+
+        self.identities.push(identity);
+        self.identities.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(())
+    }
+
+    pub fn delete(&mut self, name: &str) -> Result<()> {
+        // The name ends up in a path, which must stay in the directory
+        if !valid_name(name) {
+            bail!("Identity names can only contain letters, digits, - and _");
+        }
+        if !self.identities.iter().any(|identity| identity.name == name) {
+            bail!("There is no identity named {}", name);
+        }
+
+        if let Some(dir) = &self.dir {
+            let _ = fs::remove_file(dir.join(format!("{}.pem", name)));
+        }
+
+        self.identities.retain(|identity| identity.name != name);
+        self.scopes.retain(|(_, scope)| scope != name);
+        self.save()
+    }
+
+    /// Presents the identity to every URL starting with the prefix, in place
+    /// of the one used there until now.
+    pub fn scope(&mut self, name: &str, prefix: &str) -> Result<()> {
+        if !valid_name(name) {
+            bail!("Identity names can only contain letters, digits, - and _");
+        }
+        // Prefixes are saved one per line, and listed as links
+        if prefix.contains(|char: char| char.is_whitespace() || char.is_control()) {
+            bail!("URL prefixes cannot contain spaces or control characters");
+        }
+        if !self.identities.iter().any(|identity| identity.name == name) {
+            bail!("There is no identity named {}", name);
+        }
+
+        self.scopes.retain(|(scope, _)| scope != prefix);
+        self.scopes.push((prefix.to_owned(), name.to_owned()));
+        self.save()
+    }
+
+    pub fn unscope(&mut self, prefix: &str) -> Result<()> {
+        self.scopes.retain(|(scope, _)| scope != prefix);
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+
+        let text = self
+            .scopes
+            .iter()
+            .map(|(prefix, name)| format!("{}\t{}\n", prefix, name))
+            .collect::<String>();
+
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(SCOPES_FILE), text)?;
+
+        Ok(())
+    }
+
+    /// Gemtext page listing the identities and where they are used, with
+    /// links to manage them.
+    pub fn to_gemtext(&self) -> String {
+        let mut page = String::from("# Identities\n\n");
+        page.push_str(
+            "Identities are client certificates presented to capsules that ask for one. \
+             Create one by opening about:identities?new=<name>.\n",
+        );
+
+        if self.identities.is_empty() {
+            page.push_str("\nNo identities yet.\n");
+        }
+
+        for identity in &self.identities {
+            page.push_str(&format!(
+                "\n## {}\n\nSHA-256 {}\n",
+                identity.name, identity.certificate.fingerprint
+            ));
+
+            for (prefix, _) in self
+                .scopes
+                .iter()
+                .filter(|(_, name)| name == &identity.name)
+            {
+                page.push_str(&format!(
+                    "=> {0} Used for {0}\n=> about:identities?forget={1} Stop using it there\n",
+                    prefix,
+                    encode(prefix)
+                ));
+            }
+
+            page.push_str(&format!(
+                "=> about:identities?delete={} Delete {}\n",
+                encode(&identity.name),
+                identity.name
+            ));
+        }
+
+        page
+    }
+
+    /// Gemtext listing the identities that can be used for the URL, for
+    /// pages asking for a client certificate.
+    pub fn choices(&self, url: &str) -> String {
+        let mut page = String::new();
+
+        for identity in &self.identities {
+            page.push_str(&format!(
+                "=> about:identities?use={}&for={} Use {}\n",
+                encode(&identity.name),
+                encode(url),
+                identity.name
+            ));
+        }
+
+        page.push_str("=> about:identities Manage identities\n");
+        page
+    }
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|char| char.is_alphanumeric() || char == '-' || char == '_')
+}
+
+/// Writes the file readable by its owner only, since it holds a private key.
+fn write_private(path: &Path, contents: &str) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    options.open(path)?.write_all(contents.as_bytes())?;
+
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use std::{
+    env,
+    path::PathBuf,
+    sync::mpsc::{Receiver, Sender},
+};
+
+use crate::dirs;
+
+pub enum Command {
+    Open(String),
+    OpenTab(String),
+    Reload,
+    DumpCurrent,
+}
+
+impl Command {
+    fn parse(line: &str) -> Option<Self> {
+        let (name, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let argument = argument.trim();
+
+        match name {
+            "open" if !argument.is_empty() => Some(Self::Open(argument.to_owned())),
+            "open-tab" if !argument.is_empty() => Some(Self::OpenTab(argument.to_owned())),
+            "reload" => Some(Self::Reload),
+            "dump-current" => Some(Self::DumpCurrent),
+            _ => None,
+        }
+    }
+}
+
+pub struct Request {
+    pub command: Command,
+    reply: Sender<String>,
+}
+
+impl Request {
+    pub fn reply(self, message: String) {
+        let _ = self.reply.send(message);
+    }
+}
+
+/// Local control socket that lets other programs drive a running instance.
+///
+/// Each connection sends a single command line (`open <url>`,
+/// `open-tab <url>`, `reload` or `dump-current`) and receives the reply
+/// before the socket is closed.
+pub struct ControlSocket {
+    path: PathBuf,
+    requests: Receiver<Request>,
+}
+
+impl ControlSocket {
+    pub fn pending(&self) -> Vec<Request> {
+        self.requests.try_iter().collect()
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Path of the socket in the runtime directory, or in the state directory
+/// without one. Both belong to the user: in the shared temporary directory,
+/// another user could bind it first and receive the URLs sent to it.
+pub fn socket_path() -> Option<PathBuf> {
+    let user = env::var("USER").unwrap_or_default();
+
+    env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .or_else(dirs::state_dir)
+        .map(|dir| dir.join(format!("dioscuri-{}.sock", user)))
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::{
+        fs::{self, DirBuilder, Permissions},
+        io::{self, BufRead, BufReader, Write},
+        os::unix::{
+            fs::{DirBuilderExt, PermissionsExt},
+            net::{UnixListener, UnixStream},
+        },
+        sync::mpsc::{self, Sender},
+        thread,
+    };
+
+    use color_eyre::{Result, eyre::bail};
+
+    use super::{Command, ControlSocket, Request, socket_path};
+
+    /// Sends a command to the instance listening on the control socket, if any.
+    pub fn send(command: &str) -> Option<Result<String>> {
+        let mut stream = UnixStream::connect(socket_path()?).ok()?;
+
+        let reply = writeln!(stream, "{}", command).and_then(|()| {
+            let mut reply = String::new();
+            BufReader::new(&stream).read_line(&mut reply)?;
+            Ok(reply.trim_end().to_owned())
+        });
+
+        Some(reply.map_err(Into::into))
+    }
+
+    impl ControlSocket {
+        pub fn bind() -> Result<Self> {
+            let Some(path) = socket_path() else {
+                bail!("No runtime or state directory to put the control socket in");
+            };
+            if let Some(dir) = path.parent() {
+                DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+                // The state directory may have been created already, by the log
+                fs::set_permissions(dir, Permissions::from_mode(0o700))?;
+            }
+
+            if path.exists() {
+                if UnixStream::connect(&path).is_ok() {
+                    bail!("Another instance is listening on {}", path.display());
+                }
+                fs::remove_file(&path)?;
+            }
+
+            let listener = UnixListener::bind(&path)?;
+            let (sender, requests) = mpsc::channel();
+
+            thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let sender = sender.clone();
+                    thread::spawn(move || handle_stream(stream, sender));
+                }
+            });
+
+            Ok(Self { path, requests })
+        }
+    }
+
+    fn handle_stream(stream: UnixStream, sender: Sender<Request>) -> io::Result<()> {
+        let mut line = String::new();
+        BufReader::new(&stream).read_line(&mut line)?;
+
+        let reply = match Command::parse(line.trim()) {
+            Some(command) => {
+                let (reply, response) = mpsc::channel();
+                if sender.send(Request { command, reply }).is_err() {
+                    return Ok(());
+                }
+                response.recv().unwrap_or_default()
+            }
+            None => format!("error: unknown command {:?}", line.trim()),
+        };
+
+        (&stream).write_all(reply.as_bytes())?;
+        (&stream).write_all(b"\n")
+    }
+}
+
+#[cfg(unix)]
+pub use platform::send;
+
+#[cfg(not(unix))]
+pub fn send(_command: &str) -> Option<color_eyre::Result<String>> {
+    None
+}
+
+#[cfg(not(unix))]
+impl ControlSocket {
+    pub fn bind() -> color_eyre::Result<Self> {
+        color_eyre::eyre::bail!("The control socket is only supported on Unix platforms")
+    }
+}
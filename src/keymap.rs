@@ -0,0 +1,553 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Deserializer, de};
+
+/// What keys do in the normal mode, named as in the `[keys]` table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
+    PageUp,
+    PageDown,
+    HalfPageUp,
+    HalfPageDown,
+    Top,
+    Bottom,
+    NextLink,
+    PreviousLink,
+    FollowLink,
+    EditAddress,
+    Find,
+    NextMatch,
+    PreviousMatch,
+    NextChapter,
+    PreviousChapter,
+    Back,
+    Forward,
+    Reload,
+    ForceReload,
+    AutoRefresh,
+    OpenInNewTab,
+    NewTab,
+    DuplicateTab,
+    CloseTab,
+    NextTab,
+    PreviousTab,
+    Bookmark,
+    Bookmarks,
+    Subscribe,
+    Feeds,
+    UpdateMoved,
+    History,
+    HistoryTree,
+    CopyUrl,
+    CopyLink,
+    PreviewLink,
+    SelectLines,
+    Clip,
+    ViewSource,
+    ToggleFilters,
+    ToggleReader,
+    ToggleOffline,
+    SavePage,
+    ExportPage,
+    Upload,
+    EditPage,
+    OpenInPager,
+    OpenExternally,
+    Download,
+    Certificate,
+    PageInfo,
+    Help,
+    DebugConsole,
+    Widen,
+    Narrow,
+    Unfocus,
+    Quit,
+}
+
+/// Every action with its default keys and what it does, in the order of the
+/// help page.
+const ACTIONS: [(Action, &[&str], &str); 61] = [
+    (Action::ScrollUp, &["up"], "Scroll up"),
+    (Action::ScrollDown, &["down"], "Scroll down"),
+    (
+        Action::ScrollLeft,
+        &["left"],
+        "Scroll the preformatted blocks left",
+    ),
+    (
+        Action::ScrollRight,
+        &["right"],
+        "Scroll the preformatted blocks right",
+    ),
+    (Action::PageUp, &["pgup"], "Scroll a page up"),
+    (Action::PageDown, &["pgdn"], "Scroll a page down"),
+    (Action::HalfPageUp, &["ctrl-u"], "Scroll half a page up"),
+    (Action::HalfPageDown, &["ctrl-d"], "Scroll half a page down"),
+    (Action::Top, &["home"], "Go to the top"),
+    (Action::Bottom, &["end"], "Go to the bottom"),
+    (Action::NextLink, &["tab"], "Focus the next link"),
+    (
+        Action::PreviousLink,
+        &["backtab"],
+        "Focus the previous link",
+    ),
+    (Action::FollowLink, &["enter"], "Follow the focused link"),
+    (Action::EditAddress, &["/"], "Edit the address"),
+    (Action::Find, &["f"], "Find in the page"),
+    (Action::NextMatch, &["n"], "Next match"),
+    (Action::PreviousMatch, &["N"], "Previous match"),
+    (Action::NextChapter, &["]"], "Next chapter of a book"),
+    (
+        Action::PreviousChapter,
+        &["["],
+        "Previous chapter of a book",
+    ),
+    (Action::Back, &["H"], "Back"),
+    (Action::Forward, &["L"], "Forward"),
+    (
+        Action::Reload,
+        &["r"],
+        "Reload, keeping the scroll position",
+    ),
+    (
+        Action::ForceReload,
+        &["R"],
+        "Reload without using the cache",
+    ),
+    (
+        Action::AutoRefresh,
+        &["a"],
+        "Cycle the auto-refresh interval",
+    ),
+    (
+        Action::OpenInNewTab,
+        &["o"],
+        "Open the focused link in a new tab",
+    ),
+    (Action::NewTab, &["T"], "New tab"),
+    (Action::DuplicateTab, &["ctrl-shift-d"], "Duplicate the tab"),
+    (Action::CloseTab, &["x"], "Close the tab"),
+    (Action::NextTab, &[">"], "Next tab"),
+    (Action::PreviousTab, &["<"], "Previous tab"),
+    (Action::Bookmark, &["b"], "Bookmark the page"),
+    (Action::Bookmarks, &["B"], "Open the bookmarks"),
+    (Action::Subscribe, &["s"], "Subscribe to the page"),
+    (Action::Feeds, &["F"], "Open the posts of the subscriptions"),
+    (
+        Action::UpdateMoved,
+        &["u"],
+        "Follow a permanent redirect in the subscriptions and bookmarks",
+    ),
+    (Action::History, &["h"], "Open the history"),
+    (
+        Action::HistoryTree,
+        &["V"],
+        "Open every branch of the tab history",
+    ),
+    (
+        Action::CopyUrl,
+        &["y"],
+        "Copy the URL, or the selected lines",
+    ),
+    (
+        Action::CopyLink,
+        &["Y"],
+        "Copy the target of the focused link",
+    ),
+    (Action::PreviewLink, &["P"], "Preview the focused link"),
+    (Action::SelectLines, &["m"], "Select lines to copy"),
+    (
+        Action::Clip,
+        &["c"],
+        "Clip the paragraph into the notes file",
+    ),
+    (Action::ViewSource, &["v"], "View the source"),
+    (
+        Action::ToggleFilters,
+        &["t"],
+        "Toggle the filters of the page",
+    ),
+    (
+        Action::ToggleReader,
+        &["A"],
+        "Toggle the reader mode, for screen readers",
+    ),
+    (
+        Action::ToggleOffline,
+        &["ctrl-o"],
+        "Toggle the offline mode, reading from the cache",
+    ),
+    (Action::SavePage, &["w"], "Save the page"),
+    (
+        Action::ExportPage,
+        &["W"],
+        "Export the page to HTML or Markdown",
+    ),
+    (Action::Upload, &["U"], "Upload a file in place of the page"),
+    (Action::EditPage, &["E"], "Edit the page and upload it"),
+    (Action::OpenInPager, &["p"], "Read the page in the pager"),
+    (
+        Action::OpenExternally,
+        &["O"],
+        "Open the page with the handler of its type",
+    ),
+    (
+        Action::Download,
+        &["d"],
+        "Download the focused link, or the page",
+    ),
+    (
+        Action::Certificate,
+        &["i"],
+        "Show the certificate of the host",
+    ),
+    (
+        Action::PageInfo,
+        &["I"],
+        "Show the page info and request timings",
+    ),
+    (Action::Help, &["?"], "Show this page"),
+    (
+        Action::DebugConsole,
+        &["D"],
+        "Toggle the log under the page",
+    ),
+    (Action::Widen, &["+"], "Widen the body"),
+    (Action::Narrow, &["-"], "Narrow the body"),
+    (
+        Action::Unfocus,
+        &["esc"],
+        "Unfocus, clear the search, or exit",
+    ),
+    (Action::Quit, &["ctrl-c"], "Exit"),
+];
+
+/// A key along with the modifiers held, written like `j`, `G`, `ctrl-d`,
+/// `alt-left` or `pgdn` in the configuration file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyPress {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyPress {
+    pub fn new(mut code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers =
+            modifiers.intersection(KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+
+        // Shift is part of the character, or of the back tab, already
+        let shifted = modifiers.contains(KeyModifiers::SHIFT);
+        match code {
+            KeyCode::Char(char) if shifted => {
+                code = KeyCode::Char(char.to_uppercase().next().unwrap_or(char));
+                modifiers.remove(KeyModifiers::SHIFT);
+            }
+            KeyCode::Tab if shifted => {
+                code = KeyCode::BackTab;
+                modifiers.remove(KeyModifiers::SHIFT);
+            }
+            KeyCode::BackTab | KeyCode::Char(_) => modifiers.remove(KeyModifiers::SHIFT),
+            _ => {}
+        }
+
+        Self { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for KeyPress {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl FromStr for KeyPress {
+    type Err = String;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut name = key;
+        // A lone `-` is the minus key rather than a modifier
+        while let Some((modifier, rest)) = name.split_once('-')
+            && !rest.is_empty()
+        {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("Unknown modifier in the key {}", key)),
+            };
+            name = rest;
+        }
+
+        let code = match name.to_ascii_lowercase().as_str() {
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "pgup" | "pageup" => KeyCode::PageUp,
+            "pgdn" | "pagedown" => KeyCode::PageDown,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            "del" | "delete" => KeyCode::Delete,
+            "ins" | "insert" => KeyCode::Insert,
+            lower => match (lower.strip_prefix('f'), name.chars().count()) {
+                (Some(number), _) if !number.is_empty() => {
+                    KeyCode::F(number.parse().map_err(|_| format!("Unknown key {}", key))?)
+                }
+                (_, 1) => KeyCode::Char(name.chars().next().unwrap_or_default()),
+                _ => return Err(format!("Unknown key {}", key)),
+            },
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl Display for KeyPress {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "CTRL-"),
+            (KeyModifiers::ALT, "ALT-"),
+            (KeyModifiers::SHIFT, "SHIFT-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                formatter.write_str(name)?;
+            }
+        }
+
+        match self.code {
+            KeyCode::Char('/') => formatter.write_str("SLASH"),
+            KeyCode::Char(' ') => formatter.write_str("SPACE"),
+            KeyCode::Char(char) if self.modifiers.is_empty() => write!(formatter, "{}", char),
+            // Modified letters are written in capitals either way
+            KeyCode::Char(char) if char.is_uppercase() => write!(formatter, "SHIFT-{}", char),
+            KeyCode::Char(char) => write!(formatter, "{}", char.to_ascii_uppercase()),
+            KeyCode::PageUp => formatter.write_str("PGUP"),
+            KeyCode::PageDown => formatter.write_str("PGDN"),
+            KeyCode::F(number) => write!(formatter, "F{}", number),
+            code => write!(formatter, "{}", format!("{:?}", code).to_uppercase()),
+        }
+    }
+}
+
+/// Keys pressed one after the other, like `g g`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sequence(pub Vec<KeyPress>);
+
+impl FromStr for Sequence {
+    type Err = String;
+
+    fn from_str(sequence: &str) -> Result<Self, Self::Err> {
+        let keys = sequence
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            return Err(String::from("Empty key sequence"));
+        }
+
+        Ok(Self(keys))
+    }
+}
+
+impl Display for Sequence {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        for (index, key) in self.0.iter().enumerate() {
+            if index > 0 {
+                formatter.write_str(" ")?;
+            }
+            write!(formatter, "{}", key)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Where the keys pressed so far lead.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Step {
+    Action(Action),
+    /// The keys start longer sequences, and are bound to the action on
+    /// their own if any.
+    Pending(Option<Action>),
+    Unbound,
+}
+
+/// Key sequences of the actions: the defaults, replaced action by action by
+/// those of the `[keys]` table.
+#[derive(Clone)]
+pub struct Keymap {
+    bindings: HashMap<Action, Vec<Sequence>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = ACTIONS
+            .iter()
+            .map(|(action, keys, _)| {
+                let sequences = keys
+                    .iter()
+                    .map(|keys| keys.parse().expect("the default keys are valid"))
+                    .collect();
+                (*action, sequences)
+            })
+            .collect();
+
+        Self { bindings }
+    }
+}
+
+impl<'de> Deserialize<'de> for Keymap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// Actions take a sequence, or a list of them.
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Sequences {
+            One(String),
+            Many(Vec<String>),
+        }
+
+        let table = HashMap::<Action, Sequences>::deserialize(deserializer)?;
+        let mut keymap = Self::default();
+        for (action, sequences) in table {
+            let sequences = match sequences {
+                Sequences::One(sequence) => vec![sequence],
+                Sequences::Many(sequences) => sequences,
+            };
+            let sequences = sequences
+                .iter()
+                .map(|sequence| sequence.parse())
+                .collect::<Result<Vec<Sequence>, _>>()
+                .map_err(de::Error::custom)?;
+
+            // Keys taken from another action are no longer its defaults
+            for (other, bound) in &mut keymap.bindings {
+                if *other != action {
+                    bound.retain(|sequence| !sequences.contains(sequence));
+                }
+            }
+            keymap.bindings.insert(action, sequences);
+        }
+
+        Ok(keymap)
+    }
+}
+
+impl Keymap {
+    /// Where the keys pressed since the last action lead.
+    pub fn step(&self, keys: &[KeyPress]) -> Step {
+        let mut exact = None;
+        let mut longer = false;
+        for (action, sequences) in &self.bindings {
+            for Sequence(sequence) in sequences {
+                if sequence == keys {
+                    exact = Some(*action);
+                } else if sequence.starts_with(keys) {
+                    longer = true;
+                }
+            }
+        }
+
+        match (exact, longer) {
+            (_, true) => Step::Pending(exact),
+            (Some(action), false) => Step::Action(action),
+            (None, false) => Step::Unbound,
+        }
+    }
+
+    /// Whether the key on its own is bound to the action.
+    pub fn is_bound(&self, action: Action, key: KeyPress) -> bool {
+        self.bindings
+            .get(&action)
+            .is_some_and(|sequences| sequences.iter().any(|Sequence(keys)| keys == &[key]))
+    }
+
+    /// First sequence of the action, as shown in the key hints.
+    pub fn label(&self, action: Action) -> String {
+        self.bindings
+            .get(&action)
+            .and_then(|sequences| sequences.first())
+            .map(Sequence::to_string)
+            .unwrap_or_default()
+    }
+
+    /// Gemtext page listing what every key does, as configured.
+    pub fn to_gemtext(&self) -> String {
+        let mut page = String::from("# Help\n\n## Keys\n\n```\n");
+        for (action, _, description) in ACTIONS {
+            let keys = self
+                .bindings
+                .get(&action)
+                .map(|sequences| {
+                    sequences
+                        .iter()
+                        .map(Sequence::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                })
+                .unwrap_or_default();
+            page.push_str(&format!("{:<13} {}\n", keys, description));
+        }
+        page.push_str("```\n");
+
+        page.push_str(
+            "\nKeys are set in the [keys] table of the configuration file.\n\n\
+             ## Pages\n\n\
+             => about:bookmarks Bookmarks\n\
+             => about:history History\n\
+             => about:feeds Feeds\n\
+             => about:identities Identities\n\
+             => about:cache Cache\n\
+             => about:downloads Downloads\n\
+             => about:tree Tab history\n\
+             => about:rotation Certificate rotation\n\
+             => about:version Version\n",
+        );
+
+        page
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequences_wait_for_their_last_key() {
+        let keymap = toml::from_str::<Keymap>(
+            "top = \"g g\"\nbottom = \"G\"\nhistory = [\"g\", \"ctrl-h\"]\n",
+        )
+        .unwrap();
+        let g = KeyPress::new(KeyCode::Char('g'), KeyModifiers::NONE);
+
+        assert_eq!(keymap.step(&[g]), Step::Pending(Some(Action::History)));
+        assert_eq!(keymap.step(&[g, g]), Step::Action(Action::Top));
+        assert_eq!(
+            keymap.step(&[KeyPress::new(KeyCode::Char('g'), KeyModifiers::SHIFT)]),
+            Step::Action(Action::Bottom)
+        );
+        assert_eq!(
+            keymap.step(&["ctrl-h".parse().unwrap()]),
+            Step::Action(Action::History)
+        );
+        // The default keys of the actions are replaced
+        assert_eq!(keymap.step(&["h".parse().unwrap()]), Step::Unbound);
+        assert_eq!(keymap.step(&["home".parse().unwrap()]), Step::Unbound);
+    }
+}
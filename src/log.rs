@@ -0,0 +1,115 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, Write},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use color_eyre::Result;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::dirs;
+
+const LOG_FILE: &str = "dioscuri.log";
+/// Lines kept in memory for the debug console.
+const RECENT_LINES: usize = 500;
+
+/// Log of the client and the UI, written to a file in the state directory
+/// started afresh each session, with its last lines kept for the debug
+/// console.
+#[derive(Clone, Default)]
+pub struct Log {
+    file: Option<Arc<Mutex<File>>>,
+    recent: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl Log {
+    /// Installs the log as the destination of the events up to `level`.
+    pub fn init(level: LevelFilter) -> Result<Log> {
+        let file = match dirs::state_dir() {
+            Some(dir) if level != LevelFilter::OFF => {
+                fs::create_dir_all(&dir)?;
+                Some(Arc::new(Mutex::new(File::create(dir.join(LOG_FILE))?)))
+            }
+            _ => None,
+        };
+        let log = Log {
+            file,
+            ..Log::default()
+        };
+
+        tracing_subscriber::fmt()
+            .with_writer(log.clone())
+            .with_ansi(false)
+            .with_max_level(level)
+            .try_init()
+            .map_err(|error| color_eyre::eyre::eyre!(error))?;
+
+        Ok(log)
+    }
+
+    /// Last lines logged, oldest first.
+    pub fn recent(&self, count: usize) -> Vec<String> {
+        let recent = self.recent.lock().unwrap_or_else(PoisonError::into_inner);
+        recent
+            .range(recent.len().saturating_sub(count)..)
+            .cloned()
+            .collect()
+    }
+}
+
+impl<'a> MakeWriter<'a> for Log {
+    type Writer = Event<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        Event {
+            log: self,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+/// Formatted event, logged once complete.
+pub struct Event<'a> {
+    log: &'a Log,
+    buffer: Vec<u8>,
+}
+
+impl Write for Event<'_> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Event<'_> {
+    fn drop(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        if let Some(file) = &self.log.file {
+            let _ = file
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .write_all(&self.buffer);
+        }
+
+        let mut recent = self
+            .log
+            .recent
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        for line in String::from_utf8_lossy(&self.buffer).lines() {
+            if recent.len() == RECENT_LINES {
+                recent.pop_front();
+            }
+            recent.push_back(line.to_owned());
+        }
+    }
+}
@@ -1,34 +1,149 @@
-use std::time::{Duration, Instant};
+mod bookmarks;
+mod cache;
+mod cli;
+mod clipboard;
+mod completion;
+mod config;
+mod dates;
+mod dirs;
+mod downloads;
+mod editing;
+mod export;
+mod feeds;
+mod filters;
+mod gempub;
+mod identities;
+mod ipc;
+mod keymap;
+mod log;
+mod navigation;
+mod notes;
+mod prefetch;
+mod pretty;
+mod reader;
+mod render;
+mod rotation;
+mod search;
+mod sites;
+mod subscriptions;
+mod tab;
+mod theme;
+mod visits;
 
-use color_eyre::Result;
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
-use gemini_client::GeminiClient;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{self, IsTerminal, Write},
+    mem,
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use bookmarks::Bookmarks;
+use cache::{Cache, Lookup};
+use chrono::{DateTime, Local};
+use cli::Args;
+use clipboard::Clipboard;
+use color_eyre::{Report, Result, eyre::bail};
+use completion::Suggestion;
+use config::Config;
+use crossterm::{
+    event::{
+        self, DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+        EnableFocusChange, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+        KeyboardEnhancementFlags, MouseEventKind, PopKeyboardEnhancementFlags,
+        PushKeyboardEnhancementFlags,
+    },
+    execute,
+    terminal::{EnterAlternateScreen, enable_raw_mode},
+};
+use downloads::{DOWNLOADS_PAGE, Downloads};
+use editing::History;
+use export::Format;
+use feeds::Feeds;
+use finger_client::FingerClient;
+use gemini_client::{
+    Certificate, DEFAULT_PORT, GeminiClient, GeminiClientError, Response, StaticResolver,
+    TlsStatus, Trust, Upload, gemtext,
+};
+use gempub::{Gempub, Positions};
+use gopher_client::GopherClient;
+use identities::Identities;
+use ipc::{Command, ControlSocket};
+use keymap::{Action, KeyPress, Sequence, Step};
+use log::Log;
+use navigation::{TREE_PAGE, encode};
+use percent_encoding::percent_decode_str;
+use prefetch::Prefetcher;
 use ratatui::{
     DefaultTerminal, Frame,
     buffer::Buffer,
-    layout::{Alignment, Constraint, Layout, Position, Rect},
+    layout::{Alignment, Constraint, Layout, Margin, Position, Rect},
     style::{Style, Stylize},
-    text::{Line, Text},
+    text::{Line, Span, Text},
     widgets::{
-        Block, Paragraph, ScrollDirection, Scrollbar, ScrollbarOrientation, ScrollbarState,
-        StatefulWidget, Widget, Wrap,
+        Block, Borders, Clear, Paragraph, Scrollbar, ScrollbarOrientation, StatefulWidget, Tabs,
+        Widget, Wrap,
     },
 };
+use rotation::{ROTATION_PAGE, Rotation};
+use search::Search;
+use sites::Sites;
+use subscriptions::Subscriptions;
+use tab::{Book, Refresh, Tab};
+use theme::Theme;
+use tracing::{debug, info, trace, warn};
+use unicode_width::UnicodeWidthStr;
+use url::Url;
+use visits::Visits;
 
-const DEFAULT_URL: &str = "gemini://geminiprotocol.net/";
 const UPDATE_TICK_RATE: Duration = Duration::from_millis(300);
-
-#[derive(Default)]
-struct Scroll {
-    value: usize,
-    max: usize,
-    state: ScrollbarState,
-}
+/// How long a key sequence waits for its next key.
+const SEQUENCE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Internal page showing the gemtext piped on the standard input.
+const STDIN_PAGE: &str = "about:stdin";
+/// Link turning the offline mode off and loading the URL of its query.
+const ONLINE_PAGE: &str = "about:online";
+/// Bytes of a page read to preview a link to it, enough for its title.
+const PREVIEW_BYTES: usize = 4096;
+/// Columns the Left and Right keys scroll preformatted blocks by.
+const HORIZONTAL_SCROLL_COLUMNS: isize = 8;
+/// Columns the widen and narrow keys change the body width by.
+const WIDTH_STEP: u16 = 4;
+/// Narrowest the body can be made.
+const MIN_WIDTH: u16 = 20;
 
 #[derive(PartialEq, Eq)]
 enum InputMode {
     Normal,
     Edit,
+    Search,
+    /// Typing the path the current page is saved to.
+    Save,
+    /// Typing the path the current page is exported to.
+    Export,
+    /// Typing the path of the file uploaded in place of the current page.
+    Upload,
+    /// Selecting body lines to copy.
+    Select,
+    /// Looking at the certificate of the current host.
+    Certificate,
+    /// Deciding whether to trust a certificate that changed.
+    Trust,
+    /// Looking at how the current page was fetched.
+    Info,
+}
+
+/// Connection stopped because the host presented a certificate other than
+/// the pinned one.
+struct Untrusted {
+    url: String,
+    host: String,
+    pinned: Certificate,
+    presented: Certificate,
 }
 
 enum Status {
@@ -40,82 +155,602 @@ struct Input {
     value: String,
     index: usize,
     mode: InputMode,
+    /// Completions for the address being edited.
+    suggestions: Vec<Suggestion>,
+    selected: Option<usize>,
+    /// Addresses entered before, recalled with Up and Down.
+    history: History,
 }
 
-impl Default for Input {
-    fn default() -> Self {
+impl Input {
+    fn new(value: String) -> Self {
         Self {
-            value: String::from(DEFAULT_URL),
+            index: value.chars().count(),
+            value,
             mode: InputMode::Edit,
-            index: DEFAULT_URL.len(),
+            suggestions: Vec::new(),
+            selected: None,
+            history: History::default(),
         }
     }
 }
 
 struct App {
+    config: Config,
+    theme: Theme,
     client: GeminiClient,
-    body: String,
-    scroll: Scroll,
+    gopher: GopherClient,
+    finger: FingerClient,
+    control: Option<ControlSocket>,
+    tabs: Vec<Tab>,
+    active: usize,
+    moved: Option<(String, String)>,
+    positions: Positions,
+    subscriptions: Subscriptions,
+    feeds: Feeds,
+    bookmarks: Bookmarks,
+    visits: Visits,
+    identities: Identities,
+    sites: Sites,
+    cache: Cache,
+    downloads: Downloads,
+    prefetcher: Prefetcher,
+    clipboard: Clipboard,
+    /// Speech command reading the current page aloud, in reader mode.
+    speech: Option<process::Child>,
+    /// Whether pages are only read from the cache.
+    offline: bool,
+    notes_path: Option<PathBuf>,
+    message: Option<String>,
+    /// Path typed in the save or upload prompt.
+    save_path: String,
+    /// First and last selected body lines, the last one following the
+    /// cursor.
+    selection: (usize, usize),
+    body_width: u16,
+    body_height: u16,
+    /// Widest the body is laid out, 0 for the whole width. Starts from
+    /// [`Config::max_width`] and is changed with the widen and narrow keys.
+    max_width: u16,
+    /// Whether the terminal has focus, as reported by focus change events.
+    focused: bool,
+    untrusted: Option<Untrusted>,
+    /// Gemtext piped on the standard input, shown as [`STDIN_PAGE`].
+    stdin: Option<String>,
+    /// Whether the screen has to be drawn from scratch, after handing the
+    /// terminal over to another program.
+    clear_screen: bool,
+    log: Log,
+    /// Whether the last lines of the log are shown under the body.
+    debug_console: bool,
+    /// Keys of the sequence being typed in the normal mode.
+    pressed: Vec<KeyPress>,
+    pressed_at: Instant,
     input: Input,
 }
 
 impl App {
-    pub fn new(client: GeminiClient) -> App {
+    pub fn new(config: Config, client: GeminiClient, control: Option<ControlSocket>) -> App {
+        let sites = Sites::load(&config.sites);
+        sites.apply(&client);
+        let mut tab = Tab::default();
+        tab.reader = config.reader_mode;
+
         App {
+            notes_path: config.notes_file.clone().or_else(notes::default_path),
+            input: Input::new(config.start_url.clone()),
+            theme: Theme::from(&config.theme),
+            gopher: GopherClient::new().timeout(Duration::from_secs(config.timeout_secs)),
+            finger: FingerClient::new().timeout(Duration::from_secs(config.timeout_secs)),
+            cache: Cache::load(
+                config.cache_size_mb * 1024 * 1024,
+                Duration::from_secs(config.cache_ttl_secs),
+            ),
+            max_width: config.max_width,
+            offline: config.offline,
+            downloads: Downloads::new(
+                client.clone(),
+                config
+                    .download_dir
+                    .clone()
+                    .or_else(dirs::download_dir)
+                    .unwrap_or_else(|| PathBuf::from(".")),
+            ),
+            // Prefetched pages are only of use from the cache
+            prefetcher: Prefetcher::new(
+                client.clone(),
+                if config.cache_size_mb > 0 {
+                    config.prefetch_links
+                } else {
+                    0
+                },
+            ),
+            config,
             client,
-            body: String::new(),
-            scroll: Scroll::default(),
-            input: Input::default(),
+            control,
+            tabs: vec![tab],
+            active: 0,
+            moved: None,
+            positions: Positions::load(),
+            subscriptions: Subscriptions::load(),
+            feeds: Feeds::load(),
+            bookmarks: Bookmarks::load(),
+            visits: Visits::load(),
+            identities: Identities::load(),
+            sites,
+            clipboard: Clipboard::new(),
+            speech: None,
+            message: None,
+            save_path: String::new(),
+            selection: (0, 0),
+            body_width: 0,
+            body_height: 0,
+            focused: true,
+            untrusted: None,
+            stdin: None,
+            clear_screen: false,
+            log: Log::default(),
+            debug_console: false,
+            pressed: Vec::new(),
+            pressed_at: Instant::now(),
         }
     }
 
+    fn tab(&self) -> &Tab {
+        &self.tabs[self.active]
+    }
+
+    fn tab_mut(&mut self) -> &mut Tab {
+        &mut self.tabs[self.active]
+    }
+
     pub fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
         let mut last_tick = Instant::now();
 
         self.render(terminal)?;
 
-        while let Status::Running(should_render) =
+        while let Status::Running(mut should_render) =
             self.handle_events(UPDATE_TICK_RATE.saturating_sub(last_tick.elapsed()))?
         {
-            if should_render {
-                self.render(terminal)?;
-            }
+            should_render |= self.handle_control_requests();
 
             if last_tick.elapsed() >= UPDATE_TICK_RATE {
+                should_render |= self.tick(last_tick.elapsed());
                 last_tick = Instant::now();
             }
+
+            if should_render {
+                self.render(terminal)?;
+            }
+        }
+
+        for tab in &mut self.tabs {
+            tab.close_book(&mut self.positions)?;
         }
+        self.stop_speech();
 
         Ok(())
     }
 
     fn render(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        if mem::take(&mut self.clear_screen) {
+            terminal.clear()?;
+        }
         terminal.draw(|frame| self.draw_ui(frame))?;
         Ok(())
     }
 
     fn draw_ui(&mut self, frame: &mut Frame) {
-        let [top, bottom] =
-            Layout::vertical([Constraint::Length(3), Constraint::Fill(1)]).areas(frame.area());
+        let command_line_height = if self.tab().search.is_some()
+            || matches!(
+                self.input.mode,
+                InputMode::Save | InputMode::Export | InputMode::Upload
+            ) {
+            1
+        } else {
+            0
+        };
+        let compact =
+            self.config.compact_width > 0 && frame.area().width < self.config.compact_width;
+        let [top, tab_strip, banner, bottom, status_bar, command_line] = Layout::vertical([
+            Constraint::Length(if compact { 1 } else { 3 }),
+            Constraint::Length(1),
+            Constraint::Length(self.offline as u16),
+            Constraint::Fill(1),
+            Constraint::Length(self.config.status_bar as u16),
+            Constraint::Length(command_line_height),
+        ])
+        .areas(frame.area());
+        let [bottom, console] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(if self.debug_console {
+                (bottom.height / 3).max(4)
+            } else {
+                0
+            }),
+        ])
+        .areas(bottom);
+
+        self.draw_address_bar(frame, top, compact);
+        self.draw_tab_strip(frame.buffer_mut(), tab_strip);
+        self.draw_offline_banner(frame.buffer_mut(), banner);
+        self.draw_body(frame.buffer_mut(), bottom, compact);
+        self.draw_debug_console(frame.buffer_mut(), console, compact);
+        self.draw_status_bar(frame.buffer_mut(), status_bar);
+        self.draw_command_line(frame, command_line);
+        self.draw_suggestions(frame.buffer_mut(), top);
+        self.draw_dialog(frame.buffer_mut(), bottom);
+    }
+
+    /// Details of the current page and of the request it took.
+    fn page_info(&self) -> Vec<Line<'static>> {
+        let tab = self.tab();
+        let mut lines = vec![
+            field("URL", tab.location.clone()),
+            field("MIME type", tab.mime.clone()),
+            field("Encoding", tab.encoding.to_owned()),
+            field("Lines", tab.lines.len().to_string()),
+            field("Words", tab.words.to_string()),
+            field(
+                "Reading time",
+                format!("{} min", render::reading_time(tab.words)),
+            ),
+        ];
+
+        let Some(metrics) = tab.metrics else {
+            lines.push(Line::default());
+            lines.push(Line::from(
+                "Not fetched over the network, the page is cached, local or internal.",
+            ));
+            return lines;
+        };
+
+        let duration = |duration: Duration| format!("{:.1} ms", duration.as_secs_f64() * 1000.0);
+        lines.extend([
+            field("Status", metrics.status.to_string()),
+            field(
+                "Certificate",
+                String::from(match metrics.tls {
+                    Some(TlsStatus::Trusted) => "issued by a known authority",
+                    Some(TlsStatus::Pinned) => "pinned on first use",
+                    Some(TlsStatus::AcceptedOnce) => "accepted once over the pinned one",
+                    Some(TlsStatus::Unverified) => "other than the pinned one, accepted by policy",
+                    None => "none",
+                }),
+            ),
+            field("Size", cache::format_size(metrics.size as u64)),
+            Line::default(),
+            field("Resolve", duration(metrics.resolve)),
+            field("Connect", duration(metrics.connect)),
+            field("Handshake", duration(metrics.handshake)),
+            field("First byte", duration(metrics.first_byte)),
+            field("Total", duration(metrics.total)),
+        ]);
+
+        lines
+    }
+
+    /// Draws the last lines of the log, as many as fit.
+    fn draw_debug_console(&self, buffer: &mut Buffer, area: Rect, compact: bool) {
+        if area.is_empty() {
+            return;
+        }
+
+        let block = if compact {
+            Block::new().borders(Borders::TOP).title(" Log ")
+        } else {
+            Block::bordered().title(" Log ")
+        };
+        let lines = self
+            .log
+            .recent(block.inner(area).height as usize)
+            .into_iter()
+            .map(Line::from)
+            .collect::<Vec<_>>();
+
+        Clear.render(area, buffer);
+        Paragraph::new(lines).block(block).render(area, buffer);
+    }
+
+    /// Draws the dialog of the mode over the body: the certificate of the
+    /// current host, the prompt for a changed one, or the page info.
+    fn draw_dialog(&self, buffer: &mut Buffer, body: Rect) {
+        let (title, lines, instructions) = match (&self.input.mode, &self.untrusted) {
+            (InputMode::Certificate, _) => {
+                let host = Url::parse(&self.tab().url)
+                    .ok()
+                    .and_then(|url| url.host_str().map(str::to_owned))
+                    .unwrap_or_default();
+                let mut lines = match self.client.certificate(&host) {
+                    Some(certificate) => certificate_lines(&certificate),
+                    None => vec![Line::from("No certificate was seen for this page.")],
+                };
+                if let Some(address) = self.client.address(&host) {
+                    lines.push(field("Address", address.to_string()));
+                }
+
+                (
+                    format!(" Certificate of {} ", host),
+                    lines,
+                    " <r> - Check a Rotation | <ANY KEY> - Close ",
+                )
+            }
+            (InputMode::Info, _) => (
+                String::from(" Page info "),
+                self.page_info(),
+                " <ANY KEY> - Close ",
+            ),
+            (InputMode::Trust, Some(untrusted)) => {
+                let mut lines = vec![
+                    Line::from(format!(
+                        "{} presented a certificate other than the one pinned when it was \
+                         first visited. Capsules do this when renewing their certificate \
+                         early, but so would someone intercepting the connection.",
+                        untrusted.host
+                    )),
+                    Line::default(),
+                    Line::from("Pinned".bold()),
+                ];
+                lines.extend(certificate_lines(&untrusted.pinned));
+                lines.push(Line::default());
+                lines.push(Line::from("Presented".bold()));
+                lines.extend(certificate_lines(&untrusted.presented));
+
+                (
+                    String::from(" Certificate changed "),
+                    lines,
+                    " <o> - Accept Once | <p> - Accept Permanently | <ESC> - Abort ",
+                )
+            }
+            _ => return,
+        };
+
+        let width = body.width.saturating_sub(4).min(100);
+        let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false });
+        let height = (paragraph.line_count(width.saturating_sub(2)) as u16 + 2).min(body.height);
+        let area = Rect {
+            x: body.x + (body.width - width) / 2,
+            y: body.y + (body.height - height) / 2,
+            width,
+            height,
+        };
+
+        Clear.render(area, buffer);
+        paragraph
+            .block(
+                Block::bordered()
+                    .title(title)
+                    .title_bottom(Line::from(instructions).bold())
+                    .border_style(Style::new().fg(self.theme.accent)),
+            )
+            .style(self.theme.body)
+            .render(area, buffer);
+    }
+
+    /// Draws the address completions in a dropdown over the body, right
+    /// under the address bar.
+    fn draw_suggestions(&self, buffer: &mut Buffer, address_bar: Rect) {
+        if self.input.mode != InputMode::Edit || self.input.suggestions.is_empty() {
+            return;
+        }
+
+        let area = Rect {
+            x: address_bar.x + 1,
+            y: address_bar.bottom(),
+            width: address_bar.width.saturating_sub(2),
+            height: self.input.suggestions.len() as u16 + 2,
+        }
+        .intersection(buffer.area);
+
+        let lines = self
+            .input
+            .suggestions
+            .iter()
+            .enumerate()
+            .map(|(index, suggestion)| {
+                let line = match &suggestion.title {
+                    Some(title) => Line::from(vec![
+                        suggestion.url.clone().into(),
+                        format!("  {}", title).dim(),
+                    ]),
+                    None => Line::from(suggestion.url.clone()),
+                };
+
+                if self.input.selected == Some(index) {
+                    line.reversed()
+                } else {
+                    line
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Clear.render(area, buffer);
+        Paragraph::new(lines)
+            .block(
+                Block::bordered()
+                    .title_bottom(Line::from(" <UP DOWN> - Select | <TAB> - Accept ").bold())
+                    .border_style(Style::new().fg(self.theme.accent)),
+            )
+            .style(self.theme.body)
+            .render(area, buffer);
+    }
+
+    fn draw_tab_strip(&self, buffer: &mut Buffer, area: Rect) {
+        Tabs::new(self.tabs.iter().map(Tab::label))
+            .select(self.active)
+            .style(self.theme.status_line)
+            .highlight_style(Style::new().fg(self.theme.accent).bold().reversed())
+            .render(area, buffer);
+    }
 
-        self.draw_address_bar(frame, top);
-        self.draw_body(frame.buffer_mut(), bottom);
+    /// Draws the banner of the offline mode over the body, telling when the
+    /// page was cached.
+    fn draw_offline_banner(&self, buffer: &mut Buffer, area: Rect) {
+        if area.is_empty() {
+            return;
+        }
+
+        let mut banner = vec![Span::raw("Offline").bold()];
+        if let Some(cached_at) = self.tab().cached_at {
+            banner.push(Span::raw(format!(
+                " · cached at {}",
+                cached_at.format("%Y-%m-%d %H:%M")
+            )));
+        }
+
+        Block::new()
+            .style(self.theme.status_line)
+            .render(area, buffer);
+        let area = area.inner(Margin::new(1, 0));
+        Line::from(banner).render(area, buffer);
+        Line::from(format!(
+            "{} to go online",
+            self.config.keys.label(Action::ToggleOffline)
+        ))
+        .alignment(Alignment::Right)
+        .render(area, buffer);
+    }
+
+    /// Draws the bar describing the current page: its host and how its
+    /// certificate was trusted on the left, the status of its response, its
+    /// MIME type and its size on the right.
+    fn draw_status_bar(&self, buffer: &mut Buffer, area: Rect) {
+        if area.is_empty() {
+            return;
+        }
+
+        let tab = self.tab();
+        let separator = || Span::raw(" · ");
+
+        let url = Url::parse(&tab.location).ok();
+        let mut left = Vec::new();
+        if let Some(host) = url.as_ref().and_then(Url::host_str) {
+            left.push(Span::raw(host.to_owned()));
+        }
+        let tls = match tab.metrics.map(|metrics| metrics.tls) {
+            Some(Some(TlsStatus::Trusted)) => Some(Span::raw("trusted")),
+            Some(Some(TlsStatus::Pinned)) => Some(Span::raw("TOFU")),
+            Some(Some(TlsStatus::AcceptedOnce | TlsStatus::Unverified)) => {
+                Some("cert warning".red().bold())
+            }
+            None if tab.cached_at.is_some() => Some(Span::raw("cached")),
+            _ => None,
+        };
+        if let Some(tls) = tls {
+            if !left.is_empty() {
+                left.push(separator());
+            }
+            left.push(tls);
+        }
+
+        // Cached, local and internal pages only have what they hold
+        let mut right = Vec::new();
+        if let Some(metrics) = tab.metrics {
+            right.push(metrics.status.to_string());
+        }
+        if !tab.mime.is_empty() {
+            right.push(tab.mime.clone());
+        }
+        let size = tab.metrics.map_or(tab.source.len(), |metrics| metrics.size);
+        if size > 0 || tab.metrics.is_some() {
+            right.push(cache::format_size(size as u64));
+        }
+
+        Block::new()
+            .style(self.theme.status_line)
+            .render(area, buffer);
+        let area = area.inner(Margin::new(1, 0));
+        Line::from(left).render(area, buffer);
+        Line::from(right.join(" · "))
+            .alignment(Alignment::Right)
+            .render(area, buffer);
+    }
+
+    fn draw_command_line(&mut self, frame: &mut Frame, area: Rect) {
+        if matches!(
+            self.input.mode,
+            InputMode::Save | InputMode::Export | InputMode::Upload
+        ) {
+            let prompt = match self.input.mode {
+                InputMode::Save => format!("save to: {}", self.save_path),
+                InputMode::Export => format!("export to: {}", self.save_path),
+                _ => format!("upload: {}", self.save_path),
+            };
+
+            Line::from(prompt.clone())
+                .style(self.theme.status_line)
+                .render(area, frame.buffer_mut());
+            frame.set_cursor_position(Position::new(area.x + prompt.width() as u16, area.y));
+            return;
+        }
+
+        let Some(search) = &self.tab().search else {
+            return;
+        };
+
+        let prompt = format!("find: {}", search.query);
+        let counter = format!(" {} ", search.counter());
+
+        Line::from(prompt.clone())
+            .style(self.theme.status_line)
+            .render(area, frame.buffer_mut());
+        Line::from(counter.bold())
+            .style(self.theme.status_line)
+            .alignment(Alignment::Right)
+            .render(area, frame.buffer_mut());
+
+        if self.input.mode == InputMode::Search {
+            frame.set_cursor_position(Position::new(area.x + prompt.width() as u16, area.y));
+        }
     }
 
-    fn draw_address_bar(&mut self, frame: &mut Frame, area: Rect) {
-        let title = Line::from(" dioscuri ".blue().bold());
+    fn draw_address_bar(&mut self, frame: &mut Frame, area: Rect, compact: bool) {
+        let accent = self.theme.accent;
+        let editing = self.input.mode == InputMode::Edit;
+
+        if compact {
+            // A single line without borders, the URL losing its middle
+            // rather than its end when it doesn't fit
+            let width = area.width as usize;
+            let (value, offset) = if editing {
+                let offset = self.input.index.saturating_sub(width.saturating_sub(1));
+                (self.input.value.clone(), offset)
+            } else {
+                (abbreviate_url(&self.input.value, width), 0)
+            };
+
+            let address_bar = Paragraph::new(value).scroll((0, offset as u16));
+            let address_bar = if editing {
+                address_bar.fg(accent)
+            } else {
+                address_bar.style(self.theme.address_bar)
+            };
+            address_bar.render(area, frame.buffer_mut());
+
+            if editing {
+                frame.set_cursor_position(Position::new(
+                    area.x + (self.input.index - offset) as u16,
+                    area.y,
+                ));
+            }
+            return;
+        }
+
+        let title = Line::from(" dioscuri ".fg(accent).bold());
         let block = Block::bordered().title(title);
         let url = Text::from(self.input.value.clone());
 
-        let address_bar = if self.input.mode == InputMode::Edit {
-            Paragraph::new(url).block(block).blue()
+        let address_bar = if editing {
+            Paragraph::new(url).block(block).fg(accent)
         } else {
-            Paragraph::new(url).block(block)
+            Paragraph::new(url)
+                .block(block)
+                .style(self.theme.address_bar)
         };
 
         address_bar.render(area, frame.buffer_mut());
 
-        if self.input.mode == InputMode::Edit {
+        if editing {
             frame.set_cursor_position(Position::new(
                 area.x + self.input.index as u16 + 1,
                 area.y + 1,
@@ -123,67 +758,500 @@ impl App {
         }
     }
 
-    fn draw_body(&mut self, buffer: &mut Buffer, area: Rect) {
-        let instructions = if self.input.mode == InputMode::Normal {
-            " <SLASH> - Edit Mode | <ESC> - Exit App "
-        } else {
-            " <ENTER> - Submit Request | <ESC> - Normal Mode "
+    /// Key hints of the current mode, as `(keys, action)` pairs.
+    fn instructions(&self) -> Vec<(String, &'static str)> {
+        let keys = &self.config.keys;
+        let tab = self.tab();
+        let key = |action| keys.label(action);
+        let named = |name: &str| name.to_owned();
+
+        match self.input.mode {
+            InputMode::Normal if tab.search.is_some() => vec![
+                (
+                    format!("{} {}", key(Action::NextMatch), key(Action::PreviousMatch)),
+                    "Next/Previous Match",
+                ),
+                (named("ESC"), "Clear Search"),
+            ],
+            InputMode::Normal if tab.link.is_some() => vec![
+                (named("ENTER"), "Follow Link"),
+                (key(Action::OpenInNewTab), "Open in New Tab"),
+                (named("ESC"), "Unfocus Link"),
+            ],
+            InputMode::Normal if tab.book.is_some() => vec![
+                (
+                    format!(
+                        "{} {}",
+                        key(Action::PreviousChapter),
+                        key(Action::NextChapter)
+                    ),
+                    "Chapter",
+                ),
+                (key(Action::EditAddress), "Edit Mode"),
+                (named("ESC"), "Exit App"),
+            ],
+            InputMode::Normal => vec![
+                (key(Action::Find), "Find"),
+                (key(Action::EditAddress), "Edit Mode"),
+                (named("ESC"), "Exit App"),
+            ],
+            InputMode::Edit => vec![
+                (named("ENTER"), "Submit Request"),
+                (named("ESC"), "Normal Mode"),
+            ],
+            InputMode::Search => vec![
+                (named("ENTER"), "Confirm Search"),
+                (named("ESC"), "Cancel Search"),
+            ],
+            InputMode::Save => vec![(named("ENTER"), "Save Page"), (named("ESC"), "Cancel")],
+            InputMode::Export => vec![(named("ENTER"), "Export Page"), (named("ESC"), "Cancel")],
+            InputMode::Upload => vec![(named("ENTER"), "Upload File"), (named("ESC"), "Cancel")],
+            InputMode::Select => vec![
+                (named("UP DOWN"), "Extend Selection"),
+                (key(Action::CopyUrl), "Copy"),
+                (named("ESC"), "Cancel"),
+            ],
+            // The dialog over the body has its own instructions
+            InputMode::Certificate | InputMode::Trust | InputMode::Info => Vec::new(),
+        }
+    }
+
+    /// Indicators of how the page is shown: the chapter of a book first,
+    /// then whether it is the source, its encoding, its reading time, how
+    /// long its host asks to be left alone, how far its preformatted blocks
+    /// are scrolled and its refresh countdown.
+    fn indicators(&self) -> (Option<String>, Vec<String>) {
+        let tab = self.tab();
+        let chapter = tab.book.as_ref().map(|book| {
+            format!(
+                "{} ({}/{})",
+                book.gempub.title().unwrap_or("Gempub"),
+                book.position.chapter + 1,
+                book.gempub.chapter_count()
+            )
+        });
+
+        let mut flags = Vec::new();
+        if tab.raw {
+            flags.push(String::from("source"));
+        } else if tab.reader {
+            flags.push(String::from("reader"));
+        }
+        if tab.encoding != "UTF-8" {
+            flags.push(tab.encoding.to_owned());
+        }
+        if tab.words > 0 {
+            flags.push(format!("{} min", render::reading_time(tab.words)));
+        }
+        let downloading = self.downloads.pending();
+        if downloading > 0 {
+            flags.push(format!("↓ {}", downloading));
+        }
+        if let Some(wait) = self.cooldown() {
+            flags.push(format!("slow down {}s", wait.as_secs_f64().ceil()));
+        }
+        if tab.hscroll > 0 {
+            flags.push(format!("→ {}", tab.hscroll));
+        }
+        if let Some(refresh) = &tab.refresh {
+            flags.push(if self.focused {
+                format!("↻ {}", refresh.countdown())
+            } else {
+                format!("↻ {} (paused)", refresh.countdown())
+            });
+        }
+
+        (chapter, flags)
+    }
+
+    /// Line under the body in the compact layout, standing in for the
+    /// titles of its borders: the message or as many key hints as fit, and
+    /// the indicators on the right.
+    fn draw_status_line(
+        &self,
+        buffer: &mut Buffer,
+        area: Rect,
+        instructions: &[(String, &str)],
+        chapter: Option<String>,
+        flags: Vec<String>,
+    ) {
+        let indicators = chapter
+            .into_iter()
+            .chain(flags)
+            .collect::<Vec<_>>()
+            .join(" · ");
+        let room = (area.width as usize).saturating_sub(indicators.chars().count() + 1);
+
+        let left = match &self.message {
+            Some(message) => Line::from(message.clone()),
+            None => {
+                let mut hints = String::new();
+                for (keys, action) in instructions {
+                    let hint = format!("<{}> {}", keys, action);
+                    let separator = if hints.is_empty() { "" } else { " " };
+                    if hints.chars().count() + separator.len() + hint.chars().count() > room {
+                        break;
+                    }
+                    hints.push_str(separator);
+                    hints.push_str(&hint);
+                }
+
+                if self.input.mode == InputMode::Normal {
+                    Line::from(hints.fg(self.theme.accent).bold())
+                } else {
+                    Line::from(hints.bold())
+                }
+            }
         };
-        let instructions = Line::from(instructions.bold()).alignment(Alignment::Right);
 
-        let block = if self.input.mode == InputMode::Normal {
-            Block::bordered()
-                .title_bottom(instructions)
-                .border_style(Style::new().blue())
+        Block::new()
+            .style(self.theme.status_line)
+            .render(area, buffer);
+        left.render(area, buffer);
+        Line::from(indicators)
+            .alignment(Alignment::Right)
+            .render(area, buffer);
+    }
+
+    fn draw_body(&mut self, buffer: &mut Buffer, area: Rect, compact: bool) {
+        let instructions = self.instructions();
+        let (chapter, flags) = self.indicators();
+
+        let (block, area, scroll_area) = if compact {
+            let [body, status_line] =
+                Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+            self.draw_status_line(buffer, status_line, &instructions, chapter, flags);
+
+            // The scrollbar gets a column of its own instead of the border
+            let [text, _] =
+                Layout::horizontal([Constraint::Fill(1), Constraint::Length(1)]).areas(body);
+            (Block::new(), text, body)
         } else {
-            Block::bordered().title_bottom(instructions)
+            let instructions = instructions
+                .iter()
+                .map(|(keys, action)| format!("<{}> - {}", keys, action))
+                .collect::<Vec<_>>();
+            let instructions = if instructions.is_empty() {
+                String::new()
+            } else {
+                format!(" {} ", instructions.join(" | "))
+            };
+            let instructions = Line::from(instructions.bold()).alignment(Alignment::Right);
+
+            let block = if self.input.mode == InputMode::Normal {
+                Block::bordered()
+                    .title_bottom(instructions)
+                    .border_style(Style::new().fg(self.theme.accent))
+            } else {
+                Block::bordered().title_bottom(instructions)
+            };
+
+            let block = match chapter {
+                Some(chapter) => block.title(format!(" {} ", chapter)),
+                None => block,
+            };
+
+            let block = flags.iter().fold(block, |block, flag| {
+                block.title(Line::from(format!(" {} ", flag)).alignment(Alignment::Right))
+            });
+
+            let block = match &self.message {
+                Some(message) => block.title_bottom(
+                    Line::from(format!(" {} ", message)).style(self.theme.status_line),
+                ),
+                None => block,
+            };
+
+            (block, area, area)
+        };
+
+        // The text is centered in the block when it is wider than allowed
+        let inner = block.inner(area);
+        let inner = match self.max_width {
+            0 => inner,
+            max_width => {
+                let width = inner.width.min(max_width);
+                Rect {
+                    x: inner.x + (inner.width - width) / 2,
+                    width,
+                    ..inner
+                }
+            }
+        };
+        self.body_width = inner.width;
+        self.body_height = inner.height;
+        let tab = &mut self.tabs[self.active];
+        let height = inner.height.max(1) as usize;
+
+        // Only the lines in the viewport are handed to the paragraph, the
+        // first one scrolled by as many of its rows as are above it
+        let (first, skipped, rows) = {
+            let layout = tab.layout(inner.width);
+            let first = layout.line_at(tab.scroll.value);
+            let skipped = tab
+                .scroll
+                .value
+                .saturating_sub(layout.offsets.get(first).copied().unwrap_or_default());
+            (first, skipped, layout.rows)
         };
+        let mut shown = 0;
+
+        let text = Text::from(
+            tab.lines
+                .iter()
+                .enumerate()
+                .skip(first)
+                .take_while(|_| {
+                    let visible = shown < height + skipped;
+                    shown += 1;
+                    visible
+                })
+                .map(|(index, line)| {
+                    let styled = match &tab.search {
+                        Some(search) => search.highlight(index, line),
+                        None => Line::styled(line.text.clone(), line.style),
+                    };
+                    // Cropped to the viewport, so that the paragraph has
+                    // nothing to wrap
+                    let styled = if line.preformatted {
+                        render::crop(styled, tab.hscroll, inner.width as usize)
+                    } else {
+                        styled
+                    };
+
+                    let (start, end) = self.selection;
+                    let selected = self.input.mode == InputMode::Select
+                        && (start.min(end)..=start.max(end)).contains(&index);
+
+                    if tab.link == Some(index) || selected {
+                        styled.reversed()
+                    } else {
+                        styled
+                    }
+                })
+                .collect::<Vec<_>>(),
+        );
 
-        let paragraph = Paragraph::new(self.body.replace("\t", " "))
-            .block(block)
+        let paragraph = Paragraph::new(text)
             .wrap(Wrap { trim: false })
-            .scroll((self.scroll.value as u16, 0));
+            .scroll((skipped as u16, 0));
 
-        let lines = paragraph.line_count(area.width - 2);
-        let height = (area.height - 2) as usize;
+        // Borders count as rows, as they did when the whole page was measured
+        let lines = rows + (area.height - inner.height) as usize;
 
         let pages = lines / height;
         let reminder = lines % height;
 
-        self.scroll.max = (height * pages.saturating_sub(1)) + if pages > 0 { reminder } else { 0 };
-        self.scroll.state = self.scroll.state.content_length(self.scroll.max);
+        tab.scroll.max = (height * pages.saturating_sub(1)) + if pages > 0 { reminder } else { 0 };
+        tab.scroll.state = tab.scroll.state.content_length(tab.scroll.max);
 
-        paragraph.render(area, buffer);
+        block.render(area, buffer);
+        paragraph.render(inner, buffer);
 
-        Scrollbar::new(ScrollbarOrientation::VerticalRight).render(
-            area,
-            buffer,
-            &mut self.scroll.state,
-        );
+        Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .style(self.theme.scrollbar)
+            .render(scroll_area, buffer, &mut tab.scroll.state);
     }
 
     fn handle_events(&mut self, timeout: Duration) -> Result<Status> {
         if event::poll(timeout)? {
             match event::read()? {
                 Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                    self.message = None;
+                    trace!(code = ?key_event.code, modifiers = ?key_event.modifiers, "Key");
+
+                    let page = self.body_height as usize;
+
                     match self.input.mode {
-                        InputMode::Normal => match key_event.code {
-                            KeyCode::Up => self.scroll_up(),
-                            KeyCode::Down => self.scroll_down(),
-                            KeyCode::Char('/') => self.enter_edit_mode(),
-                            KeyCode::Esc => return Ok(Status::Exit),
+                        InputMode::Normal => return self.press(KeyPress::from(key_event)),
+                        InputMode::Edit => {
+                            let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+                            let alt = key_event.modifiers.contains(KeyModifiers::ALT);
+                            let index = self.input.index;
+                            let value = &self.input.value;
+
+                            match key_event.code {
+                                KeyCode::Enter => {
+                                    self.accept_suggestion();
+                                    self.request_url()?
+                                }
+                                // The keys of readline and Emacs
+                                KeyCode::Char('a') if ctrl => self.input.index = 0,
+                                KeyCode::Char('e') if ctrl => self.reset_cursor(),
+                                KeyCode::Char('b') if alt => {
+                                    self.input.index = editing::word_start(value, index)
+                                }
+                                KeyCode::Char('f') if alt => {
+                                    self.input.index = editing::word_end(value, index)
+                                }
+                                KeyCode::Char('k') if ctrl => self.delete_to(usize::MAX),
+                                KeyCode::Char('u') if ctrl => self.delete_to(0),
+                                KeyCode::Char('w') if ctrl => {
+                                    self.delete_to(editing::word_start(value, index))
+                                }
+                                KeyCode::Char('d') if alt => {
+                                    self.delete_to(editing::word_end(value, index))
+                                }
+                                KeyCode::Backspace if ctrl || alt => {
+                                    self.delete_to(editing::word_start(value, index))
+                                }
+                                KeyCode::Char(_) if ctrl || alt => {
+                                    return Ok(Status::Running(false));
+                                }
+                                KeyCode::Char(char) => self.enter_char(char),
+                                KeyCode::Backspace => self.delete_char(),
+                                KeyCode::Delete => self.delete_to(index + 1),
+                                KeyCode::Left if ctrl || alt => {
+                                    self.input.index = editing::word_start(value, index)
+                                }
+                                KeyCode::Right if ctrl || alt => {
+                                    self.input.index = editing::word_end(value, index)
+                                }
+                                KeyCode::Left => self.move_cursor_left(),
+                                KeyCode::Right => self.move_cursor_right(),
+                                KeyCode::Home => self.input.index = 0,
+                                KeyCode::End => self.reset_cursor(),
+                                // The addresses entered before, unless
+                                // suggestions are shown for the one typed
+                                KeyCode::Up if self.input.suggestions.is_empty() => {
+                                    self.recall_address(true)
+                                }
+                                KeyCode::Down if self.input.suggestions.is_empty() => {
+                                    self.recall_address(false)
+                                }
+                                KeyCode::Up => self.select_suggestion(false),
+                                KeyCode::Down => self.select_suggestion(true),
+                                KeyCode::Tab => {
+                                    if self.input.selected.is_none() {
+                                        self.select_suggestion(true);
+                                    }
+                                    self.accept_suggestion()
+                                }
+                                KeyCode::Esc => self.exit_edit_mode(),
+                                _ => return Ok(Status::Running(false)),
+                            }
+                        }
+                        InputMode::Search => match key_event.code {
+                            KeyCode::Enter => self.exit_edit_mode(),
+                            KeyCode::Char(char) => self.edit_search(|query| query.push(char)),
+                            KeyCode::Backspace => self.edit_search(|query| {
+                                query.pop();
+                            }),
+                            KeyCode::Esc => {
+                                self.tab_mut().search = None;
+                                self.exit_edit_mode();
+                            }
+                            _ => return Ok(Status::Running(false)),
+                        },
+                        InputMode::Save | InputMode::Export => match key_event.code {
+                            KeyCode::Enter => {
+                                if self.input.mode == InputMode::Save {
+                                    self.save_page();
+                                } else {
+                                    self.export_page();
+                                }
+                                self.exit_edit_mode();
+                            }
+                            KeyCode::Char(char) => self.save_path.push(char),
+                            KeyCode::Backspace => {
+                                self.save_path.pop();
+                            }
+                            KeyCode::Esc => self.exit_edit_mode(),
                             _ => return Ok(Status::Running(false)),
                         },
-                        InputMode::Edit => match key_event.code {
-                            KeyCode::Enter => self.request_url()?,
-                            KeyCode::Char(char) => self.enter_char(char),
-                            KeyCode::Backspace => self.delete_char(),
-                            KeyCode::Left => self.move_cursor_left(),
-                            KeyCode::Right => self.move_cursor_right(),
+                        InputMode::Upload => match key_event.code {
+                            KeyCode::Enter => {
+                                self.exit_edit_mode();
+                                self.upload_file()?
+                            }
+                            KeyCode::Char(char) => self.save_path.push(char),
+                            KeyCode::Backspace => {
+                                self.save_path.pop();
+                            }
                             KeyCode::Esc => self.exit_edit_mode(),
                             _ => return Ok(Status::Running(false)),
                         },
+                        InputMode::Select => match key_event.code {
+                            KeyCode::Up => self.extend_selection(-1),
+                            KeyCode::Down => self.extend_selection(1),
+                            KeyCode::PageUp => self.extend_selection(-(page as isize)),
+                            KeyCode::PageDown => self.extend_selection(page as isize),
+                            KeyCode::Enter => self.copy_selection(),
+                            _ if self
+                                .config
+                                .keys
+                                .is_bound(Action::CopyUrl, KeyPress::from(key_event)) =>
+                            {
+                                self.copy_selection()
+                            }
+                            KeyCode::Esc => self.exit_edit_mode(),
+                            _ => return Ok(Status::Running(false)),
+                        },
+                        InputMode::Certificate => {
+                            self.exit_edit_mode();
+                            if key_event.code == KeyCode::Char('r')
+                                && let Ok(url) = Url::parse(&self.tab().url)
+                                && let Some(host) = url.host_str()
+                            {
+                                let host = match url.port() {
+                                    Some(port) => format!("{}:{}", host, port),
+                                    None => host.to_owned(),
+                                };
+                                self.open(format!("{}?{}", ROTATION_PAGE, host))?;
+                            }
+                        }
+                        InputMode::Info => self.exit_edit_mode(),
+                        InputMode::Trust => match key_event.code {
+                            KeyCode::Char('o') => self.trust_certificate(Trust::Once)?,
+                            KeyCode::Char('p') => self.trust_certificate(Trust::Permanently)?,
+                            KeyCode::Esc => {
+                                self.untrusted = None;
+                                self.message = Some(String::from("Connection aborted"));
+                                self.exit_edit_mode();
+                            }
+                            _ => return Ok(Status::Running(false)),
+                        },
+                    };
+                    return Ok(Status::Running(true));
+                }
+                Event::Mouse(mouse_event) => {
+                    let step = self.config.wheel_scroll_lines;
+                    let up = match mouse_event.kind {
+                        MouseEventKind::ScrollUp => true,
+                        MouseEventKind::ScrollDown => false,
+                        _ => return Ok(Status::Running(false)),
                     };
+                    if up != self.config.invert_scroll {
+                        self.tab_mut().scroll_up(step);
+                    } else {
+                        self.tab_mut().scroll_down(step);
+                    }
+                    return Ok(Status::Running(true));
+                }
+                Event::Paste(text) => {
+                    // Pasted line breaks would end up in the middle of the
+                    // address
+                    let text = text
+                        .chars()
+                        .filter(|char| !char.is_control())
+                        .collect::<String>();
+
+                    match self.input.mode {
+                        InputMode::Edit => self.enter_text(&text),
+                        InputMode::Search => self.edit_search(|query| query.push_str(&text)),
+                        InputMode::Save | InputMode::Export | InputMode::Upload => {
+                            self.save_path.push_str(&text)
+                        }
+                        _ => return Ok(Status::Running(false)),
+                    }
+                    return Ok(Status::Running(true));
+                }
+                Event::FocusGained => {
+                    self.focused = true;
+                    return Ok(Status::Running(true));
+                }
+                Event::FocusLost => {
+                    self.focused = false;
                     return Ok(Status::Running(true));
                 }
                 Event::Resize(_, _) => return Ok(Status::Running(true)),
@@ -191,29 +1259,172 @@ impl App {
             }
         }
 
+        // A sequence bound on its own that could go on runs once no key
+        // follows it
+        if !self.pressed.is_empty() && self.pressed_at.elapsed() >= SEQUENCE_TIMEOUT {
+            let pressed = mem::take(&mut self.pressed);
+            self.message = None;
+            return match self.config.keys.step(&pressed) {
+                Step::Pending(Some(action)) => self.run_action(action),
+                _ => Ok(Status::Running(true)),
+            };
+        }
+
         Ok(Status::Running(false))
     }
 
-    fn scroll_up(&mut self) {
-        if self.scroll.value > 0 {
-            self.scroll.state.scroll(ScrollDirection::Backward);
-            self.scroll.value = self.scroll.value.saturating_sub(1);
+    /// Takes a key pressed in the normal mode, running the action of the
+    /// sequence it completes.
+    fn press(&mut self, key: KeyPress) -> Result<Status> {
+        self.pressed.push(key);
+
+        match self.config.keys.step(&self.pressed) {
+            Step::Action(action) => {
+                self.pressed.clear();
+                self.run_action(action)
+            }
+            Step::Pending(_) => {
+                self.pressed_at = Instant::now();
+                self.message = Some(format!("{} …", Sequence(self.pressed.clone())));
+                Ok(Status::Running(true))
+            }
+            // A key that does not continue the sequence starts another one,
+            // after the action of the keys before it if they have one
+            Step::Unbound if self.pressed.len() > 1 => {
+                self.pressed.pop();
+                let pressed = mem::take(&mut self.pressed);
+                if let Step::Pending(Some(action)) = self.config.keys.step(&pressed)
+                    && let Status::Exit = self.run_action(action)?
+                {
+                    return Ok(Status::Exit);
+                }
+                self.press(key).map(|status| match status {
+                    Status::Running(_) => Status::Running(true),
+                    Status::Exit => Status::Exit,
+                })
+            }
+            Step::Unbound => {
+                self.pressed.clear();
+                Ok(Status::Running(false))
+            }
+        }
+    }
+
+    fn run_action(&mut self, action: Action) -> Result<Status> {
+        let page = self.body_height as usize;
+        let width = self.body_width;
+        let step = self.config.scroll_step_lines;
+
+        match action {
+            Action::ScrollUp => self.tab_mut().scroll_up(step),
+            Action::ScrollDown => self.tab_mut().scroll_down(step),
+            Action::ScrollLeft => self
+                .tab_mut()
+                .scroll_horizontally(-HORIZONTAL_SCROLL_COLUMNS, width),
+            Action::ScrollRight => self
+                .tab_mut()
+                .scroll_horizontally(HORIZONTAL_SCROLL_COLUMNS, width),
+            Action::PageUp => self.tab_mut().scroll_up(page),
+            Action::PageDown => self.tab_mut().scroll_down(page),
+            Action::HalfPageUp => self.tab_mut().scroll_up(page / 2),
+            Action::HalfPageDown => self.tab_mut().scroll_down(page / 2),
+            Action::Top => self.tab_mut().set_scroll(0),
+            Action::Bottom => {
+                let tab = self.tab_mut();
+                tab.set_scroll(tab.scroll.max)
+            }
+            Action::NextLink => self.tab_mut().focus_link(true, width, page),
+            Action::PreviousLink => self.tab_mut().focus_link(false, width, page),
+            Action::FollowLink if self.tab().link.is_some() => self.follow_link(false)?,
+            Action::FollowLink => return Ok(Status::Running(false)),
+            Action::DuplicateTab => self.duplicate_tab()?,
+            Action::EditAddress => self.enter_edit_mode(),
+            Action::DebugConsole => self.debug_console = !self.debug_console,
+            Action::NextChapter => self.turn_chapter(true)?,
+            Action::PreviousChapter => self.turn_chapter(false)?,
+            Action::Subscribe => self.toggle_subscription()?,
+            Action::Bookmark => self.toggle_bookmark()?,
+            Action::ViewSource => self.toggle_source(),
+            Action::SavePage => self.enter_save_mode(InputMode::Save),
+            Action::ExportPage => self.enter_save_mode(InputMode::Export),
+            Action::Upload => self.enter_upload_mode(),
+            Action::EditPage => self.edit_page()?,
+            Action::Download => self.download(),
+            Action::OpenInPager => self.open_in_pager()?,
+            Action::OpenExternally => self.open_externally()?,
+            Action::Clip => self.clip_paragraph()?,
+            Action::CopyUrl => self.copy_url(),
+            Action::CopyLink => self.copy_link(),
+            Action::PreviewLink => self.preview_link(),
+            Action::SelectLines => self.enter_select_mode(),
+            Action::Certificate => self.input.mode = InputMode::Certificate,
+            Action::PageInfo => self.input.mode = InputMode::Info,
+            Action::Widen => self.resize_body(true),
+            Action::Narrow => self.resize_body(false),
+            Action::Find => self.enter_search_mode(),
+            Action::NextMatch => self.focus_next_match(),
+            Action::PreviousMatch => self.focus_previous_match(),
+            Action::Back => self.travel(-1)?,
+            Action::Forward => self.travel(1)?,
+            Action::UpdateMoved => self.update_moved_subscription()?,
+            Action::ToggleFilters => self.toggle_filters(),
+            Action::ToggleReader => self.toggle_reader(),
+            Action::ToggleOffline => self.toggle_offline(),
+            Action::OpenInNewTab => self.follow_link(true)?,
+            Action::NewTab => self.new_tab(),
+            Action::CloseTab => self.close_tab()?,
+            Action::NextTab => self.cycle_tab(1),
+            Action::PreviousTab => self.cycle_tab(-1),
+            Action::AutoRefresh => self.cycle_refresh(),
+            Action::Reload => self.reload(Lookup::Fresh)?,
+            Action::ForceReload => self.reload(Lookup::Bypass)?,
+            Action::Feeds => self.open(String::from("about:feeds"))?,
+            Action::Bookmarks => self.open(String::from("about:bookmarks"))?,
+            Action::History => self.open(String::from("about:history"))?,
+            Action::HistoryTree => self.open(String::from(TREE_PAGE))?,
+            Action::Help => self.open(String::from("about:help"))?,
+            Action::Unfocus if self.tab().search.is_some() => self.tab_mut().search = None,
+            Action::Unfocus if self.tab().link.is_some() => self.tab_mut().link = None,
+            Action::Unfocus | Action::Quit => return Ok(Status::Exit),
         }
+
+        Ok(Status::Running(true))
     }
 
-    fn scroll_down(&mut self) {
-        if self.scroll.value < self.scroll.max {
-            self.scroll.state.scroll(ScrollDirection::Forward);
-            self.scroll.value = self.scroll.value.saturating_add(1);
+    fn handle_control_requests(&mut self) -> bool {
+        let Some(control) = &self.control else {
+            return false;
+        };
+
+        let requests = control.pending();
+        let should_render = !requests.is_empty();
+
+        for request in requests {
+            let reply = match &request.command {
+                Command::Open(url) => self.open(url.clone()).map(|()| String::from("ok")),
+                Command::OpenTab(url) => self
+                    .open_in_new_tab(url.clone())
+                    .map(|()| String::from("ok")),
+                Command::Reload => self.reload(Lookup::Bypass).map(|()| String::from("ok")),
+                Command::DumpCurrent => Ok(self.tab().body.clone()),
+            };
+            request.reply(reply.unwrap_or_else(|error| format!("error: {}", error)));
         }
+
+        should_render
     }
 
     fn exit_edit_mode(&mut self) {
         self.input.mode = InputMode::Normal;
+        self.input.suggestions.clear();
+        self.input.selected = None;
     }
 
     fn enter_edit_mode(&mut self) {
         self.input.mode = InputMode::Edit;
+        self.input.suggestions.clear();
+        self.input.selected = None;
+        self.input.history.reset();
         self.reset_cursor();
     }
 
@@ -231,6 +1442,79 @@ impl App {
         let index = self.byte_index();
         self.input.value.insert(index, char);
         self.move_cursor_right();
+        self.update_suggestions();
+    }
+
+    fn enter_text(&mut self, text: &str) {
+        let index = self.byte_index();
+        self.input.value.insert_str(index, text);
+        self.input.index += text.chars().count();
+        self.update_suggestions();
+    }
+
+    /// Deletes the characters between the cursor and the index.
+    fn delete_to(&mut self, index: usize) {
+        let index = self.clamp_cursor(index);
+        if index == self.input.index {
+            return;
+        }
+
+        self.input.index = editing::remove_range(&mut self.input.value, self.input.index, index);
+        self.update_suggestions();
+    }
+
+    fn update_suggestions(&mut self) {
+        self.input.suggestions =
+            completion::suggest(&self.input.value, &self.bookmarks, &self.visits);
+        self.input.selected = None;
+        // The address edited is no longer the one recalled
+        self.input.history.reset();
+    }
+
+    /// Replaces the address with the one entered before or after the one
+    /// shown.
+    fn recall_address(&mut self, previous: bool) {
+        let history = &mut self.input.history;
+        let address = if previous {
+            history.previous(&self.input.value)
+        } else {
+            history.next()
+        };
+
+        if let Some(address) = address {
+            self.input.value = address.to_owned();
+            self.reset_cursor();
+        }
+    }
+
+    fn select_suggestion(&mut self, down: bool) {
+        let count = self.input.suggestions.len();
+        if count == 0 {
+            return;
+        }
+
+        self.input.selected = match self.input.selected {
+            Some(index) if down => Some((index + 1) % count),
+            Some(index) => Some(index.checked_sub(1).unwrap_or(count - 1)),
+            None if down => Some(0),
+            None => Some(count - 1),
+        };
+    }
+
+    /// Replaces the address with the selected suggestion, if any.
+    fn accept_suggestion(&mut self) {
+        let Some(suggestion) = self
+            .input
+            .selected
+            .and_then(|index| self.input.suggestions.get(index))
+        else {
+            return;
+        };
+
+        self.input.value = suggestion.url.clone();
+        self.input.suggestions.clear();
+        self.input.selected = None;
+        self.reset_cursor();
     }
 
     fn byte_index(&self) -> usize {
@@ -253,36 +1537,2028 @@ impl App {
 
             self.input.value = before_char_to_delete.chain(after_char_to_delete).collect();
             self.move_cursor_left();
+            self.update_suggestions();
         }
     }
 
     fn clamp_cursor(&self, new_cursor_pos: usize) -> usize {
-        new_cursor_pos.clamp(0, self.input.value.len())
+        new_cursor_pos.min(self.input.value.chars().count())
     }
 
     fn reset_cursor(&mut self) {
-        self.input.index = self.input.value.len();
+        self.input.index = self.input.value.chars().count();
+    }
+
+    fn open(&mut self, url: String) -> Result<()> {
+        if let Some(index) = navigation::tree_link(&url) {
+            return self.jump(index);
+        }
+
+        self.tab_mut().save_history_scroll();
+        if !self.load(url, Lookup::Fresh)? {
+            return Ok(());
+        }
+
+        let tab = self.tab_mut();
+        if tab.url != TREE_PAGE {
+            tab.history
+                .push(tab.url.clone(), tab.title().map(str::to_owned));
+        }
+
+        self.record_visit()
+    }
+
+    fn record_visit(&mut self) -> Result<()> {
+        let tab = &self.tabs[self.active];
+
+        if tab.url.starts_with("about:") {
+            return Ok(());
+        }
+
+        self.visits.record(&tab.url, tab.title())
     }
 
     fn request_url(&mut self) -> Result<()> {
-        self.body = self.client.request(&self.input.value)?;
-        self.reset_cursor();
-        self.input.mode = InputMode::Normal;
+        self.input.history.push(&self.input.value);
+        let config = &self.config;
+        let url = navigation::search_url(
+            &self.input.value,
+            &config.search_url,
+            &config.search_keywords,
+        )
+        .unwrap_or_else(|| self.input.value.clone());
+
+        self.open(url)
+    }
+
+    /// Opens the URL in a new tab placed after the others, dropping the tab
+    /// again if the request fails.
+    fn open_in_new_tab(&mut self, url: String) -> Result<()> {
+        self.tabs.push(self.blank_tab());
+        let previous = mem::replace(&mut self.active, self.tabs.len() - 1);
+
+        if let Err(error) = self.open(url) {
+            self.tabs.pop();
+            self.select_tab(previous);
+            return Err(error);
+        }
 
         Ok(())
     }
-}
 
-fn main() -> Result<()> {
-    color_eyre::install()?;
+    /// Tab opened before any page, in reader mode if configured.
+    fn blank_tab(&self) -> Tab {
+        let mut tab = Tab::default();
+        tab.reader = self.config.reader_mode;
+        tab
+    }
 
-    let gemini_client = GeminiClient::new();
-    let mut terminal = ratatui::init();
+    fn new_tab(&mut self) {
+        self.tabs.push(self.blank_tab());
+        self.select_tab(self.tabs.len() - 1);
+        self.input.value = self.config.start_url.clone();
+        self.enter_edit_mode();
+    }
 
-    let mut app = App::new(gemini_client);
-    let result = app.run(&mut terminal);
+    /// Opens a copy of the current tab right after it, with the same page,
+    /// history and scroll position.
+    fn duplicate_tab(&mut self) -> Result<()> {
+        let tab = self.tab_mut();
+        let scroll = tab.scroll.value;
+        let book = tab.book.as_mut().map(|book| {
+            book.position.set_scroll(book.position.chapter, scroll);
+            (book.source.clone(), book.position.clone())
+        });
 
-    ratatui::restore();
+        let duplicate = self.tab().duplicate();
+        self.tabs.insert(self.active + 1, duplicate);
+        self.select_tab(self.active + 1);
 
-    result
+        // Books are opened again, at the position the original tab is at
+        if let Some((source, position)) = book {
+            self.positions.set(source, position)?;
+            self.load(self.tab().url.clone(), Lookup::Any)?;
+        }
+
+        Ok(())
+    }
+
+    fn close_tab(&mut self) -> Result<()> {
+        if self.tabs.len() == 1 {
+            return Ok(());
+        }
+
+        let mut tab = self.tabs.remove(self.active);
+        tab.close_book(&mut self.positions)?;
+        self.select_tab(self.active.min(self.tabs.len() - 1));
+
+        Ok(())
+    }
+
+    fn cycle_tab(&mut self, offset: isize) {
+        let count = self.tabs.len() as isize;
+        self.select_tab((self.active as isize + offset).rem_euclid(count) as usize);
+    }
+
+    fn select_tab(&mut self, index: usize) {
+        self.active = index;
+        self.input.value = self.tab().location.clone();
+        self.reset_cursor();
+        self.input.mode = InputMode::Normal;
+    }
+
+    /// Follows the focused link, turning to the chapter it points to when
+    /// reading a book.
+    fn follow_link(&mut self, new_tab: bool) -> Result<()> {
+        let tab = self.tab();
+        let Some(target) = tab.focused_link().map(str::to_owned) else {
+            return Ok(());
+        };
+
+        if let Some(book) = &tab.book
+            && let Some(chapter) = book.gempub.resolve(book.position.chapter, &target)
+        {
+            return self.go_to_chapter(chapter);
+        }
+
+        // Targets that cannot be resolved against the page, like local paths
+        // listed on about: pages, are opened as written
+        let url = Url::parse(&tab.location)
+            .and_then(|base| base.join(&target))
+            .map_or(target, |url| url.to_string());
+
+        // Pages could otherwise change settings behind the reader's back,
+        // like turning certificate checks off for a host
+        if !tab.internal
+            && let Ok(about) = Url::parse(&url)
+            && about.scheme() == "about"
+            && about.query().is_some()
+        {
+            self.message = Some(format!(
+                "Links of pages cannot run about: actions, type {} in the address bar",
+                url
+            ));
+            return Ok(());
+        }
+
+        if new_tab {
+            self.open_in_new_tab(url)
+        } else {
+            self.open(url)
+        }
+    }
+
+    /// Requests the current page again without recording a visit, keeping
+    /// the scroll position.
+    fn reload(&mut self, lookup: Lookup) -> Result<()> {
+        let tab = self.tab();
+        if tab.url.is_empty() {
+            return Ok(());
+        }
+        let (url, scroll) = (tab.url.clone(), tab.scroll.value);
+
+        self.load(url, lookup)?;
+        self.tab_mut().set_scroll(scroll);
+
+        Ok(())
+    }
+
+    /// Switches the current tab to the next configured auto-refresh
+    /// interval, turning it off after the longest one.
+    fn cycle_refresh(&mut self) {
+        let intervals = &self.config.refresh_intervals;
+        let tab = &mut self.tabs[self.active];
+
+        let next = match &tab.refresh {
+            Some(refresh) => intervals
+                .iter()
+                .position(|&seconds| seconds == refresh.interval.as_secs())
+                .and_then(|index| intervals.get(index + 1)),
+            None => intervals.first(),
+        };
+
+        tab.refresh = next.map(|&seconds| Refresh::new(Duration::from_secs(seconds)));
+        self.message = Some(match next {
+            Some(&seconds) if seconds % 60 == 0 => {
+                format!("Auto-refresh every {}m", seconds / 60)
+            }
+            Some(&seconds) => format!("Auto-refresh every {}s", seconds),
+            None => String::from("Auto-refresh off"),
+        });
+    }
+
+    /// Counts down the auto-refresh of the visible tab while the terminal has
+    /// focus, refreshing the page when it is due. Returns whether the UI
+    /// needs to be rendered again.
+    fn tick(&mut self, elapsed: Duration) -> bool {
+        let interval = Duration::from_secs(self.config.feed_interval_mins * 60);
+        if !self.offline && self.feeds.due(interval) {
+            self.feeds.check(&self.client, &self.subscriptions);
+        }
+
+        // The page shows the progress of the downloads, and is kept up to
+        // date while they run
+        let downloading = self.downloads.pending() > 0;
+        let completed = self.downloads.poll();
+        if let Some(path) = completed.last() {
+            self.message = Some(format!("Downloaded {}", path.display()));
+        }
+        let downloaded = downloading || !completed.is_empty();
+        if downloaded && self.tab().url == DOWNLOADS_PAGE {
+            let link = self.tab().link;
+            if let Err(error) = self.reload(Lookup::Fresh) {
+                self.message = Some(error.to_string());
+            }
+            // Links keep their place from one refresh to the next
+            let tab = self.tab_mut();
+            tab.link =
+                link.filter(|&line| tab.lines.get(line).is_some_and(|line| line.link.is_some()));
+        }
+
+        self.prefetch();
+
+        // The timeline shows the outcome of the check when it is open,
+        // otherwise only new posts are worth interrupting for
+        let checked = self.feeds.poll().map(|found| {
+            let shown = self.tab().url == "about:feeds";
+            self.message = match found {
+                Ok(_) if shown => self
+                    .reload(Lookup::Fresh)
+                    .err()
+                    .map(|error| error.to_string()),
+                Ok(0) => None,
+                Ok(found) => Some(format!("{} new post(s) in about:feeds", found)),
+                Err(error) => Some(format!("Could not save the posts: {}", error)),
+            };
+        });
+
+        // The countdown of a host slowing down is kept up to date
+        let changed = checked.is_some() || downloaded || self.cooldown().is_some();
+        if !self.focused {
+            return changed;
+        }
+
+        let Some(refresh) = &mut self.tab_mut().refresh else {
+            return changed;
+        };
+
+        // Failing to refresh should not bring the whole browser down, the
+        // next attempt may succeed
+        if refresh.elapse(elapsed)
+            && let Err(error) = self.reload(Lookup::Bypass)
+        {
+            self.message = Some(format!("Refresh failed: {}", error));
+        }
+
+        true
+    }
+
+    /// Queues the visible links of the page for prefetching, and caches the
+    /// pages prefetched since the last tick.
+    fn prefetch(&mut self) {
+        let tab = &self.tabs[self.active];
+        // Nothing new is requested offline, what was requested before still
+        // gets cached
+        if !self.offline {
+            let links = tab.visible_links(self.body_width, self.body_height as usize);
+            // Pages behind an identity are only fetched when followed
+            let (cache, identities, sites) = (&self.cache, &self.identities, &self.sites);
+            self.prefetcher
+                .queue(&tab.location, links.iter().map(String::as_str), |url| {
+                    cache.is_fresh(url) || sites.identity(url, identities).is_some()
+                });
+        }
+
+        let (bookmarks, subscriptions) = (&self.bookmarks, &self.subscriptions);
+        for response in self.prefetcher.poll() {
+            if let Err(error) = self.cache.store(
+                response.url.as_str(),
+                &response.meta,
+                &response.body,
+                |url| bookmarks.contains(url) || subscriptions.contains(url),
+            ) {
+                warn!(url = response.url.as_str(), %error, "Could not cache a prefetched page");
+            }
+        }
+    }
+
+    /// How long the host of the page asks to be left alone for, after it
+    /// answered with status 44.
+    fn cooldown(&self) -> Option<Duration> {
+        let url = Url::parse(&self.tab().url).ok()?;
+        self.client.cooldown(url.host_str()?)
+    }
+
+    /// Moves `offset` steps through the history (negative is back),
+    /// restoring the scroll position the page was left at.
+    fn travel(&mut self, offset: isize) -> Result<()> {
+        // Going back from the tree page returns to the entry it was opened
+        // from, since the page itself is not part of the history
+        let offset = if self.tab().url == TREE_PAGE && offset < 0 {
+            offset + 1
+        } else {
+            offset
+        };
+
+        match self.tab().history.index(offset) {
+            Some(index) => self.jump(index),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns to an entry of the history tree, restoring the scroll
+    /// position it was left at.
+    fn jump(&mut self, index: usize) -> Result<()> {
+        let tab = self.tab_mut();
+        let Some(entry) = tab.history.get(index) else {
+            return Ok(());
+        };
+        let (url, scroll) = (entry.url.clone(), entry.scroll);
+
+        tab.save_history_scroll();
+        if !self.load(url, Lookup::Any)? {
+            return Ok(());
+        }
+
+        let tab = self.tab_mut();
+        tab.history.go(index);
+        tab.set_scroll(scroll);
+
+        self.record_visit()
+    }
+
+    /// Shows the page at the URL. Returns whether it was shown, rather than
+    /// held back by a changed certificate or saved as a download.
+    fn load(&mut self, mut url: String, lookup: Lookup) -> Result<bool> {
+        if let Some(target) = url
+            .strip_prefix(ONLINE_PAGE)
+            .and_then(|query| query.strip_prefix('?'))
+        {
+            url = percent_decode_str(target).decode_utf8_lossy().into_owned();
+            self.offline = false;
+            self.message = Some(String::from("Online"));
+        }
+        // Paths of local files are opened as file:// URLs, so that their
+        // relative links resolve
+        if let Some(file) = navigation::file_url(&url) {
+            url = file;
+        }
+        let path = Url::parse(&url)
+            .ok()
+            .filter(|url| url.scheme() == "file")
+            .and_then(|url| url.to_file_path().ok());
+        let mut location = None;
+        let mut cached_at = None;
+
+        if let Ok(mut about) = Url::parse(&url)
+            && about.scheme() == "about"
+        {
+            let body = self.about_page(&mut about)?;
+            url = about.to_string();
+            // Relative links of piped gemtext resolve against the directory
+            // it was piped from
+            if url == STDIN_PAGE {
+                location = navigation::file_url(".");
+            }
+            self.show_gemtext(&url, body)?;
+        } else if let Some(path) = path.as_deref()
+            && path
+                .extension()
+                .is_some_and(|extension| extension == "gpub")
+        {
+            self.tab_mut().url = url.clone();
+            let opened = path
+                .canonicalize()
+                .map_err(Report::from)
+                .and_then(|source| Ok((source.display().to_string(), Gempub::open(path)?)))
+                .and_then(|(source, gempub)| self.open_book(source, gempub));
+            if let Err(error) = opened {
+                let body = format!(
+                    "# Cannot open the book\n\n> {}\n\n=> {} Try again\n",
+                    error, url
+                );
+                self.show_gemtext(&url, body)?;
+            }
+        } else if let Some(path) = path.as_deref()
+            && path.is_dir()
+        {
+            let body = navigation::directory_listing(path).unwrap_or_else(|error| {
+                format!(
+                    "# Cannot list the directory\n\n> {}\n\n=> {} Try again\n",
+                    error, url
+                )
+            });
+            self.show_gemtext(&url, body)?;
+        } else if let Some(path) = path {
+            match fs::read(&path) {
+                Ok(body) => {
+                    let mime = mime_type(&path);
+                    let response = Response {
+                        url: Url::parse(&url)?,
+                        redirects: Vec::new(),
+                        // Anything else is shown as text, like files without
+                        // an extension usually are
+                        meta: if mime.starts_with("text/") {
+                            mime
+                        } else {
+                            String::from("text/plain")
+                        },
+                        body,
+                        metrics: None,
+                    };
+
+                    url = self.show_response(url, response, false)?;
+                }
+                Err(error) => {
+                    let body = format!(
+                        "# Cannot read the file\n\n> {}\n\n=> {} Try again\n",
+                        error, url
+                    );
+                    self.show_gemtext(&url, body)?;
+                }
+            }
+        } else if self.offline {
+            match self.cache.get(&url, Lookup::Any) {
+                Some(response) => {
+                    cached_at = self.cache.stored(&url);
+                    location = Some(response.url.to_string());
+                    url = self.show_response(url, response, false)?;
+                }
+                None => {
+                    let body = format!(
+                        "# Offline\n\nThe page is not in the cache, and nothing is fetched \
+                         while offline.\n\n=> {0} {0}\n=> {1}?{2} Retry online\n",
+                        url,
+                        ONLINE_PAGE,
+                        encode(&url)
+                    );
+                    self.show_gemtext(&url, body)?;
+                }
+            }
+        } else {
+            let cached = self.cache.get(&url, lookup);
+            if cached.is_some() {
+                cached_at = self.cache.stored(&url);
+            }
+            let fetched = cached.is_none();
+            debug!(url, cached = !fetched, "Loading");
+
+            let identity = self.sites.identity(&url, &self.identities);
+            let response = match cached {
+                Some(response) => Ok(response),
+                None if url.starts_with("gopher://") => {
+                    // Failures share the error page of Gemini requests
+                    self.gopher
+                        .fetch(&url)
+                        .map(|response| Response {
+                            url: response.url,
+                            redirects: Vec::new(),
+                            meta: response.mime,
+                            body: response.body,
+                            metrics: None,
+                        })
+                        .map_err(|error| GeminiClientError::RequestError(error.to_string()))
+                }
+                None if url.starts_with("finger://") => self
+                    .finger
+                    .fetch(&url)
+                    .map(|response| Response {
+                        url: response.url,
+                        redirects: Vec::new(),
+                        meta: String::from(finger_client::MIME),
+                        body: response.body,
+                        metrics: None,
+                    })
+                    .map_err(|error| GeminiClientError::RequestError(error.to_string())),
+                None => self.client.fetch_as(&url, identity),
+            };
+            if let Err(error) = &response {
+                warn!(url, %error, "Request failed");
+            }
+
+            url = match response {
+                Ok(response)
+                    if !response.mime().starts_with("text/") && response.mime() != gempub::MIME =>
+                {
+                    let path = self.downloads.save(response.url.as_str(), &response.body)?;
+                    self.message =
+                        Some(format!("Saved to {} | {}", path.display(), DOWNLOADS_PAGE));
+                    if let Some(handler) = self.config.handler(response.mime()) {
+                        let handler = handler.to_owned();
+                        self.run_suspended(handler_on(&handler, &path))?;
+                    }
+                    return Ok(false);
+                }
+                Ok(response) => {
+                    location = Some(response.url.to_string());
+                    self.show_response(url, response, fetched)?
+                }
+                Err(GeminiClientError::TruncatedResponse(received)) => {
+                    // Whatever the server sent is shown as is, for inspection
+                    let tab = &mut self.tabs[self.active];
+                    tab.close_book(&mut self.positions)?;
+                    tab.encoding = "UTF-8";
+                    tab.mime = String::new();
+                    tab.metrics = None;
+                    tab.show_page(
+                        String::from_utf8_lossy(&received).into_owned(),
+                        0,
+                        &self.config.filters,
+                        &self.theme,
+                    );
+                    tab.raw = true;
+                    tab.apply_filters(&self.config.filters, &self.theme);
+                    self.message = Some(String::from(
+                        "Truncated response, showing the bytes received",
+                    ));
+                    url
+                }
+                Err(GeminiClientError::Disconnected(disconnect)) => {
+                    let body = format!(
+                        "# No response\n\n> {}\n\n=> {} Try again\n",
+                        disconnect, url
+                    );
+
+                    self.show_gemtext(&url, body)?;
+                    url
+                }
+                Err(GeminiClientError::Redirected(target)) => {
+                    let host = Url::parse(&url)
+                        .ok()
+                        .and_then(|url| url.host_str().map(str::to_owned))
+                        .unwrap_or_default();
+                    // The target comes from the server, and the page can run
+                    // about: actions
+                    let target = if target.scheme() == "about" {
+                        format!("> {}", target)
+                    } else {
+                        format!("=> {0} {0}", target)
+                    };
+                    let body = format!(
+                        "# Redirect\n\nThe page moved, and redirects are not followed for \
+                         {0}.\n\n{1}\n=> about:site?host={2} Settings of {0}\n",
+                        host,
+                        target,
+                        encode(&host)
+                    );
+
+                    self.show_gemtext(&url, body)?;
+                    url
+                }
+                Err(GeminiClientError::SlowDown { host, wait }) => {
+                    let body = format!(
+                        "# Slow down\n\n> {} asks to be left alone for {} more seconds.\n\n\
+                         => {} Try again\n",
+                        host,
+                        wait.as_secs_f64().ceil(),
+                        url
+                    );
+
+                    self.show_gemtext(&url, body)?;
+                    url
+                }
+                Err(GeminiClientError::Security(error)) => {
+                    let body = format!(
+                        "# Response refused\n\nThe server sent a malformed header, the \
+                         connection was dropped: {}.\n",
+                        error
+                    );
+
+                    self.show_gemtext(&url, body)?;
+                    url
+                }
+                Err(GeminiClientError::CertificateRequired { status, meta }) => {
+                    let heading = match status {
+                        60 => "Client certificate required",
+                        61 => "Client certificate not authorized",
+                        _ => "Client certificate not valid",
+                    };
+                    let mut body = format!("# {}\n\n", heading);
+                    if !meta.is_empty() {
+                        body.push_str(&format!("> {}\n\n", meta));
+                    }
+                    body.push_str(&self.identities.choices(&url));
+
+                    self.show_gemtext(&url, body)?;
+                    url
+                }
+                Err(GeminiClientError::CertificateChanged {
+                    host,
+                    pinned,
+                    presented,
+                }) => {
+                    self.untrusted = Some(Untrusted {
+                        url,
+                        host,
+                        pinned: *pinned,
+                        presented: *presented,
+                    });
+                    self.input.mode = InputMode::Trust;
+                    return Ok(false);
+                }
+                Err(error) => {
+                    let body = format!("# Request failed\n\n> {}\n\n=> {} Try again\n", error, url);
+
+                    self.show_gemtext(&url, body)?;
+                    url
+                }
+            };
+        }
+
+        let tab = &mut self.tabs[self.active];
+        tab.location = location.unwrap_or_else(|| url.clone());
+        tab.url = url;
+        tab.cached_at = cached_at;
+        self.input.value = tab.location.clone();
+        self.reset_cursor();
+        self.input.mode = InputMode::Normal;
+        self.speak();
+
+        Ok(true)
+    }
+
+    /// Answers the prompt for a changed certificate, loading the page again
+    /// when it is trusted.
+    fn trust_certificate(&mut self, trust: Trust) -> Result<()> {
+        self.exit_edit_mode();
+        let Some(untrusted) = self.untrusted.take() else {
+            return Ok(());
+        };
+
+        self.client
+            .trust(&untrusted.host, untrusted.presented, trust)?;
+        self.open(untrusted.url)
+    }
+
+    /// Shows a response in the current tab, storing it in the cache when it
+    /// was just fetched, and returns the URL it was finally served from.
+    fn show_response(
+        &mut self,
+        mut url: String,
+        response: Response,
+        fetched: bool,
+    ) -> Result<String> {
+        let canonical = navigation::canonical_url(&url, &response);
+        let requested = url.clone();
+
+        let stored = match (
+            self.subscriptions.contains(&url),
+            self.bookmarks.contains(&url),
+        ) {
+            _ if canonical == url => None,
+            (true, true) => Some("Subscription and Bookmark"),
+            (true, false) => Some("Subscription"),
+            (false, true) => Some("Bookmark"),
+            (false, false) => None,
+        };
+        if let Some(stored) = stored {
+            self.message = Some(format!(
+                "Moved permanently to {} | <{}> - Update {}",
+                canonical,
+                self.config.keys.label(Action::UpdateMoved),
+                stored
+            ));
+            self.moved = Some((url, canonical.clone()));
+        }
+        url = canonical;
+        self.tab_mut().url = url.clone();
+
+        let (bookmarks, subscriptions) = (&self.bookmarks, &self.subscriptions);
+        // Requests for the URL that redirected are answered by its target
+        if fetched
+            && let Err(error) = self
+                .cache
+                .store(
+                    response.url.as_str(),
+                    &response.meta,
+                    &response.body,
+                    |url| bookmarks.contains(url) || subscriptions.contains(url),
+                )
+                .and_then(|()| self.cache.alias(&requested, response.url.as_str()))
+        {
+            self.message = Some(format!("Could not cache the page: {}", error));
+        }
+
+        let metrics = response.metrics;
+        if response.mime() == gempub::MIME {
+            self.open_book(url.clone(), Gempub::from_bytes(response.body)?)?;
+        } else {
+            let tab = &mut self.tabs[self.active];
+            tab.close_book(&mut self.positions)?;
+            let response_mime = response.mime().to_owned();
+            let decoded = response.decode()?;
+
+            if decoded.detected {
+                self.message = Some(format!("Decoded as {} (detected)", decoded.encoding));
+            }
+
+            tab.encoding = decoded.encoding;
+            tab.mime = response_mime;
+            tab.show_page(decoded.text, 0, &self.config.filters, &self.theme);
+        }
+        self.tab_mut().metrics = metrics;
+
+        Ok(url)
+    }
+
+    /// Shows gemtext made by the browser itself rather than fetched.
+    fn show_gemtext(&mut self, url: &str, body: String) -> Result<()> {
+        let tab = &mut self.tabs[self.active];
+        tab.close_book(&mut self.positions)?;
+        tab.url = url.to_owned();
+        tab.metrics = None;
+        tab.encoding = "UTF-8";
+        tab.mime = String::from(gemtext::MIME);
+        tab.show_page(body, 0, &self.config.filters, &self.theme);
+        // Piped gemtext comes from elsewhere, like any page
+        tab.internal = url != STDIN_PAGE;
+
+        Ok(())
+    }
+
+    /// Builds the gemtext of an internal `about:` page. Pages performing an
+    /// action drop it from the URL, so that reloading them does not repeat it.
+    fn about_page(&mut self, url: &mut Url) -> Result<String> {
+        let query = url
+            .query()
+            .map(|query| percent_decode_str(query).decode_utf8_lossy().into_owned())
+            .filter(|query| !query.trim().is_empty());
+
+        match url.path() {
+            "history" => {
+                let pairs = url.query_pairs().into_owned().collect::<Vec<_>>();
+                let action = match pairs.first().map(|(key, value)| (key.as_str(), value)) {
+                    Some(("delete", target)) => Some(self.visits.remove(target)),
+                    Some(("clear", _)) => Some(self.visits.clear()),
+                    _ => None,
+                };
+
+                match action {
+                    Some(done) => {
+                        url.set_query(None);
+                        done?;
+                        Ok(self.visits.to_gemtext(None, self.config.absolute_dates))
+                    }
+                    None => Ok(self
+                        .visits
+                        .to_gemtext(query.as_deref().map(str::trim), self.config.absolute_dates)),
+                }
+            }
+            "bookmarks" => {
+                if let Some((_, target)) = url.query_pairs().find(|(key, _)| key == "delete") {
+                    self.bookmarks.remove(&target)?;
+                    url.set_query(None);
+                }
+
+                Ok(self.bookmarks.to_gemtext())
+            }
+            "rotation" => {
+                let Some(query) = query else {
+                    return Ok(rotation::usage());
+                };
+                let (host, path) = match query.trim().split_once(' ') {
+                    Some((host, path)) => (host, Some(path.trim())),
+                    None => (query.trim(), None),
+                };
+                let target = Url::parse(&format!("gemini://{}/", host))?;
+                let name = target.host_str().unwrap_or(host);
+
+                let rotation = Rotation {
+                    host,
+                    pinned: self.client.certificate(name),
+                    presented: self
+                        .client
+                        .presented_certificate(name, target.port().unwrap_or(DEFAULT_PORT))
+                        .map_err(|error| error.to_string()),
+                    planned: path.map(|path| {
+                        let certificate = fs::read_to_string(expand_home(path))
+                            .map_err(|error| error.to_string())
+                            .and_then(|pem| {
+                                Certificate::from_pem(&pem).map_err(|error| error.to_string())
+                            });
+                        (path, certificate)
+                    }),
+                };
+
+                Ok(rotation.to_gemtext(Local::now().timestamp()))
+            }
+            "downloads" => {
+                let pairs = url.query_pairs().into_owned().collect::<Vec<_>>();
+                match pairs
+                    .first()
+                    .map(|(key, value)| (key.as_str(), value.parse()))
+                {
+                    Some(("cancel", Ok(id))) => self.downloads.cancel(id),
+                    Some(("retry", Ok(id))) => self.downloads.retry(id),
+                    Some(("clear", _)) => self.downloads.clear(),
+                    _ => {}
+                }
+                url.set_query(None);
+
+                Ok(self.downloads.to_gemtext())
+            }
+            "site" => {
+                let pairs = url.query_pairs().into_owned().collect::<Vec<_>>();
+                let Some(host) = pairs
+                    .iter()
+                    .find(|(key, _)| key == "host")
+                    .map(|(_, host)| host.trim().to_lowercase())
+                    .filter(|host| !host.is_empty())
+                else {
+                    let current = Url::parse(&self.tab().location)
+                        .ok()
+                        .filter(|url| url.scheme() == "gemini")
+                        .and_then(|url| url.host_str().map(str::to_owned));
+                    return Ok(self.sites.to_gemtext(current.as_deref()));
+                };
+
+                let mut page = String::new();
+                if let Some((key, value)) = pairs.iter().find(|(key, _)| key != "host") {
+                    if let Err(error) =
+                        self.sites
+                            .set(&host, key, value, &self.client, &self.identities)
+                    {
+                        page.push_str(&format!("> {}\n\n", error));
+                    }
+                    url.set_query(Some(&format!("host={}", encode(&host))));
+                }
+
+                page.push_str(&self.sites.host_gemtext(
+                    &host,
+                    &self.identities,
+                    self.config.tls_policy,
+                ));
+                Ok(page)
+            }
+            "help" => Ok(self.config.keys.to_gemtext()),
+            "version" => Ok(version_page()),
+            "tree" => Ok(self.tab().history.to_gemtext()),
+            "stdin" => Ok(self.stdin.clone().unwrap_or_default()),
+            "feeds" => {
+                if query.as_deref() == Some("check") {
+                    self.feeds.check(&self.client, &self.subscriptions);
+                    url.set_query(None);
+                }
+
+                let page = self
+                    .feeds
+                    .to_gemtext(&self.subscriptions, self.config.absolute_dates);
+                self.feeds.mark_seen()?;
+                Ok(page)
+            }
+            "cache" => {
+                if query.as_deref() == Some("clear") {
+                    self.cache.clear()?;
+                    url.set_query(None);
+                }
+
+                let (bookmarks, subscriptions) = (&self.bookmarks, &self.subscriptions);
+                Ok(self
+                    .cache
+                    .to_gemtext(|url| bookmarks.contains(url) || subscriptions.contains(url)))
+            }
+            "identities" => {
+                let pairs = url.query_pairs().into_owned().collect::<Vec<_>>();
+                let value = |key: &str| {
+                    pairs
+                        .iter()
+                        .find(|(name, _)| name == key)
+                        .map(|(_, value)| value.as_str())
+                };
+
+                let mut page = String::new();
+                let done = if let Some(name) = value("new") {
+                    self.identities.create(name.trim())
+                } else if let Some(name) = value("delete") {
+                    self.identities.delete(name)
+                } else if let Some(prefix) = value("forget") {
+                    self.identities.unscope(prefix)
+                } else if let (Some(name), Some(prefix)) = (value("use"), value("for")) {
+                    self.identities.scope(name, prefix).map(|()| {
+                        page.push_str(&format!("=> {0} Open {0} as {1}\n\n", prefix, name));
+                    })
+                } else {
+                    Ok(())
+                };
+                if let Err(error) = done {
+                    page.push_str(&format!("> {}\n\n", error));
+                }
+                url.set_query(None);
+
+                page.push_str(&self.identities.to_gemtext());
+                Ok(page)
+            }
+            _ => Ok(format!(
+                "# Unknown page\n\n> {} is not a page of the browser\n\n=> about:help Help\n",
+                url
+            )),
+        }
+    }
+
+    fn update_moved_subscription(&mut self) -> Result<()> {
+        if let Some((from, to)) = self.moved.take() {
+            let subscribed = self.subscriptions.contains(&from);
+            let bookmarked = self.bookmarks.contains(&from);
+            if subscribed {
+                self.subscriptions.replace(&from, &to)?;
+            }
+            if bookmarked {
+                self.bookmarks.replace(&from, &to)?;
+            }
+            self.message = Some(String::from(match (subscribed, bookmarked) {
+                (true, true) => "Subscription and bookmark updated",
+                (false, true) => "Bookmark updated",
+                _ => "Subscription updated",
+            }));
+        }
+
+        Ok(())
+    }
+
+    fn toggle_subscription(&mut self) -> Result<()> {
+        let tab = &self.tabs[self.active];
+
+        if tab.url.is_empty() {
+            return Ok(());
+        }
+
+        let title = tab.page_title().unwrap_or_default();
+        self.subscriptions.toggle(&tab.url, title)?;
+
+        self.message = Some(String::from(if self.subscriptions.contains(&tab.url) {
+            "Subscribed"
+        } else {
+            "Unsubscribed"
+        }));
+
+        Ok(())
+    }
+
+    fn toggle_bookmark(&mut self) -> Result<()> {
+        let tab = &self.tabs[self.active];
+
+        if tab.url.is_empty() || tab.url.starts_with("about:") {
+            return Ok(());
+        }
+
+        let title = tab.title().unwrap_or_default();
+        self.bookmarks.toggle(&tab.url, title)?;
+
+        self.message = Some(String::from(if self.bookmarks.contains(&tab.url) {
+            "Bookmarked"
+        } else {
+            "Bookmark removed"
+        }));
+
+        Ok(())
+    }
+
+    fn toggle_source(&mut self) {
+        let tab = &mut self.tabs[self.active];
+        tab.raw = !tab.raw;
+        tab.apply_filters(&self.config.filters, &self.theme);
+    }
+
+    fn toggle_offline(&mut self) {
+        self.offline = !self.offline;
+        self.message = Some(String::from(if self.offline {
+            "Offline, pages only come from the cache"
+        } else {
+            "Online"
+        }));
+    }
+
+    /// Tells that the network is needed for the action while offline,
+    /// returning whether it must be held back.
+    fn refuse_offline(&mut self, action: &str) -> bool {
+        if self.offline {
+            self.message = Some(format!(
+                "Offline, {} to go online before {}",
+                self.config.keys.label(Action::ToggleOffline),
+                action
+            ));
+        }
+
+        self.offline
+    }
+
+    fn toggle_reader(&mut self) {
+        let tab = &mut self.tabs[self.active];
+        tab.reader = !tab.reader;
+        tab.apply_filters(&self.config.filters, &self.theme);
+        self.speak();
+    }
+
+    /// Reads the current page aloud with the speech command when its tab is
+    /// in reader mode, cutting off the page read before.
+    fn speak(&mut self) {
+        self.stop_speech();
+
+        let tab = self.tab();
+        let Some(command) = &self.config.speech_command else {
+            return;
+        };
+        if !tab.reader || tab.raw {
+            return;
+        }
+
+        match reader::speak(command, reader::text(&tab.document())) {
+            Ok(speech) => self.speech = Some(speech),
+            Err(error) => self.message = Some(format!("Could not run {}: {}", command, error)),
+        }
+    }
+
+    fn stop_speech(&mut self) {
+        if let Some(mut speech) = self.speech.take() {
+            let _ = speech.kill();
+            let _ = speech.wait();
+        }
+    }
+
+    fn copy(&mut self, text: &str, done: String) {
+        self.message = Some(match self.clipboard.copy(text) {
+            Ok(()) => done,
+            Err(error) => format!("Could not copy: {}", error),
+        });
+    }
+
+    fn copy_url(&mut self) {
+        let url = self.tab().url.clone();
+        if !url.is_empty() {
+            self.copy(&url, String::from("Copied page URL"));
+        }
+    }
+
+    /// Copies the target of the focused link, resolved against the page.
+    fn copy_link(&mut self) {
+        let tab = self.tab();
+        let Some(target) = tab.focused_link() else {
+            return;
+        };
+
+        let link = Url::parse(&tab.location)
+            .and_then(|url| url.join(target))
+            .map_or_else(|_| target.to_owned(), |url| url.to_string());
+        self.copy(&link, String::from("Copied link"));
+    }
+
+    /// Tells what the focused link points to without opening it: its MIME
+    /// type and size, and the title of gemtext pages. Pages that aren't
+    /// cached are only read up to their title.
+    fn preview_link(&mut self) {
+        let tab = self.tab();
+        let Some(target) = tab.focused_link() else {
+            return;
+        };
+        let Ok(link) = Url::parse(&tab.location).and_then(|url| url.join(target)) else {
+            return;
+        };
+        if link.scheme() != "gemini" {
+            self.message = Some(format!("{} is not a Gemini link", link));
+            return;
+        }
+
+        let (response, complete) = match self.cache.get(link.as_str(), Lookup::Any) {
+            Some(response) => (Ok(response), true),
+            None if self.refuse_offline("previewing links") => return,
+            None => {
+                let identity = self.sites.identity(link.as_str(), &self.identities);
+                let response = self.client.peek(link.as_str(), identity, PREVIEW_BYTES);
+                let complete = response
+                    .as_ref()
+                    .is_ok_and(|response| response.body.len() < PREVIEW_BYTES);
+                (response, complete)
+            }
+        };
+
+        self.message = Some(match response {
+            Ok(response) => {
+                let size = if complete {
+                    format!("{} bytes", response.body.len())
+                } else {
+                    format!("{} bytes or more", PREVIEW_BYTES)
+                };
+                let title = (response.mime() == gemtext::MIME)
+                    .then(|| String::from_utf8_lossy(&response.body).into_owned())
+                    .and_then(|text| gemtext::Document::parse(&text).title().map(str::to_owned));
+
+                match title {
+                    Some(title) => format!("{} · {}, {}", title, response.mime(), size),
+                    None => format!("{}, {}", response.mime(), size),
+                }
+            }
+            Err(error) => format!("Could not preview the link: {}", error),
+        });
+    }
+
+    /// Starts a selection on the top visible line.
+    fn enter_select_mode(&mut self) {
+        let tab = self.tab();
+        if tab.lines.is_empty() {
+            return;
+        }
+
+        let line = tab.top_line(self.body_width).min(tab.lines.len() - 1);
+        self.selection = (line, line);
+        self.input.mode = InputMode::Select;
+    }
+
+    fn extend_selection(&mut self, offset: isize) {
+        let (width, height) = (self.body_width, self.body_height as usize);
+        let last = self.tab().lines.len().saturating_sub(1);
+        let cursor = self.selection.1.saturating_add_signed(offset).min(last);
+
+        self.selection.1 = cursor;
+        self.tab_mut().reveal(cursor, width, height);
+    }
+
+    fn copy_selection(&mut self) {
+        let (start, end) = self.selection;
+        let (start, end) = (start.min(end), start.max(end));
+        let text = self.tab().lines[start..=end]
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let count = end - start + 1;
+        self.copy(
+            &text,
+            format!("Copied {} line{}", count, if count == 1 { "" } else { "s" }),
+        );
+        self.exit_edit_mode();
+    }
+
+    /// Opens the save or export prompt, suggesting a file name taken from
+    /// the URL.
+    fn enter_save_mode(&mut self, mode: InputMode) {
+        let tab = self.tab();
+        if tab.url.is_empty() {
+            return;
+        }
+
+        let name = Url::parse(&tab.url)
+            .ok()
+            .and_then(|url| {
+                url.path_segments()?
+                    .next_back()
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_owned)
+            })
+            .unwrap_or_else(|| String::from("index.gmi"));
+
+        self.save_path = match mode {
+            InputMode::Export => format!(
+                "{}.html",
+                Path::new(&name).file_stem().unwrap_or_default().display()
+            ),
+            _ => name,
+        };
+        self.input.mode = mode;
+    }
+
+    /// Writes the source of the current page, as received, to the path typed
+    /// in the save prompt.
+    fn save_page(&mut self) {
+        let path = expand_home(&self.save_path);
+
+        self.message = Some(match fs::write(&path, &self.tab().source) {
+            Ok(()) => format!("Saved to {}", path.display()),
+            Err(error) => format!("Could not save to {}: {}", path.display(), error),
+        });
+    }
+
+    /// Writes the current page, converted to the format of the path typed in
+    /// the export prompt, to that path.
+    fn export_page(&mut self) {
+        let path = expand_home(&self.save_path);
+        let tab = self.tab();
+        let base = Url::parse(&tab.location).ok();
+        let exported = Format::of_path(&path).export(&tab.document(), base.as_ref());
+
+        self.message = Some(match fs::write(&path, exported) {
+            Ok(()) => format!("Exported to {}", path.display()),
+            Err(error) => format!("Could not export to {}: {}", path.display(), error),
+        });
+    }
+
+    /// `titan://` URL the current page is uploaded to, for Gemini pages.
+    fn titan_url(&self) -> Option<Url> {
+        let mut url = Url::parse(&self.tab().location)
+            .ok()
+            .filter(|url| url.scheme() == "gemini")?;
+        url.set_scheme("titan").ok()?;
+        url.set_query(None);
+
+        Some(url)
+    }
+
+    fn enter_upload_mode(&mut self) {
+        if self.refuse_offline("uploading") {
+            return;
+        }
+        if self.titan_url().is_none() {
+            self.message = Some(String::from("Only Gemini pages can be uploaded to"));
+            return;
+        }
+
+        self.save_path = String::new();
+        self.input.mode = InputMode::Upload;
+    }
+
+    /// Uploads the file typed in the upload prompt in place of the page.
+    fn upload_file(&mut self) -> Result<()> {
+        let path = expand_home(&self.save_path);
+
+        match fs::read(&path) {
+            Ok(body) => self.upload(body, mime_type(&path)),
+            Err(error) => {
+                self.message = Some(format!("Could not read {}: {}", path.display(), error));
+                Ok(())
+            }
+        }
+    }
+
+    /// Opens the source of the page in the editor, uploading it in place of
+    /// the page when it was changed.
+    fn edit_page(&mut self) -> Result<()> {
+        if self.refuse_offline("editing") {
+            return Ok(());
+        }
+        if self.titan_url().is_none() {
+            self.message = Some(String::from("Only Gemini pages can be edited"));
+            return Ok(());
+        }
+
+        let tab = self.tab();
+        let (source, mime) = (tab.source.clone(), tab.mime.clone());
+        let path = match temporary_file("gmi", source.as_bytes()) {
+            Ok(path) => path,
+            Err(error) => {
+                self.message = Some(format!("Could not write a temporary file: {}", error));
+                return Ok(());
+            }
+        };
+
+        let editor = env::var("VISUAL")
+            .or_else(|_| env::var("EDITOR"))
+            .ok()
+            .filter(|editor| !editor.trim().is_empty())
+            .unwrap_or_else(|| String::from("vi"));
+        let program = editor.split_whitespace().next().unwrap_or("vi");
+        let status = self.suspend(|| command_on(&editor, &path).status())?;
+
+        let edited = fs::read(&path);
+        let _ = fs::remove_file(&path);
+
+        match (status, edited) {
+            (Ok(status), Ok(edited)) if status.success() => {
+                if edited == source.as_bytes() {
+                    self.message = Some(String::from("No changes to upload"));
+                    Ok(())
+                } else if mime.is_empty() {
+                    self.upload(edited, String::from(gemtext::MIME))
+                } else {
+                    self.upload(edited, mime)
+                }
+            }
+            (Ok(status), _) => {
+                self.message = Some(format!(
+                    "{} exited with {}, nothing uploaded",
+                    program, status
+                ));
+                Ok(())
+            }
+            (Err(error), _) => {
+                self.message = Some(format!("Could not run {}: {}", program, error));
+                Ok(())
+            }
+        }
+    }
+
+    /// Uploads the content in place of the current page over Titan, then
+    /// shows the page the endpoint redirects to.
+    fn upload(&mut self, body: Vec<u8>, mime: String) -> Result<()> {
+        let Some(url) = self.titan_url() else {
+            return Ok(());
+        };
+        let upload = Upload {
+            mime,
+            token: url
+                .host_str()
+                .and_then(|host| self.config.titan_tokens.get(host))
+                .cloned(),
+            body,
+        };
+        let identity = self.sites.identity(&self.tab().location, &self.identities);
+
+        let response = match self.client.upload(url.as_str(), &upload, identity) {
+            Ok(response) => response,
+            Err(error) => {
+                self.message = Some(format!("Upload failed: {}", error));
+                return Ok(());
+            }
+        };
+        let done = format!("Uploaded {} bytes", upload.body.len());
+
+        // Without a redirect, the endpoint answered with a page of its own
+        // and the uploaded one is fetched again
+        if response.url.scheme() == "titan" {
+            self.reload(Lookup::Bypass)?;
+            self.message = Some(done);
+            return Ok(());
+        }
+
+        // The page the upload redirected to is shown from the cache rather
+        // than fetched a second time
+        let (bookmarks, subscriptions) = (&self.bookmarks, &self.subscriptions);
+        if let Err(error) = self.cache.store(
+            response.url.as_str(),
+            &response.meta,
+            &response.body,
+            |url| bookmarks.contains(url) || subscriptions.contains(url),
+        ) {
+            self.message = Some(format!("Could not cache the page: {}", error));
+        }
+
+        self.open(response.url.to_string())?;
+        self.message = Some(done);
+
+        Ok(())
+    }
+
+    /// Downloads the target of the focused link, or the current page, in the
+    /// background.
+    fn download(&mut self) {
+        if self.refuse_offline("downloading") {
+            return;
+        }
+        let tab = self.tab();
+        let url = match tab.focused_link() {
+            Some(target) => Url::parse(&tab.location).and_then(|base| base.join(target)),
+            None => Url::parse(&tab.location),
+        };
+
+        self.message = Some(match url {
+            Ok(url)
+                if url.scheme() == "gemini"
+                    || (cfg!(feature = "https") && url.scheme() == "https") =>
+            {
+                let identity = self.sites.identity(url.as_str(), &self.identities).cloned();
+                let path = self.downloads.queue(url.as_str(), identity);
+                format!("Downloading to {} | {}", path.display(), DOWNLOADS_PAGE)
+            }
+            _ => String::from("Only Gemini URLs can be downloaded"),
+        });
+    }
+
+    /// Widens or narrows the body by [`WIDTH_STEP`] from the width it is
+    /// shown at. Widening a body already as wide as the terminal lifts the
+    /// limit.
+    fn resize_body(&mut self, wider: bool) {
+        let width = self.body_width;
+        self.max_width = match wider {
+            true if self.max_width == 0 || width < self.max_width => 0,
+            true => width + WIDTH_STEP,
+            false => width.saturating_sub(WIDTH_STEP).max(MIN_WIDTH),
+        };
+
+        self.message = Some(match self.max_width {
+            0 => String::from("The body takes the whole width"),
+            max_width => format!("The body is at most {} columns wide", max_width),
+        });
+    }
+
+    /// Hands the rendered page to the pager, the terminal being given back
+    /// once it exits.
+    fn open_in_pager(&mut self) -> Result<()> {
+        let text = self
+            .tab()
+            .lines
+            .iter()
+            .map(|line| format!("{}\n", line.text))
+            .collect::<String>();
+        let path = match temporary_file("txt", text.as_bytes()) {
+            Ok(path) => path,
+            Err(error) => {
+                self.message = Some(format!("Could not write a temporary file: {}", error));
+                return Ok(());
+            }
+        };
+
+        let pager = self
+            .config
+            .pager
+            .clone()
+            .or_else(|| env::var("PAGER").ok())
+            .filter(|pager| !pager.trim().is_empty())
+            .unwrap_or_else(|| String::from("less"));
+        self.message = None;
+        self.run_suspended(Ok(command_on(&pager, &path)))?;
+        let _ = fs::remove_file(&path);
+
+        Ok(())
+    }
+
+    /// Hands the source of the page to the handler of its MIME type.
+    fn open_externally(&mut self) -> Result<()> {
+        let tab = self.tab();
+        let mime = if tab.mime.is_empty() {
+            gemtext::MIME
+        } else {
+            &tab.mime
+        };
+        let Some(handler) = self.config.handler(mime).map(str::to_owned) else {
+            self.message = Some(format!("No handler for {} in the configuration", mime));
+            return Ok(());
+        };
+
+        let extension = if mime == gemtext::MIME { "gmi" } else { "txt" };
+        let path = match temporary_file(extension, tab.source.as_bytes()) {
+            Ok(path) => path,
+            Err(error) => {
+                self.message = Some(format!("Could not write a temporary file: {}", error));
+                return Ok(());
+            }
+        };
+
+        self.message = None;
+        self.run_suspended(handler_on(&handler, &path))?;
+        let _ = fs::remove_file(&path);
+
+        Ok(())
+    }
+
+    /// Runs the command with the terminal handed over to it, telling when
+    /// it did not succeed.
+    fn run_suspended(&mut self, command: io::Result<process::Command>) -> Result<()> {
+        let mut command = match command {
+            Ok(command) => command,
+            Err(error) => {
+                self.message = Some(format!("Could not open the file: {}", error));
+                return Ok(());
+            }
+        };
+        let program = command.get_program().to_string_lossy().into_owned();
+        let status = self.suspend(|| command.status())?;
+
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => self.message = Some(format!("{} exited with {}", program, status)),
+            Err(error) => self.message = Some(format!("Could not run {}: {}", program, error)),
+        }
+
+        Ok(())
+    }
+
+    /// Hands the terminal over to another program while `run` runs.
+    fn suspend<T>(&mut self, run: impl FnOnce() -> T) -> Result<T> {
+        disable_terminal_features()?;
+        ratatui::restore();
+
+        let result = run();
+
+        enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        enable_terminal_features()?;
+        self.clear_screen = true;
+
+        Ok(result)
+    }
+
+    fn enter_search_mode(&mut self) {
+        self.input.mode = InputMode::Search;
+        self.tab_mut().search = Some(Search::default());
+    }
+
+    fn edit_search(&mut self, edit: impl FnOnce(&mut String)) {
+        let width = self.body_width;
+        let tab = self.tab_mut();
+        let top_line = tab.top_line(width);
+
+        if let Some(search) = &mut tab.search {
+            edit(&mut search.query);
+            search.find(&tab.lines);
+            search.focus_from(top_line);
+        }
+
+        self.scroll_to_match();
+    }
+
+    fn focus_next_match(&mut self) {
+        if let Some(search) = &mut self.tab_mut().search {
+            search.next();
+        }
+        self.scroll_to_match();
+    }
+
+    fn focus_previous_match(&mut self) {
+        if let Some(search) = &mut self.tab_mut().search {
+            search.previous();
+        }
+        self.scroll_to_match();
+    }
+
+    fn scroll_to_match(&mut self) {
+        let width = self.body_width;
+        let tab = self.tab_mut();
+        let Some(line) = tab
+            .search
+            .as_ref()
+            .and_then(Search::current)
+            .map(|found| found.line)
+        else {
+            return;
+        };
+
+        let offset = tab.line_offsets(width)[line];
+        tab.set_scroll(offset.min(tab.scroll.max));
+    }
+
+    /// Appends the first paragraph visible in the viewport to the notes file.
+    fn clip_paragraph(&mut self) -> Result<()> {
+        let Some(path) = &self.notes_path else {
+            return Ok(());
+        };
+        let tab = self.tab();
+
+        let paragraph = tab
+            .body
+            .lines()
+            .skip(tab.top_line(self.body_width))
+            .skip_while(|line| line.trim().is_empty())
+            .take_while(|line| !line.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        if paragraph.is_empty() {
+            return Ok(());
+        }
+
+        notes::clip(path, &paragraph, &tab.url, tab.page_title())?;
+        self.message = Some(format!("Clipped to {}", path.display()));
+
+        Ok(())
+    }
+
+    fn toggle_filters(&mut self) {
+        let tab = &mut self.tabs[self.active];
+        tab.filtering = !tab.filtering;
+        tab.apply_filters(&self.config.filters, &self.theme);
+        self.message = Some(String::from(if tab.filtering {
+            "Filters on"
+        } else {
+            "Filters off"
+        }));
+    }
+
+    fn open_book(&mut self, source: String, gempub: Gempub) -> Result<()> {
+        let tab = &mut self.tabs[self.active];
+        tab.close_book(&mut self.positions)?;
+
+        let position = self.positions.get(&source);
+        let mut book = Book {
+            source,
+            gempub,
+            position,
+        };
+
+        let body = book.gempub.read_chapter(book.position.chapter)?;
+        tab.encoding = "UTF-8";
+        tab.metrics = None;
+        tab.mime = String::from(gemtext::MIME);
+        tab.show_page(
+            body,
+            book.position.scroll(book.position.chapter),
+            &self.config.filters,
+            &self.theme,
+        );
+        tab.book = Some(book);
+
+        Ok(())
+    }
+
+    fn turn_chapter(&mut self, forward: bool) -> Result<()> {
+        let Some(book) = &self.tab().book else {
+            return Ok(());
+        };
+
+        let current = book.position.chapter;
+        let chapter = if forward {
+            current + 1
+        } else {
+            current.saturating_sub(1)
+        };
+
+        if chapter == current || chapter >= book.gempub.chapter_count() {
+            return Ok(());
+        }
+
+        self.go_to_chapter(chapter)
+    }
+
+    fn go_to_chapter(&mut self, chapter: usize) -> Result<()> {
+        let tab = &mut self.tabs[self.active];
+        let Some(book) = &mut tab.book else {
+            return Ok(());
+        };
+
+        let current = book.position.chapter;
+        book.position.set_scroll(current, tab.scroll.value);
+        book.position.chapter = chapter;
+        let body = book.gempub.read_chapter(chapter)?;
+        let scroll = book.position.scroll(chapter);
+
+        self.positions
+            .set(book.source.clone(), book.position.clone())?;
+        tab.show_page(body, scroll, &self.config.filters, &self.theme);
+        self.speak();
+
+        Ok(())
+    }
+}
+
+fn certificate_lines(certificate: &Certificate) -> Vec<Line<'static>> {
+    let date = |timestamp| {
+        DateTime::from_timestamp(timestamp, 0)
+            .map(|time| time.with_timezone(&Local).format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    };
+
+    [
+        ("Subject", certificate.subject.clone()),
+        ("Issuer", certificate.issuer.clone()),
+        ("Valid from", date(certificate.not_before)),
+        ("Valid until", date(certificate.not_after)),
+        ("SHA-256", certificate.fingerprint.clone()),
+    ]
+    .into_iter()
+    .map(|(name, value)| field(name, value))
+    .collect()
+}
+
+/// Writes the contents to a new temporary file only its owner can read. The
+/// file must not exist yet, so that no other user can have it written through
+/// a link they made, and its name cannot be guessed in advance.
+fn temporary_file(extension: &str, contents: &[u8]) -> io::Result<PathBuf> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    let path = env::temp_dir().join(format!(
+        "dioscuri-{}-{}.{}",
+        process::id(),
+        nanos,
+        extension
+    ));
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let written = options.open(&path)?.write_all(contents);
+    if let Err(error) = written {
+        let _ = fs::remove_file(&path);
+        return Err(error);
+    }
+
+    Ok(path)
+}
+
+/// Command running the program of a setting like `$EDITOR`, with the
+/// arguments it gives, on the file.
+fn command_on(setting: &str, path: &Path) -> process::Command {
+    let mut words = setting.split_whitespace();
+    let mut command = process::Command::new(words.next().unwrap_or_default());
+    command.args(words).arg(path);
+    command
+}
+
+/// Command running a handler on the file as mailcap does: `%s` in its
+/// arguments stands for the path, otherwise the file is piped to it.
+fn handler_on(handler: &str, path: &Path) -> io::Result<process::Command> {
+    let mut words = handler.split_whitespace();
+    let mut command = process::Command::new(words.next().unwrap_or_default());
+    let mut piped = true;
+    for word in words {
+        if word.contains("%s") {
+            command.arg(word.replace("%s", &path.to_string_lossy()));
+            piped = false;
+        } else {
+            command.arg(word);
+        }
+    }
+
+    if piped {
+        command.stdin(fs::File::open(path)?);
+    }
+    Ok(command)
+}
+
+/// Line of a dialog naming a value.
+fn field(name: &str, value: String) -> Line<'static> {
+    Line::from(vec![format!("{:<14}", name).dim(), value.into()])
+}
+
+/// Gemtext of about:version, describing the build.
+fn version_page() -> String {
+    let features = [cfg!(feature = "https").then_some("https")]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+    format!(
+        "# dioscuri {}\n\n\
+         * Platform: {} {}\n\
+         * Features: {}\n\
+         * Build: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        env::consts::OS,
+        env::consts::ARCH,
+        if features.is_empty() {
+            String::from("none")
+        } else {
+            features.join(", ")
+        },
+        if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        },
+    )
+}
+
+/// Shortens the URL to fit in `width` columns, dropping the `gemini://`
+/// scheme and then the middle of the path, so the host and the end of the
+/// path stay visible.
+fn abbreviate_url(url: &str, width: usize) -> String {
+    let url = url.strip_prefix("gemini://").unwrap_or(url);
+    let length = url.chars().count();
+    if length <= width {
+        return url.to_owned();
+    }
+
+    let host = url.split_inclusive('/').next().unwrap_or(url);
+    let host = if host.chars().count() + 2 <= width / 2 {
+        host
+    } else {
+        ""
+    };
+    let tail = width.saturating_sub(host.chars().count() + 1);
+    let tail = url.chars().skip(length - tail).collect::<String>();
+
+    format!("{}…{}", host, tail)
+}
+
+/// Turns on the terminal features the UI relies on: mouse wheel events,
+/// focus changes, pastes told apart from typing and, where supported,
+/// modifiers reported with every key so that shortcuts like Ctrl-Shift-D can
+/// be told apart.
+fn enable_terminal_features() -> io::Result<()> {
+    execute!(
+        io::stdout(),
+        EnableMouseCapture,
+        EnableFocusChange,
+        EnableBracketedPaste
+    )?;
+    // Terminals without the enhancement ignore the request
+    let _ = execute!(
+        io::stdout(),
+        PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+    );
+
+    Ok(())
+}
+
+/// Turns the terminal features off again, before leaving the alternate
+/// screen since some terminals keep them per screen.
+fn disable_terminal_features() -> io::Result<()> {
+    let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+    execute!(
+        io::stdout(),
+        DisableMouseCapture,
+        DisableFocusChange,
+        DisableBracketedPaste
+    )
+}
+
+/// Path typed in a prompt, with a leading `~/` standing for the home
+/// directory.
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => env::home_dir()
+            .map(|home| home.join(rest))
+            .unwrap_or_else(|| PathBuf::from(path)),
+        None => PathBuf::from(path),
+    }
+}
+
+/// MIME type a file is uploaded with, from its extension.
+fn mime_type(path: &Path) -> String {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    String::from(match extension.as_str() {
+        "gmi" | "gemini" => gemtext::MIME,
+        "txt" => "text/plain",
+        "md" => "text/markdown",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    })
+}
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+
+    let args = Args::parse()?;
+
+    if args.help {
+        println!("{}", Args::usage());
+        return Ok(());
+    }
+
+    if let Some(path) = &args.export_subscriptions {
+        fs::write(path, Subscriptions::load().to_opml())?;
+        return Ok(());
+    }
+
+    // Gemtext piped in is read before the terminal is taken over, with
+    // keys read from the terminal itself
+    let stdin = match args.url.as_deref() {
+        None | Some("-") if !io::stdin().is_terminal() => Some(io::read_to_string(io::stdin())?),
+        _ => None,
+    };
+    let start_url = match stdin {
+        Some(_) => Some(String::from(STDIN_PAGE)),
+        None => args
+            .url
+            .clone()
+            .map(|url| navigation::file_url(&url).unwrap_or(url)),
+    };
+
+    if args.dump || args.pretty || args.reader || args.export.is_some() {
+        let config = load_config(&args)?;
+        let client = build_client(&config);
+        let sites = Sites::load(&config.sites);
+        sites.apply(&client);
+        // Offline, pages come from the cache of the UI
+        let cache = config.offline.then(|| {
+            Cache::load(
+                config.cache_size_mb * 1024 * 1024,
+                Duration::from_secs(config.cache_ttl_secs),
+            )
+        });
+        return print_page(&args, start_url, stdin, &client, &sites, cache);
+    }
+
+    // Hand the URL over to an already running instance instead of opening a
+    // second UI (e.g. when the OS opens a gemini:// link)
+    if let Some(url) = &start_url
+        && stdin.is_none()
+        && let Some(reply) = ipc::send(&format!("open-tab {}", url))
+    {
+        println!("{}", reply?);
+        return Ok(());
+    }
+
+    let config = load_config(&args)?;
+    let log = Log::init(config.log_level)?;
+    info!(version = env!("CARGO_PKG_VERSION"), "Starting");
+    let gemini_client = build_client(&config);
+    let control = ControlSocket::bind().ok();
+
+    let start_url = start_url.or_else(|| config.fetch_start_url.then(|| config.start_url.clone()));
+
+    let mut terminal = ratatui::init();
+    enable_terminal_features()?;
+
+    let mut app = App::new(config, gemini_client, control);
+    app.stdin = stdin;
+    app.log = log;
+    let result = match start_url {
+        Some(url) => app.open(url).and_then(|()| app.run(&mut terminal)),
+        None => app.run(&mut terminal),
+    };
+
+    disable_terminal_features()?;
+    ratatui::restore();
+
+    result
+}
+
+/// Configuration, with the settings given on the command line in place of
+/// those of the file.
+fn load_config(args: &Args) -> Result<Config> {
+    let mut config = Config::load()?;
+    if let Some(policy) = args.tls_policy {
+        config.tls_policy = policy;
+    }
+    config.offline |= args.offline;
+
+    Ok(config)
+}
+
+fn build_client(config: &Config) -> GeminiClient {
+    let mut builder = GeminiClient::builder()
+        .redirect_limit(config.redirect_limit)
+        .tls_policy(config.tls_policy)
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .slow_down_wait(Duration::from_secs(config.slow_down_wait_secs));
+    if let Some(dir) = dirs::data_dir() {
+        builder = builder.known_hosts(dir.join("known_hosts"));
+    }
+    if !config.hosts.is_empty() {
+        let resolver = config
+            .hosts
+            .iter()
+            .fold(StaticResolver::new(), |resolver, (host, &address)| {
+                resolver.host(host, address)
+            });
+        builder = builder.resolver(resolver);
+    }
+    if let Some(client_id) = &config.client_id {
+        builder = builder.client_id(client_id);
+    }
+    if let Some(proxy) = &config.proxy {
+        builder = builder.proxy(
+            proxy
+                .trim_start_matches("socks5h://")
+                .trim_start_matches("socks5://"),
+        );
+    }
+    builder.build()
+}
+
+/// Prints the page without the UI, like curl would: gemtext piped in, a
+/// local file or a Gemini page. It is converted to the export format,
+/// linearized for screen readers, or rendered for the terminal when pretty,
+/// if any of them is asked for. Offline, Gemini pages come from the cache.
+fn print_page(
+    args: &Args,
+    url: Option<String>,
+    stdin: Option<String>,
+    client: &GeminiClient,
+    sites: &Sites,
+    cache: Option<Cache>,
+) -> Result<()> {
+    let (base, mime, text) = match (stdin, url) {
+        (Some(text), _) => (None, String::from(gemtext::MIME), text),
+        (None, Some(url)) => {
+            let parsed = Url::parse(&url)?;
+            match parsed.scheme() {
+                "file" => {
+                    let Ok(path) = parsed.to_file_path() else {
+                        bail!("{} is not a local path", url);
+                    };
+                    let text = fs::read_to_string(&path)?;
+                    (Some(parsed), mime_type(&path), text)
+                }
+                "gemini" if let Some(mut cache) = cache => {
+                    let Some(response) = cache.get(&url, Lookup::Any) else {
+                        bail!(
+                            "{} is not in the cache, and nothing is fetched offline",
+                            url
+                        );
+                    };
+                    let mime = response.mime().to_owned();
+                    (Some(response.url.clone()), mime, response.decode()?.text)
+                }
+                "gemini" => {
+                    let identities = Identities::load();
+                    let response = client.fetch_as(&url, sites.identity(&url, &identities))?;
+                    let (base, mime) = (response.url.clone(), response.mime().to_owned());
+                    (Some(base), mime, response.decode()?.text)
+                }
+                _ => bail!("Only Gemini pages and local files can be printed"),
+            }
+        }
+        (None, None) => bail!("Printing requires a URL, a file or gemtext on the standard input"),
+    };
+
+    let document = || {
+        if mime == gemtext::MIME {
+            gemtext::Document::parse(&text)
+        } else {
+            gemtext::Document::plain(&text)
+        }
+    };
+    let page = match args.export {
+        Some(format) => format.export(&document(), base.as_ref()),
+        None if args.reader => {
+            let linear = reader::linearize(&document());
+            reader::text(&linear) + &reader::footnotes(&linear, base.as_ref())
+        }
+        None if args.pretty && mime == gemtext::MIME => pretty::pretty(&document(), base.as_ref()),
+        None => text.clone(),
+    };
+    print!("{}", page);
+
+    Ok(())
 }
@@ -0,0 +1,260 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use gemini_client::{Response, normalize};
+use url::Url;
+
+/// Internal page drawing the history tree of the current tab. It is not
+/// recorded in the history itself.
+pub const TREE_PAGE: &str = "about:tree";
+
+#[derive(Clone)]
+pub struct Entry {
+    pub url: String,
+    pub title: Option<String>,
+    pub scroll: usize,
+}
+
+#[derive(Clone)]
+struct Node {
+    entry: Entry,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// Child that going forward leads to, the one visited last.
+    forward: Option<usize>,
+}
+
+/// Back/forward history of the visited pages.
+///
+/// Visits form a tree rather than a stack: following a link after going
+/// back starts a new branch and keeps the abandoned one reachable from the
+/// tree page.
+#[derive(Clone, Default)]
+pub struct History {
+    nodes: Vec<Node>,
+    current: Option<usize>,
+}
+
+impl History {
+    /// Records a new visit as a child of the current entry.
+    pub fn push(&mut self, url: String, title: Option<String>) {
+        if self.current().is_some_and(|entry| entry.url == url) {
+            return;
+        }
+
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            entry: Entry {
+                url,
+                title,
+                scroll: 0,
+            },
+            parent: self.current,
+            children: Vec::new(),
+            forward: None,
+        });
+
+        if let Some(parent) = self.current {
+            self.nodes[parent].children.push(index);
+        }
+        self.go(index);
+    }
+
+    pub fn current(&self) -> Option<&Entry> {
+        self.get(self.current?)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Entry> {
+        self.nodes.get(index).map(|node| &node.entry)
+    }
+
+    /// Makes the node current, so that going forward from any of its
+    /// ancestors leads back to it.
+    pub fn go(&mut self, index: usize) {
+        if index >= self.nodes.len() {
+            return;
+        }
+
+        self.current = Some(index);
+
+        let mut child = index;
+        while let Some(parent) = self.nodes[child].parent {
+            self.nodes[parent].forward = Some(child);
+            child = parent;
+        }
+    }
+
+    pub fn set_scroll(&mut self, scroll: usize) {
+        if let Some(current) = self.current {
+            self.nodes[current].entry.scroll = scroll;
+        }
+    }
+
+    /// Node `offset` steps away from the current one (negative is back).
+    pub fn index(&self, offset: isize) -> Option<usize> {
+        let mut index = self.current?;
+
+        for _ in 0..offset.unsigned_abs() {
+            let node = &self.nodes[index];
+            index = if offset < 0 {
+                node.parent
+            } else {
+                node.forward
+            }?;
+        }
+
+        Some(index)
+    }
+
+    /// Gemtext page drawing every branch of the history, with links jumping
+    /// to each entry.
+    pub fn to_gemtext(&self) -> String {
+        let mut page = String::from("# History tree\n\n");
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            if node.parent.is_none() {
+                self.write_node(&mut page, index, "", "");
+            }
+        }
+
+        if self.nodes.is_empty() {
+            page.push_str("Nothing visited in this tab yet.\n");
+        }
+
+        page
+    }
+
+    fn write_node(&self, page: &mut String, index: usize, branch: &str, indent: &str) {
+        let node = &self.nodes[index];
+        // The marker leads the label so that the indentation is not trimmed
+        // away as leading whitespace
+        let marker = if self.current == Some(index) {
+            "●"
+        } else {
+            "○"
+        };
+
+        page.push_str(&format!(
+            "=> {}?{} {} {}{}{}\n",
+            TREE_PAGE,
+            index,
+            marker,
+            indent,
+            branch,
+            node.entry.title.as_deref().unwrap_or(&node.entry.url)
+        ));
+
+        let indent = match branch {
+            "├─ " => format!("{}│  ", indent),
+            "└─ " => format!("{}   ", indent),
+            _ => indent.to_owned(),
+        };
+
+        for (position, &child) in node.children.iter().enumerate() {
+            let branch = if position + 1 == node.children.len() {
+                "└─ "
+            } else {
+                "├─ "
+            };
+            self.write_node(page, child, branch, &indent);
+        }
+    }
+}
+
+/// History node a link of the tree page jumps to.
+pub fn tree_link(url: &str) -> Option<usize> {
+    url.strip_prefix(TREE_PAGE)?.strip_prefix('?')?.parse().ok()
+}
+
+/// URL a visit should be recorded under in history and bookmarks.
+///
+/// Permanent redirects (status 31) replace the requested URL with their
+/// target, while the first temporary redirect (status 30) keeps the URL that
+/// led to it, since the content is expected to move back eventually. Either
+/// way the URL is normalized, as it was requested.
+pub fn canonical_url(requested: &str, response: &Response) -> String {
+    response
+        .redirects
+        .iter()
+        .take_while(|redirect| redirect.permanent)
+        .last()
+        .map(|redirect| redirect.to.to_string())
+        .or_else(|| Url::parse(requested).ok().map(|url| normalize(&url).into()))
+        .unwrap_or_else(|| requested.to_owned())
+}
+
+/// Percent-encodes a value for the query of an `about:` page action link.
+pub fn encode(value: &str) -> String {
+    percent_encoding::utf8_percent_encode(value, percent_encoding::NON_ALPHANUMERIC).to_string()
+}
+
+/// Search request typed in the address bar, either `?terms` for the default
+/// engine or `keyword terms` for the engine configured for the keyword, with
+/// the terms as the query of its URL.
+pub fn search_url(input: &str, engine: &str, keywords: &HashMap<String, String>) -> Option<String> {
+    let input = input.trim();
+    let (engine, terms) = match input.strip_prefix('?') {
+        Some(terms) => (engine, terms),
+        None => {
+            let (keyword, terms) = input.split_once(' ').unwrap_or((input, ""));
+            (keywords.get(keyword)?.as_str(), terms)
+        }
+    };
+
+    let terms = terms.trim();
+    if terms.is_empty() || engine.is_empty() {
+        return (!engine.is_empty()).then(|| engine.to_owned());
+    }
+
+    Some(format!("{}?{}", engine, encode(terms)))
+}
+
+/// `file://` URL of a local file or directory given by its path, for
+/// anything that isn't a URL already.
+pub fn file_url(path: &str) -> Option<String> {
+    // Single letter schemes are Windows drive letters rather than URLs
+    if Url::parse(path).is_ok_and(|url| url.scheme().len() > 1) {
+        return None;
+    }
+
+    // Directories end with a slash, for links to resolve inside them
+    let path = Path::new(path).canonicalize().ok()?;
+    if path.is_dir() {
+        Url::from_directory_path(path).ok().map(String::from)
+    } else {
+        Url::from_file_path(path).ok().map(String::from)
+    }
+}
+
+/// Gemtext page listing the entries of a local directory.
+pub fn directory_listing(dir: &Path) -> io::Result<String> {
+    let mut entries = fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let name = path.file_name()?.to_string_lossy().into_owned();
+            let url = if path.is_dir() {
+                Url::from_directory_path(&path)
+            } else {
+                Url::from_file_path(&path)
+            };
+
+            Some((path.is_dir(), name, url.ok()?))
+        })
+        .collect::<Vec<_>>();
+    // Directories first, then files, each by name
+    entries.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+
+    let mut page = format!("# {}\n\n", dir.display());
+    if dir.parent().is_some() {
+        page.push_str("=> ../ ..\n");
+    }
+    for (is_dir, name, url) in entries {
+        page.push_str(&format!(
+            "=> {} {}{}\n",
+            url,
+            name,
+            if is_dir { "/" } else { "" }
+        ));
+    }
+
+    Ok(page)
+}
@@ -0,0 +1,43 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::Local;
+use color_eyre::Result;
+
+use crate::dirs;
+
+const NOTES_FILE: &str = "notes.gmi";
+
+pub fn default_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(NOTES_FILE))
+}
+
+/// Appends a snippet to the gemtext notes file as a quote, followed by a link
+/// back to the page it was taken from.
+pub fn clip(path: &Path, snippet: &str, url: &str, title: Option<&str>) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let is_new = !path.exists();
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+    if is_new {
+        writeln!(file, "# Notes")?;
+    }
+
+    writeln!(file)?;
+    writeln!(file, "## {}", Local::now().format("%Y-%m-%d %H:%M"))?;
+    for line in snippet.lines() {
+        writeln!(file, "> {}", line)?;
+    }
+    match title {
+        Some(title) => writeln!(file, "=> {} {}", url, title)?,
+        None => writeln!(file, "=> {}", url)?,
+    }
+
+    Ok(())
+}
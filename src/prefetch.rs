@@ -0,0 +1,145 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use gemini_client::{GeminiClient, Response, Result, RobotsPolicy};
+use url::Url;
+
+/// Virtual user agent whose robots.txt rules prefetching follows, along
+/// with those for every agent.
+const USER_AGENT: &str = "dioscuri";
+/// Largest body prefetched, links to anything bigger are left to the
+/// reader.
+const MAX_BODY: usize = 512 * 1024;
+
+/// Pages the visible links of the current page point to, fetched in the
+/// background so that following them is served from the cache.
+///
+/// Only links to the host of the page are prefetched, a few at a time and
+/// once per session, within what its robots.txt allows and never while it
+/// asks to slow down.
+pub struct Prefetcher {
+    client: GeminiClient,
+    robots: RobotsPolicy,
+    /// Requests running at once, 0 disabling prefetching.
+    concurrency: usize,
+    /// Page the queued links were found on.
+    page: String,
+    queue: VecDeque<String>,
+    /// Links queued in the session, fetched or not.
+    requested: HashSet<String>,
+    running: usize,
+    sender: Sender<Result<Response>>,
+    receiver: Receiver<Result<Response>>,
+}
+
+impl Prefetcher {
+    pub fn new(client: GeminiClient, concurrency: usize) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        Self {
+            robots: RobotsPolicy::new(client.clone()).user_agent(USER_AGENT),
+            client,
+            concurrency,
+            page: String::new(),
+            queue: VecDeque::new(),
+            requested: HashSet::new(),
+            running: 0,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Queues the links of the page to the same host, unless `skip` says
+    /// otherwise, like for those already cached. The links still queued
+    /// for another page are dropped.
+    pub fn queue<'a>(
+        &mut self,
+        page: &str,
+        links: impl IntoIterator<Item = &'a str>,
+        skip: impl Fn(&str) -> bool,
+    ) {
+        if self.concurrency == 0 {
+            return;
+        }
+        let Ok(base) = Url::parse(page) else {
+            return;
+        };
+        if base.scheme() != "gemini" {
+            return;
+        }
+
+        if self.page != page {
+            for link in self.queue.drain(..) {
+                self.requested.remove(&link);
+            }
+            self.page = page.to_owned();
+        }
+
+        for link in links {
+            if let Ok(url) = base.join(link)
+                && url.scheme() == "gemini"
+                && url.host_str() == base.host_str()
+                && url.port() == base.port()
+                && url.as_str() != page
+                && !skip(url.as_str())
+                && self.requested.insert(url.to_string())
+            {
+                self.queue.push_back(url.to_string());
+            }
+        }
+
+        self.start_queued();
+    }
+
+    /// Collects the pages fetched since the last poll, starting the queued
+    /// links in their place.
+    pub fn poll(&mut self) -> Vec<Response> {
+        let mut fetched = Vec::new();
+        while let Ok(response) = self.receiver.try_recv() {
+            self.running -= 1;
+            // Bodies as big as the limit may have been cut short
+            if let Ok(response) = response
+                && response.mime().starts_with("text/")
+                && response.body.len() < MAX_BODY
+            {
+                fetched.push(response);
+            }
+        }
+        self.start_queued();
+
+        fetched
+    }
+
+    fn start_queued(&mut self) {
+        while self.running < self.concurrency {
+            let Some(link) = self.queue.pop_front() else {
+                return;
+            };
+
+            // The link is left for the next time it is visible
+            let cooling = Url::parse(&link)
+                .ok()
+                .and_then(|url| self.client.cooldown(url.host_str()?));
+            if cooling.is_some() {
+                self.requested.remove(&link);
+                continue;
+            }
+
+            let (client, robots, sender) = (
+                self.client.clone(),
+                self.robots.clone(),
+                self.sender.clone(),
+            );
+            self.running += 1;
+            thread::spawn(move || {
+                let response = robots
+                    .check(&link)
+                    .and_then(|()| client.peek(&link, None, MAX_BODY));
+                let _ = sender.send(response);
+            });
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use crossterm::style::{Color, Stylize};
+use gemini_client::gemtext::{Document, Line};
+use url::Url;
+
+/// Colors of the headings, by level.
+const HEADINGS: [Color; 3] = [Color::Magenta, Color::Cyan, Color::Blue];
+
+/// Text of the page with the control characters it could drive the terminal
+/// with, like escape sequences, shown in caret notation. Tabs are kept.
+fn printable(text: &str) -> String {
+    let mut printable = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '\t' => printable.push(char),
+            '\0'..='\x1f' => {
+                printable.push('^');
+                printable.push(char::from(char as u8 + 0x40));
+            }
+            '\x7f' => printable.push_str("^?"),
+            // C1 controls have no caret notation
+            char if char.is_control() => {}
+            char => printable.push(char),
+        }
+    }
+
+    printable
+}
+
+/// Renders the document with ANSI colors and formatting for the terminal,
+/// or for `less -R`. Links are numbered, their targets listed as footnotes
+/// resolved against the URL the document was served from.
+pub fn pretty(document: &Document, base: Option<&Url>) -> String {
+    let mut text = String::new();
+    let mut links = Vec::new();
+
+    for line in &document.lines {
+        let line = match line {
+            Line::Text(text) => printable(text),
+            Line::Link { url, label } => {
+                links.push(printable(
+                    &base
+                        .and_then(|base| base.join(url).ok())
+                        .map_or_else(|| url.clone(), |url| url.to_string()),
+                ));
+                format!(
+                    "{}{}",
+                    printable(label.as_deref().unwrap_or(url)).underlined(),
+                    format!("[{}]", links.len()).dark_grey()
+                )
+            }
+            Line::Heading { level, text } => {
+                let index = (*level as usize).clamp(1, 3) - 1;
+                printable(text).with(HEADINGS[index]).bold().to_string()
+            }
+            Line::ListItem(item) => format!("• {}", printable(item)),
+            Line::Quote(quote) => format!("│ {}", printable(quote)).dim().to_string(),
+            // The alt text of a block is its caption
+            Line::PreformatToggle { alt: Some(alt) } => printable(alt).dim().italic().to_string(),
+            Line::PreformatToggle { alt: None } => continue,
+            Line::Preformatted(text) => printable(text),
+        };
+        text.push_str(&line);
+        text.push('\n');
+    }
+
+    if !links.is_empty() {
+        text.push('\n');
+        for (number, link) in links.iter().enumerate() {
+            text.push_str(&format!(
+                "{} {}\n",
+                format!("[{}]", number + 1).dark_grey(),
+                link
+            ));
+        }
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_characters_are_escaped() {
+        let document = Document::parse(
+            "Clear\x1b[2J\x1b]52;c;cGxvdA==\x07 this\u{9b}\n```\n\tcode\x7f\n```\n",
+        );
+
+        assert_eq!(
+            pretty(&document, None),
+            "Clear^[[2J^[]52;c;cGxvdA==^G this\n\tcode^?\n"
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    io::{self, Write},
+    process::{Child, Command, Stdio},
+    thread,
+};
+
+use gemini_client::gemtext::{Document, Line};
+use url::Url;
+
+/// Preformatted blocks with fewer letters and digits than other visible
+/// characters are taken for drawings rather than code or tables.
+fn is_drawing(lines: &[String]) -> bool {
+    let (mut visible, mut alphanumeric) = (0, 0);
+    for char in lines.iter().flat_map(|line| line.chars()) {
+        if !char.is_whitespace() {
+            visible += 1;
+            alphanumeric += usize::from(char.is_alphanumeric());
+        }
+    }
+
+    alphanumeric * 2 < visible
+}
+
+/// Document read in a single column, for screen readers and speech: links
+/// are numbered in their labels, and drawings made of preformatted text are
+/// replaced by a line announcing their alt text.
+pub fn linearize(document: &Document) -> Document {
+    let mut lines = Vec::new();
+    let mut links = 0;
+    let mut rest = document.lines.iter().peekable();
+
+    while let Some(line) = rest.next() {
+        // Documents made of preformatted text have no toggle lines
+        let (alt, fenced) = match line {
+            Line::Link { url, label } => {
+                links += 1;
+                lines.push(Line::Link {
+                    url: url.clone(),
+                    label: Some(format!("[{}] {}", links, label.as_deref().unwrap_or(url))),
+                });
+                continue;
+            }
+            Line::PreformatToggle { alt } => (alt.clone(), true),
+            Line::Preformatted(_) => (None, false),
+            line => {
+                lines.push(line.clone());
+                continue;
+            }
+        };
+
+        let mut block = Vec::new();
+        if let Line::Preformatted(text) = line {
+            block.push(text.clone());
+        }
+        while let Some(Line::Preformatted(text)) = rest.peek() {
+            block.push(text.clone());
+            rest.next();
+        }
+        if fenced {
+            rest.next_if(|line| matches!(line, Line::PreformatToggle { .. }));
+        }
+
+        if is_drawing(&block) {
+            lines.push(Line::Text(match alt {
+                Some(alt) => format!("[Drawing: {}]", alt),
+                None => String::from("[Drawing]"),
+            }));
+            continue;
+        }
+
+        if fenced {
+            lines.push(Line::PreformatToggle { alt });
+        }
+        lines.extend(block.into_iter().map(Line::Preformatted));
+        if fenced {
+            lines.push(Line::PreformatToggle { alt: None });
+        }
+    }
+
+    Document { lines }
+}
+
+/// Plain text of a linearized document, without markup to be read out.
+pub fn text(document: &Document) -> String {
+    let mut text = String::new();
+
+    for line in &document.lines {
+        let line = match line {
+            Line::Link { url, label } => label.as_deref().unwrap_or(url),
+            Line::ListItem(item) => {
+                text.push_str("• ");
+                item
+            }
+            Line::Text(line) | Line::Heading { text: line, .. } | Line::Quote(line) => line,
+            Line::Preformatted(line) => line,
+            Line::PreformatToggle { alt: Some(alt) } => alt,
+            Line::PreformatToggle { alt: None } => continue,
+        };
+        text.push_str(line);
+        text.push('\n');
+    }
+
+    text
+}
+
+/// Targets of the numbered links, resolved against the URL the document was
+/// served from.
+pub fn footnotes(document: &Document, base: Option<&Url>) -> String {
+    let mut footnotes = String::new();
+
+    for (number, (url, _)) in document.links().enumerate() {
+        let url = base
+            .and_then(|base| base.join(url).ok())
+            .map_or_else(|| url.to_owned(), |url| url.to_string());
+        footnotes.push_str(&format!("[{}] {}\n", number + 1, url));
+    }
+
+    if footnotes.is_empty() {
+        footnotes
+    } else {
+        format!("\nLinks:\n{}", footnotes)
+    }
+}
+
+/// Starts the speech command, like `espeak-ng`, reading the text from its
+/// standard input while the UI keeps running.
+pub fn speak(command: &str, text: String) -> io::Result<Child> {
+    let mut words = command.split_whitespace();
+    let mut child = Command::new(words.next().unwrap_or_default())
+        .args(words)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    // Slow readers would block the UI once the pipe is full
+    if let Some(mut stdin) = child.stdin.take() {
+        thread::spawn(move || stdin.write_all(text.as_bytes()));
+    }
+
+    Ok(child)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drawings_are_announced_and_code_kept() {
+        let document = Document::parse(
+            "# Welcome\n```A cat\n /\\_/\\\n( o.o )\n > ^ <\n```\n=> /about About\n\
+             ```rust\nlet answer = 42;\n```\n=> gemini://example.org/\n",
+        );
+        let linear = linearize(&document);
+
+        assert_eq!(
+            text(&linear),
+            "Welcome\n[Drawing: A cat]\n[1] About\nrust\nlet answer = 42;\n\
+             [2] gemini://example.org/\n"
+        );
+        assert_eq!(
+            footnotes(
+                &linear,
+                Some(&Url::parse("gemini://example.org/cat/").unwrap())
+            ),
+            "\nLinks:\n[1] gemini://example.org/about\n[2] gemini://example.org/\n"
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use gemini_client::gemtext::{Document, Line};
+use ratatui::{
+    style::{Modifier, Style},
+    text::{self, Span},
+};
+use unicode_width::UnicodeWidthChar;
+
+use crate::theme::Theme;
+
+/// Reading speed the reading time is estimated at.
+const WORDS_PER_MINUTE: usize = 200;
+
+/// A document line as displayed in the body, before wrapping.
+#[derive(Clone)]
+pub struct StyledLine {
+    pub text: String,
+    pub style: Style,
+    /// Target of link lines, as written in the document.
+    pub link: Option<String>,
+    /// Whether the line is in a preformatted block, which is scrolled
+    /// horizontally instead of wrapped so that its columns line up.
+    pub preformatted: bool,
+}
+
+/// Turns every line of the document into its displayed text and style.
+pub fn render(document: &Document, theme: &Theme) -> Vec<StyledLine> {
+    document
+        .lines
+        .iter()
+        .map(|line| {
+            let (text, style) = match line {
+                Line::Text(text) => (text.clone(), theme.body),
+                Line::Link { url, label } => {
+                    (format!("→ {}", label.as_deref().unwrap_or(url)), theme.link)
+                }
+                Line::Heading { level, text } => {
+                    let index = (*level as usize).clamp(1, 3) - 1;
+                    (text.clone(), theme.headings[index])
+                }
+                Line::ListItem(item) => (format!("• {}", item), theme.list_item),
+                Line::Quote(quote) => (format!("│ {}", quote), theme.quote),
+                // The alt text of a block is its caption
+                Line::PreformatToggle { alt: Some(alt) } => (
+                    alt.clone(),
+                    theme.preformatted.add_modifier(Modifier::ITALIC),
+                ),
+                Line::PreformatToggle { alt: None } => (String::new(), theme.preformatted),
+                Line::Preformatted(text) => (text.clone(), theme.preformatted),
+            };
+
+            StyledLine {
+                text: text.replace('\t', " "),
+                style,
+                link: match line {
+                    Line::Link { url, .. } => Some(url.clone()),
+                    _ => None,
+                },
+                preformatted: matches!(line, Line::Preformatted(_)),
+            }
+        })
+        .collect()
+}
+
+/// Columns `skip..skip + width` of a displayed line, for those scrolled
+/// horizontally. Wide characters straddling either edge are left out.
+pub fn crop(line: text::Line<'_>, skip: usize, width: usize) -> text::Line<'static> {
+    let mut column = 0;
+    let spans = line
+        .spans
+        .iter()
+        .map(|span| {
+            let mut content = String::new();
+            for char in span.content.chars() {
+                let char_width = char.width().unwrap_or_default();
+                if column >= skip && column + char_width <= skip + width {
+                    content.push(char);
+                }
+                column += char_width;
+            }
+            Span::styled(content, span.style)
+        })
+        .collect::<Vec<_>>();
+
+    text::Line::from(spans).style(line.style)
+}
+
+/// Words of the document a reader goes through: its text, headings, list
+/// items and quotes, but neither its links nor its preformatted blocks.
+pub fn word_count(document: &Document) -> usize {
+    document
+        .lines
+        .iter()
+        .map(|line| match line {
+            Line::Text(text)
+            | Line::Heading { text, .. }
+            | Line::ListItem(text)
+            | Line::Quote(text) => text.split_whitespace().count(),
+            Line::Link { .. } | Line::PreformatToggle { .. } | Line::Preformatted(_) => 0,
+        })
+        .sum()
+}
+
+/// Minutes it takes to read the words, rounded up.
+pub fn reading_time(words: usize) -> usize {
+    words.div_ceil(WORDS_PER_MINUTE)
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{
+        style::Stylize,
+        widgets::{Paragraph, Wrap},
+    };
+
+    use super::*;
+    use crate::theme::ThemeConfig;
+
+    /// Displayed lines and wrapped rows at a width of 40 columns of a page
+    /// from the parser fixtures.
+    fn line_counts(name: &str) -> (usize, usize) {
+        let path = format!(
+            "{}/crates/gemini_client/tests/fixtures/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        );
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines = render(
+            &Document::parse(&text),
+            &Theme::from(&ThemeConfig::default()),
+        );
+
+        let rows = lines
+            .iter()
+            .map(|line| {
+                Paragraph::new(line.text.as_str())
+                    .wrap(Wrap { trim: false })
+                    .line_count(40)
+                    .max(1)
+            })
+            .sum();
+
+        (lines.len(), rows)
+    }
+
+    #[test]
+    fn fixture_line_counts() {
+        assert_eq!(line_counts("links.gmi"), (258, 419));
+        assert_eq!(line_counts("quotes.gmi"), (16, 21));
+        assert_eq!(line_counts("broken.gmi"), (18, 18));
+    }
+
+    #[test]
+    fn cropped_lines_keep_their_styles() {
+        let line = text::Line::from(vec![Span::raw("ab"), Span::raw("漢字").bold()]);
+        let cropped = crop(line, 1, 3);
+        assert_eq!(cropped.spans[0].content, "b");
+        assert_eq!(cropped.spans[1].content, "漢");
+        assert!(cropped.spans[1].style.add_modifier.contains(Modifier::BOLD));
+    }
+
+    #[test]
+    fn preformatted_blocks_are_not_read() {
+        let document = Document::parse(
+            "# A short page\n\nSome words here.\n```\nlet code = 1;\n```\n=> gemini://example.org Link\n",
+        );
+        assert_eq!(word_count(&document), 6);
+        assert_eq!(reading_time(6), 1);
+        assert_eq!(reading_time(401), 3);
+    }
+
+    #[test]
+    fn wide_characters_wrap_by_width() {
+        // The Japanese paragraph takes 88 columns, so three rows
+        assert_eq!(line_counts("scripts.gmi"), (28, 31));
+    }
+}
@@ -0,0 +1,184 @@
+use chrono::{DateTime, Local};
+use gemini_client::Certificate;
+
+pub const ROTATION_PAGE: &str = "about:rotation";
+
+/// Certificates of a capsule compared on [`ROTATION_PAGE`]: the one pinned
+/// for it, the one it presents, and the one it is about to be served with.
+pub struct Rotation<'a> {
+    pub host: &'a str,
+    pub pinned: Option<Certificate>,
+    pub presented: Result<Certificate, String>,
+    /// PEM file given, and the certificate read from it.
+    pub planned: Option<(&'a str, Result<Certificate, String>)>,
+}
+
+impl Rotation<'_> {
+    pub fn to_gemtext(&self, now: i64) -> String {
+        let mut page = format!("# Certificate rotation of {}\n\n", self.host);
+
+        page.push_str("## Pinned\n\n");
+        match &self.pinned {
+            Some(pinned) => page.push_str(&details(pinned)),
+            None => page.push_str("No certificate is pinned for this host yet.\n"),
+        }
+        page.push_str("\n## Presented\n\n");
+        match &self.presented {
+            Ok(presented) => page.push_str(&details(presented)),
+            Err(error) => page.push_str(&format!("> {}\n", error)),
+        }
+        if let Some((path, planned)) = &self.planned {
+            page.push_str(&format!("\n## In {}\n\n", path));
+            match planned {
+                Ok(planned) => page.push_str(&details(planned)),
+                Err(error) => page.push_str(&format!("> {}\n", error)),
+            }
+        }
+
+        page.push_str("\n## Checks\n\n");
+        for check in self.checks(now) {
+            page.push_str(&format!("* {}\n", check));
+        }
+
+        page
+    }
+
+    /// What readers pinning the certificates will see, in the order they
+    /// would see it.
+    fn checks(&self, now: i64) -> Vec<String> {
+        let mut checks = Vec::new();
+        let Ok(presented) = &self.presented else {
+            checks.push(String::from(
+                "The presented certificate is unknown, the host could not be reached.",
+            ));
+            return checks;
+        };
+
+        match &self.pinned {
+            Some(pinned) if pinned.fingerprint == presented.fingerprint => {
+                checks.push(String::from("The pinned certificate is the one presented."));
+            }
+            Some(pinned) if pinned.not_after > now => checks.push(format!(
+                "The presented certificate is not the pinned one: clients pinning it warn \
+                 about a changed certificate until it expires on {}.",
+                date(pinned.not_after)
+            )),
+            Some(_) => checks.push(String::from(
+                "The pinned certificate expired, the presented one replaces it silently.",
+            )),
+            None => {}
+        }
+        if presented.not_after <= now {
+            checks.push(String::from("The presented certificate expired."));
+        }
+
+        let Some((_, Ok(planned))) = &self.planned else {
+            return checks;
+        };
+        if planned.fingerprint == presented.fingerprint {
+            checks.push(String::from(
+                "The certificate of the file is the one presented, the rotation is live.",
+            ));
+            return checks;
+        }
+
+        if presented.not_after > now {
+            checks.push(format!(
+                "Serving the certificate of the file before {} warns the clients pinning the \
+                 presented one, from then on they pin it silently.",
+                date(presented.not_after)
+            ));
+        } else {
+            checks.push(String::from(
+                "The certificate of the file can be served right away, clients pin it silently.",
+            ));
+        }
+        if planned.not_before > now.max(presented.not_after) {
+            checks.push(format!(
+                "The certificate of the file is only valid from {}.",
+                date(planned.not_before)
+            ));
+        }
+        if planned.not_after <= now {
+            checks.push(String::from("The certificate of the file expired."));
+        } else if planned.not_after <= presented.not_after {
+            checks.push(String::from(
+                "The certificate of the file expires before the presented one.",
+            ));
+        }
+        if !planned.subject.is_empty() && planned.subject != presented.subject {
+            checks.push(format!(
+                "The certificate of the file is for {} instead of {}.",
+                planned.subject, presented.subject
+            ));
+        }
+
+        checks
+    }
+}
+
+/// Guide to the page, shown when no host is given.
+pub fn usage() -> String {
+    format!(
+        "# Certificate rotation\n\n\
+         Compares the certificate a capsule presents with the one pinned for it, and with the \
+         one in a PEM file, to check a planned rotation before readers are warned about a \
+         changed certificate. Clients trusting on first use, like this one, only accept a new \
+         certificate silently once the pinned one expired.\n\n\
+         Open {0}?<host> <PEM file>, like:\n\n\
+         ```\n{0}?example.org ~/capsule/cert.pem\n```\n",
+        ROTATION_PAGE
+    )
+}
+
+fn details(certificate: &Certificate) -> String {
+    format!(
+        "* Subject: {}\n* Issuer: {}\n* Valid from {} until {}\n* SHA-256: {}\n",
+        certificate.subject,
+        certificate.issuer,
+        date(certificate.not_before),
+        date(certificate.not_after),
+        certificate.fingerprint
+    )
+}
+
+fn date(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|time| time.with_timezone(&Local).format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn certificate(fingerprint: &str, not_after: i64) -> Certificate {
+        Certificate {
+            fingerprint: fingerprint.to_owned(),
+            subject: String::from("CN=example.org"),
+            issuer: String::from("CN=example.org"),
+            not_before: 0,
+            not_after,
+        }
+    }
+
+    #[test]
+    fn early_rotation_is_flagged() {
+        let rotation = Rotation {
+            host: "example.org",
+            pinned: Some(certificate("A", 2_000)),
+            presented: Ok(certificate("A", 2_000)),
+            planned: Some(("cert.pem", Ok(certificate("B", 5_000)))),
+        };
+
+        let checks = rotation.checks(1_000);
+        assert_eq!(checks.len(), 2);
+        assert!(checks[0].starts_with("The pinned certificate is the one presented"));
+        assert!(checks[1].starts_with("Serving the certificate of the file before"));
+
+        let checks = rotation.checks(3_000);
+        assert!(checks.contains(&String::from(
+            "The certificate of the file can be served right away, clients pin it silently."
+        )));
+    }
+}
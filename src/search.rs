@@ -0,0 +1,109 @@
+use ratatui::{
+    style::{Style, Stylize},
+    text::{Line, Span},
+};
+
+use crate::render::StyledLine;
+
+pub struct Match {
+    pub line: usize,
+    start: usize,
+    end: usize,
+}
+
+/// Find-in-page state: the query being searched and every match found in
+/// the body, with the one currently focused.
+#[derive(Default)]
+pub struct Search {
+    pub query: String,
+    matches: Vec<Match>,
+    current: usize,
+}
+
+impl Search {
+    /// Finds every ASCII case-insensitive occurrence of the query in the
+    /// displayed lines.
+    pub fn find(&mut self, lines: &[StyledLine]) {
+        self.matches.clear();
+        self.current = 0;
+
+        if self.query.is_empty() {
+            return;
+        }
+
+        let query = self.query.to_ascii_lowercase();
+
+        for (index, line) in lines.iter().enumerate() {
+            let line = line.text.to_ascii_lowercase();
+            self.matches
+                .extend(line.match_indices(&query).map(|(start, found)| Match {
+                    line: index,
+                    start,
+                    end: start + found.len(),
+                }));
+        }
+    }
+
+    /// Focuses the first match at or after the given line.
+    pub fn focus_from(&mut self, line: usize) {
+        self.current = self
+            .matches
+            .iter()
+            .position(|found| found.line >= line)
+            .unwrap_or_default();
+    }
+
+    pub fn next(&mut self) {
+        if !self.matches.is_empty() {
+            self.current = (self.current + 1) % self.matches.len();
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.matches.is_empty() {
+            self.current = self
+                .current
+                .checked_sub(1)
+                .unwrap_or(self.matches.len() - 1);
+        }
+    }
+
+    pub fn current(&self) -> Option<&Match> {
+        self.matches.get(self.current)
+    }
+
+    pub fn counter(&self) -> String {
+        if self.matches.is_empty() {
+            String::from("No matches")
+        } else {
+            format!("{}/{}", self.current + 1, self.matches.len())
+        }
+    }
+
+    /// Splits a displayed line into spans with its matches highlighted.
+    pub fn highlight(&self, index: usize, line: &StyledLine) -> Line<'static> {
+        let text = &line.text;
+        let mut spans = Vec::new();
+        let mut last = 0;
+
+        for (position, found) in self.matches.iter().enumerate() {
+            if found.line != index {
+                continue;
+            }
+
+            let style = if position == self.current {
+                Style::new().black().on_yellow()
+            } else {
+                Style::new().reversed()
+            };
+
+            spans.push(Span::raw(text[last..found.start].to_owned()));
+            spans.push(Span::styled(text[found.start..found.end].to_owned(), style));
+            last = found.end;
+        }
+
+        spans.push(Span::raw(text[last..].to_owned()));
+
+        Line::from(spans).style(line.style)
+    }
+}
@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+
+use color_eyre::{Result, eyre::bail};
+use gemini_client::{DEFAULT_PORT, GeminiClient, HostSettings, Identity, TlsPolicy};
+use serde::Deserialize;
+use url::Url;
+
+use crate::{config::from_str_option, dirs, identities::Identities, navigation::encode};
+
+const SITES_FILE: &str = "sites";
+const SITE_PAGE: &str = "about:site";
+const TLS_POLICIES: [TlsPolicy; 4] = [
+    TlsPolicy::Strict,
+    TlsPolicy::AllowUnknownIssuer,
+    TlsPolicy::Tofu,
+    TlsPolicy::AcceptAll,
+];
+
+/// Settings of a host overriding the general ones, those left unset
+/// keeping them.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Site {
+    /// Name of the identity presented to the host, over those scoped to its
+    /// pages.
+    pub identity: Option<String>,
+    #[serde(deserialize_with = "from_str_option")]
+    pub tls_policy: Option<TlsPolicy>,
+    /// Port the URLs of the host without one are requested on.
+    pub port: Option<u16>,
+    /// Whether connections to the host go through the proxy, when one is
+    /// set.
+    pub proxy: Option<bool>,
+    pub follow_redirects: Option<bool>,
+}
+
+impl Site {
+    fn host_settings(&self) -> HostSettings {
+        HostSettings {
+            tls_policy: self.tls_policy,
+            port: self.port,
+            proxy: self.proxy.unwrap_or(true),
+            follow_redirects: self.follow_redirects.unwrap_or(true),
+        }
+    }
+
+    /// Changes the setting named like in the configuration, `default`
+    /// unsetting it.
+    fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let value = Some(value.trim()).filter(|value| *value != "default");
+        let flag = |value: &str| match value {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            _ => bail!("{} is either true or false", key),
+        };
+
+        match key {
+            "identity" => self.identity = value.map(str::to_owned),
+            "tls_policy" => {
+                self.tls_policy = value
+                    .map(|value| value.parse::<TlsPolicy>())
+                    .transpose()
+                    .map_err(color_eyre::eyre::Error::msg)?;
+            }
+            "port" => self.port = value.map(str::parse).transpose()?,
+            "proxy" => self.proxy = value.map(flag).transpose()?,
+            "follow_redirects" => self.follow_redirects = value.map(flag).transpose()?,
+            _ => bail!("Unknown setting {}", key),
+        }
+
+        Ok(())
+    }
+}
+
+/// Settings of hosts from the `[sites]` table of the configuration, with
+/// those changed on about:site over them. The changes are persisted in the
+/// data directory as `{host}\t{setting}\t{value}` lines.
+pub struct Sites {
+    configured: HashMap<String, Site>,
+    changes: Vec<(String, String, String)>,
+    sites: HashMap<String, Site>,
+}
+
+impl Sites {
+    pub fn load(configured: &HashMap<String, Site>) -> Self {
+        let changes = dirs::read_data(SITES_FILE)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                Some((
+                    fields.next()?.to_owned(),
+                    fields.next()?.to_owned(),
+                    fields.next()?.to_owned(),
+                ))
+            })
+            .collect();
+
+        Self::new(configured.clone(), changes)
+    }
+
+    fn new(configured: HashMap<String, Site>, changes: Vec<(String, String, String)>) -> Self {
+        let mut sites = configured.clone();
+        for (host, key, value) in &changes {
+            // Settings no longer understood are left out
+            let _ = sites.entry(host.clone()).or_default().set(key, value);
+        }
+
+        Self {
+            configured,
+            changes,
+            sites,
+        }
+    }
+
+    /// Hands the settings of every host to the client, and to its clones.
+    pub fn apply(&self, client: &GeminiClient) {
+        for (host, site) in &self.sites {
+            client.set_host_settings(host, site.host_settings());
+        }
+    }
+
+    /// Identity presented for the URL: the one set for its host, or the
+    /// one scoped to it.
+    pub fn identity<'a>(&self, url: &str, identities: &'a Identities) -> Option<&'a Identity> {
+        Url::parse(url)
+            .ok()
+            .and_then(|url| self.sites.get(url.host_str()?)?.identity.as_deref())
+            .and_then(|name| identities.named(name))
+            .or_else(|| identities.for_url(url))
+    }
+
+    /// Changes a setting of the host, for the client too, keeping it for the
+    /// next sessions.
+    pub fn set(
+        &mut self,
+        host: &str,
+        key: &str,
+        value: &str,
+        client: &GeminiClient,
+        identities: &Identities,
+    ) -> Result<()> {
+        // Each change is saved on a line of its own, with tabs between fields
+        if [host, key, value]
+            .iter()
+            .any(|field| field.contains(char::is_control))
+        {
+            bail!("Settings cannot contain tabs, line breaks or control characters");
+        }
+        if key == "identity"
+            && value.trim() != "default"
+            && identities.named(value.trim()).is_none()
+        {
+            bail!("There is no identity named {}", value.trim());
+        }
+
+        let site = self.sites.entry(host.to_owned()).or_default();
+        site.set(key, value)?;
+        client.set_host_settings(host, site.host_settings());
+
+        self.changes
+            .retain(|(changed, setting, _)| changed != host || setting != key);
+        self.changes
+            .push((host.to_owned(), key.to_owned(), value.trim().to_owned()));
+
+        let text = self
+            .changes
+            .iter()
+            .map(|(host, key, value)| format!("{}\t{}\t{}\n", host, key, value))
+            .collect::<String>();
+        dirs::write_data(SITES_FILE, &text)
+    }
+
+    /// Gemtext page listing the hosts with settings of their own, along
+    /// with the one the current page is from.
+    pub fn to_gemtext(&self, current: Option<&str>) -> String {
+        let mut page = String::from("# Sites\n\n");
+        page.push_str(
+            "Settings of a host override the general ones for its pages. They are set in \
+             the [sites] table of the configuration, and changed on the page of the host.\n\n",
+        );
+
+        let mut hosts = self.sites.keys().map(String::as_str).collect::<Vec<_>>();
+        hosts.sort();
+        if let Some(current) = current
+            && !hosts.contains(&current)
+        {
+            hosts.insert(0, current);
+        }
+        if hosts.is_empty() {
+            page.push_str("No host has settings of its own yet.\n");
+        }
+
+        for host in hosts {
+            page.push_str(&format!(
+                "=> {}?host={} {}\n",
+                SITE_PAGE,
+                encode(host),
+                host
+            ));
+        }
+
+        page
+    }
+
+    /// Gemtext page of the settings of the host, with links to change them.
+    /// Values without a link, like ports, are typed in the address.
+    pub fn host_gemtext(&self, host: &str, identities: &Identities, policy: TlsPolicy) -> String {
+        let site = self.sites.get(host).cloned().unwrap_or_default();
+        let configured = self.configured.get(host);
+        let link = |key: &str, value: &str, label: &str, current: bool| {
+            format!(
+                "=> {}?host={}&{}={} {}{}\n",
+                SITE_PAGE,
+                encode(host),
+                key,
+                encode(value),
+                label,
+                if current { " (current)" } else { "" }
+            )
+        };
+
+        let mut page = format!("# {}\n\n", host);
+        page.push_str(&format!(
+            "=> gemini://{}/ Open {}\n\nOther values can be typed in the address, like \
+             {}?host={}&port=1966.\n",
+            host,
+            host,
+            SITE_PAGE,
+            encode(host)
+        ));
+        if configured.is_some() {
+            page.push_str("Changes made here apply over the [sites] table of the configuration.\n");
+        }
+
+        page.push_str("\n## Identity\n\n");
+        page.push_str(&link(
+            "identity",
+            "default",
+            "The ones scoped to its pages",
+            site.identity.is_none(),
+        ));
+        for name in identities.names() {
+            page.push_str(&link(
+                "identity",
+                name,
+                name,
+                site.identity.as_deref() == Some(name),
+            ));
+        }
+
+        page.push_str("\n## TLS policy\n\n");
+        page.push_str(&link(
+            "tls_policy",
+            "default",
+            &format!("General one, {}", policy),
+            site.tls_policy.is_none(),
+        ));
+        for candidate in TLS_POLICIES {
+            page.push_str(&link(
+                "tls_policy",
+                &candidate.to_string(),
+                &candidate.to_string(),
+                site.tls_policy == Some(candidate),
+            ));
+        }
+
+        page.push_str("\n## Port\n\n");
+        page.push_str(&link(
+            "port",
+            "default",
+            &DEFAULT_PORT.to_string(),
+            site.port.is_none(),
+        ));
+        if let Some(port) = site.port {
+            page.push_str(&link("port", &port.to_string(), &port.to_string(), true));
+        }
+
+        page.push_str("\n## Proxy\n\n");
+        let proxy = site.proxy.unwrap_or(true);
+        page.push_str(&link("proxy", "true", "Through the proxy, if set", proxy));
+        page.push_str(&link("proxy", "false", "Directly", !proxy));
+
+        page.push_str("\n## Redirects\n\n");
+        let follow = site.follow_redirects.unwrap_or(true);
+        page.push_str(&link("follow_redirects", "true", "Followed", follow));
+        page.push_str(&link(
+            "follow_redirects",
+            "false",
+            "Shown as links",
+            !follow,
+        ));
+
+        page
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_apply_over_the_configuration() {
+        let configured = HashMap::from([(
+            String::from("example.org"),
+            Site {
+                port: Some(1966),
+                proxy: Some(false),
+                ..Site::default()
+            },
+        )]);
+        let changes = [
+            ("example.org", "port", "default"),
+            ("example.org", "tls_policy", "tofu"),
+            ("example.org", "follow_redirects", "sometimes"),
+        ]
+        .map(|(host, key, value)| (host.to_owned(), key.to_owned(), value.to_owned()));
+
+        let sites = Sites::new(configured, changes.to_vec());
+
+        assert_eq!(
+            sites.sites["example.org"],
+            Site {
+                tls_policy: Some(TlsPolicy::Tofu),
+                proxy: Some(false),
+                ..Site::default()
+            }
+        );
+    }
+
+    #[test]
+    fn changes_are_checked_before_being_saved() {
+        let (client, identities) = (GeminiClient::new(), Identities::default());
+        let mut sites = Sites::new(HashMap::new(), Vec::new());
+        let mut refused =
+            |host, key, value| sites.set(host, key, value, &client, &identities).is_err();
+
+        assert!(refused(
+            "example.org",
+            "port",
+            "1966\nevil.example\ttls_policy\taccept_all"
+        ));
+        assert!(refused("evil.example\tport", "port", "1966"));
+        assert!(refused("example.org", "identity", "nobody"));
+        assert!(sites.changes.is_empty());
+    }
+}
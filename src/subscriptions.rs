@@ -0,0 +1,122 @@
+use color_eyre::Result;
+
+use crate::dirs;
+
+const SUBSCRIPTIONS_FILE: &str = "subscriptions";
+
+pub struct Subscription {
+    pub url: String,
+    pub title: String,
+}
+
+/// Subscribed capsules, persisted in the data directory as `{url}\t{title}`
+/// lines.
+#[derive(Default)]
+pub struct Subscriptions {
+    entries: Vec<Subscription>,
+}
+
+impl Subscriptions {
+    pub fn load() -> Self {
+        let entries = dirs::read_data(SUBSCRIPTIONS_FILE)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let (url, title) = line.split_once('\t').unwrap_or((line, ""));
+                (!url.is_empty()).then(|| Subscription {
+                    url: url.to_owned(),
+                    title: title.to_owned(),
+                })
+            })
+            .collect();
+
+        Self { entries }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Subscription> {
+        self.entries.iter()
+    }
+
+    /// Title the capsule was subscribed with, if it had one.
+    pub fn title(&self, url: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|entry| entry.url == url && !entry.title.is_empty())
+            .map(|entry| entry.title.as_str())
+    }
+
+    pub fn contains(&self, url: &str) -> bool {
+        self.entries.iter().any(|entry| entry.url == url)
+    }
+
+    /// Subscribes to the URL, or unsubscribes from it if it was already
+    /// subscribed.
+    pub fn toggle(&mut self, url: &str, title: &str) -> Result<()> {
+        if self.contains(url) {
+            self.entries.retain(|entry| entry.url != url);
+        } else {
+            self.entries.push(Subscription {
+                url: url.to_owned(),
+                title: title.to_owned(),
+            });
+        }
+
+        self.save()
+    }
+
+    /// Points the subscription to a URL it moved to permanently.
+    pub fn replace(&mut self, from: &str, to: &str) -> Result<()> {
+        for entry in self.entries.iter_mut().filter(|entry| entry.url == from) {
+            entry.url = to.to_owned();
+        }
+
+        self.save()
+    }
+
+    /// Renders the subscriptions as an OPML 2.0 document that conventional
+    /// feed readers can import.
+    pub fn to_opml(&self) -> String {
+        let outlines = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let title = if entry.title.is_empty() {
+                    &entry.url
+                } else {
+                    &entry.title
+                };
+                format!(
+                    "    <outline type=\"rss\" text=\"{0}\" title=\"{0}\" xmlUrl=\"{1}\" htmlUrl=\"{1}\"/>\n",
+                    escape_xml(title),
+                    escape_xml(&entry.url)
+                )
+            })
+            .collect::<String>();
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <opml version=\"2.0\">\n  \
+             <head>\n    <title>dioscuri subscriptions</title>\n  </head>\n  \
+             <body>\n{}  </body>\n\
+             </opml>\n",
+            outlines
+        )
+    }
+
+    fn save(&self) -> Result<()> {
+        let text = self
+            .entries
+            .iter()
+            .map(|entry| format!("{}\t{}\n", entry.url, entry.title))
+            .collect::<String>();
+
+        dirs::write_data(SUBSCRIPTIONS_FILE, &text)
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
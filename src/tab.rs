@@ -0,0 +1,455 @@
+use std::{
+    cell::{Ref, RefCell},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local};
+use color_eyre::Result;
+use gemini_client::{
+    Metrics,
+    gemtext::{self, Document},
+};
+use ratatui::widgets::{Paragraph, ScrollbarState, Wrap};
+use unicode_width::UnicodeWidthStr;
+use url::Url;
+
+use crate::{
+    filters::{self, Filter},
+    gempub::{self, Gempub, Positions},
+    navigation::History,
+    reader,
+    render::{self, StyledLine},
+    search::Search,
+    theme::Theme,
+};
+
+/// Longest page title shown in the tab strip.
+const LABEL_LENGTH: usize = 24;
+
+#[derive(Clone, Default)]
+pub struct Scroll {
+    pub value: usize,
+    pub max: usize,
+    pub state: ScrollbarState,
+}
+
+/// Rows of the body lines once wrapped to a width, kept until either one
+/// changes so that scrolling and drawing don't wrap the whole page again.
+pub struct Layout {
+    width: u16,
+    /// Row at which each line starts.
+    pub offsets: Vec<usize>,
+    pub rows: usize,
+}
+
+impl Layout {
+    fn new(lines: &[StyledLine], width: u16) -> Self {
+        let mut rows = 0;
+        let offsets = lines
+            .iter()
+            .map(|line| {
+                let offset = rows;
+                rows += if line.preformatted {
+                    1
+                } else {
+                    Paragraph::new(line.text.as_str())
+                        .wrap(Wrap { trim: false })
+                        .line_count(width)
+                        .max(1)
+                };
+                offset
+            })
+            .collect();
+
+        Self {
+            width,
+            offsets,
+            rows,
+        }
+    }
+
+    /// Index of the line shown at the row.
+    pub fn line_at(&self, row: usize) -> usize {
+        self.offsets
+            .partition_point(|&offset| offset <= row)
+            .saturating_sub(1)
+    }
+}
+
+/// Auto-refresh timer of a tab. It only counts down while the tab is shown,
+/// so background tabs are left alone.
+pub struct Refresh {
+    pub interval: Duration,
+    remaining: Duration,
+}
+
+impl Refresh {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            remaining: interval,
+        }
+    }
+
+    /// Advances the countdown, returning whether the page is due for a
+    /// refresh, in which case the countdown starts over.
+    pub fn elapse(&mut self, elapsed: Duration) -> bool {
+        self.remaining = self.remaining.saturating_sub(elapsed);
+
+        if self.remaining.is_zero() {
+            self.remaining = self.interval;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn countdown(&self) -> String {
+        let seconds = self.remaining.as_secs() + u64::from(self.remaining.subsec_nanos() > 0);
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+pub struct Book {
+    pub source: String,
+    pub gempub: Gempub,
+    pub position: gempub::Position,
+}
+
+/// A page open in the browser along with everything needed to come back to
+/// it: its own history, scroll position, search and focused link.
+pub struct Tab {
+    /// URL the page is recorded under, see [`crate::navigation::canonical_url`].
+    pub url: String,
+    /// URL the page was actually served from, after every redirect. It is
+    /// shown in the address bar and relative links resolve against it.
+    pub location: String,
+    pub mime: String,
+    /// Page as received, before filters are applied.
+    pub source: String,
+    pub body: String,
+    /// Rendered body, only replaced by [`Self::apply_filters`] which resets
+    /// the layout along with it.
+    pub lines: Vec<StyledLine>,
+    /// Words of the body, see [`render::word_count`].
+    pub words: usize,
+    layout: RefCell<Option<Layout>>,
+    pub filtering: bool,
+    /// Whether the source is shown as plain text instead of being rendered.
+    pub raw: bool,
+    /// Whether the page was written by the browser, like about: and error
+    /// pages, whose links may run the actions of about: pages.
+    pub internal: bool,
+    /// Whether the page is linearized for screen readers, see
+    /// [`reader::linearize`]. It stays on for the next pages of the tab.
+    pub reader: bool,
+    pub encoding: &'static str,
+    /// How the page was fetched, unknown for cached and local pages.
+    pub metrics: Option<Metrics>,
+    /// When the page was fetched, for those read from the cache.
+    pub cached_at: Option<DateTime<Local>>,
+    pub history: History,
+    pub book: Option<Book>,
+    pub search: Option<Search>,
+    /// Index of the focused link line.
+    pub link: Option<usize>,
+    pub scroll: Scroll,
+    /// Columns the preformatted lines are scrolled by.
+    pub hscroll: usize,
+    pub refresh: Option<Refresh>,
+}
+
+impl Default for Tab {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            location: String::new(),
+            mime: String::new(),
+            source: String::new(),
+            body: String::new(),
+            lines: Vec::new(),
+            words: 0,
+            layout: RefCell::default(),
+            filtering: true,
+            raw: false,
+            internal: false,
+            reader: false,
+            encoding: "UTF-8",
+            metrics: None,
+            cached_at: None,
+            history: History::default(),
+            book: None,
+            search: None,
+            link: None,
+            scroll: Scroll::default(),
+            hscroll: 0,
+            refresh: None,
+        }
+    }
+}
+
+impl Tab {
+    /// Copy of the tab with its page, history and scroll position, but
+    /// neither its search nor its auto-refresh. Books are left out, they
+    /// are opened again from their source.
+    pub fn duplicate(&self) -> Self {
+        Self {
+            url: self.url.clone(),
+            location: self.location.clone(),
+            mime: self.mime.clone(),
+            source: self.source.clone(),
+            body: self.body.clone(),
+            lines: self.lines.clone(),
+            words: self.words,
+            layout: RefCell::default(),
+            filtering: self.filtering,
+            raw: self.raw,
+            internal: self.internal,
+            reader: self.reader,
+            encoding: self.encoding,
+            metrics: self.metrics,
+            cached_at: self.cached_at,
+            history: self.history.clone(),
+            book: None,
+            search: None,
+            link: self.link,
+            scroll: self.scroll.clone(),
+            hscroll: self.hscroll,
+            refresh: None,
+        }
+    }
+
+    /// Returns the text of the first top-level heading of a gemtext page.
+    pub fn page_title(&self) -> Option<&str> {
+        self.body
+            .lines()
+            .find_map(|line| line.strip_prefix("# "))
+            .map(str::trim)
+    }
+
+    /// Title of the open book, or of the page otherwise.
+    pub fn title(&self) -> Option<&str> {
+        match &self.book {
+            Some(book) => book.gempub.title(),
+            None => self.page_title(),
+        }
+    }
+
+    /// Name shown in the tab strip: the book or page title, falling back to
+    /// the host.
+    pub fn label(&self) -> String {
+        let label = self
+            .title()
+            .map(str::to_owned)
+            .or_else(|| {
+                Url::parse(&self.url)
+                    .ok()
+                    .and_then(|url| url.host_str().map(str::to_owned))
+            })
+            .unwrap_or_else(|| {
+                if self.url.is_empty() {
+                    String::from("New Tab")
+                } else {
+                    self.url.clone()
+                }
+            });
+
+        if label.chars().count() > LABEL_LENGTH {
+            let label = label.chars().take(LABEL_LENGTH - 1).collect::<String>();
+            format!("{}…", label)
+        } else {
+            label
+        }
+    }
+
+    pub fn scroll_up(&mut self, rows: usize) {
+        self.set_scroll(self.scroll.value.saturating_sub(rows));
+    }
+
+    pub fn scroll_down(&mut self, rows: usize) {
+        self.set_scroll(self.scroll.value.saturating_add(rows).min(self.scroll.max));
+    }
+
+    /// Scrolls the preformatted lines by `columns`, no further than the
+    /// widest of them overflows a viewport of `width`.
+    pub fn scroll_horizontally(&mut self, columns: isize, width: u16) {
+        let widest = self
+            .lines
+            .iter()
+            .filter(|line| line.preformatted)
+            .map(|line| line.text.width())
+            .max()
+            .unwrap_or_default();
+        self.hscroll = self
+            .hscroll
+            .saturating_add_signed(columns)
+            .min(widest.saturating_sub(width as usize));
+    }
+
+    pub fn set_scroll(&mut self, value: usize) {
+        self.scroll.value = value;
+        self.scroll.state = self.scroll.state.position(value);
+    }
+
+    /// Stores the scroll position in the current history entry, unless the
+    /// page shown is not the entry itself (like the history tree page).
+    pub fn save_history_scroll(&mut self) {
+        if self
+            .history
+            .current()
+            .is_some_and(|entry| entry.url == self.url)
+        {
+            self.history.set_scroll(self.scroll.value);
+        }
+    }
+
+    /// Layout of the body wrapped to `width`, computed once per page and
+    /// width.
+    pub fn layout(&self, width: u16) -> Ref<'_, Layout> {
+        let stale = self
+            .layout
+            .borrow()
+            .as_ref()
+            .is_none_or(|layout| layout.width != width);
+        if stale {
+            *self.layout.borrow_mut() = Some(Layout::new(&self.lines, width));
+        }
+
+        Ref::map(self.layout.borrow(), |layout| {
+            layout.as_ref().expect("the layout was just computed")
+        })
+    }
+
+    /// Row at which each body line starts once wrapped to `width`.
+    pub fn line_offsets(&self, width: u16) -> Ref<'_, [usize]> {
+        Ref::map(self.layout(width), |layout| layout.offsets.as_slice())
+    }
+
+    /// Index of the body line shown at the top of the viewport.
+    pub fn top_line(&self, width: u16) -> usize {
+        self.layout(width).line_at(self.scroll.value)
+    }
+
+    /// Scrolls just enough for the line to be inside a viewport of `height`
+    /// rows.
+    pub fn reveal(&mut self, line: usize, width: u16, height: usize) {
+        let offset = self.line_offsets(width)[line];
+
+        if offset < self.scroll.value || offset >= self.scroll.value + height {
+            self.set_scroll(offset.min(self.scroll.max));
+        }
+    }
+
+    /// Focuses the next or previous link line, starting from the top of the
+    /// viewport when no link is focused yet.
+    pub fn focus_link(&mut self, forward: bool, width: u16, height: usize) {
+        let links = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.link.is_some())
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        let focused = match self.link {
+            Some(current) if forward => links.iter().find(|&&link| link > current),
+            Some(current) => links.iter().rev().find(|&&link| link < current),
+            None => {
+                let top_line = self.top_line(width);
+                links.iter().find(|&&link| link >= top_line)
+            }
+        };
+        let focused = match focused {
+            Some(&link) => Some(link),
+            None if forward => links.first().copied(),
+            None => links.last().copied(),
+        };
+
+        self.link = focused;
+
+        if let Some(line) = focused {
+            self.reveal(line, width, height);
+        }
+    }
+
+    /// Targets of the links at least partly inside a viewport of `height`
+    /// rows, as written in the page.
+    pub fn visible_links(&self, width: u16, height: usize) -> Vec<String> {
+        let layout = self.layout(width);
+        let first = layout.line_at(self.scroll.value);
+        let last = layout.line_at(self.scroll.value + height.saturating_sub(1));
+
+        self.lines
+            .get(first..=last)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|line| line.link.clone())
+            .collect()
+    }
+
+    /// Target of the focused link, as written in the page.
+    pub fn focused_link(&self) -> Option<&str> {
+        self.link
+            .and_then(|line| self.lines.get(line))
+            .and_then(|line| line.link.as_deref())
+    }
+
+    pub fn show_page(&mut self, source: String, scroll: usize, filters: &[Filter], theme: &Theme) {
+        self.source = source;
+        self.filtering = true;
+        self.raw = false;
+        self.internal = false;
+        self.apply_filters(filters, theme);
+        self.set_scroll(scroll);
+    }
+
+    /// Rebuilds the body from the page source, running it through the
+    /// filters configured for its host unless filtering was toggled off.
+    pub fn apply_filters(&mut self, filters: &[Filter], theme: &Theme) {
+        let host = Url::parse(&self.url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_owned));
+
+        let filtered = host
+            .filter(|_| self.filtering)
+            .and_then(|host| filters::apply(filters, &host, &self.source));
+
+        self.body = filtered.unwrap_or_else(|| self.source.clone());
+        self.search = None;
+        self.link = None;
+        self.hscroll = 0;
+
+        let document = self.document();
+        self.lines = render::render(&document, theme);
+        self.words = render::word_count(&document);
+        *self.layout.get_mut() = None;
+    }
+
+    /// Body of the page as a document, the way it is shown.
+    pub fn document(&self) -> Document {
+        if self.raw {
+            Document::plain(&self.source)
+        } else if self.mime == gemtext::MIME && self.reader {
+            reader::linearize(&Document::parse(&self.body))
+        } else if self.mime == gemtext::MIME {
+            Document::parse(&self.body)
+        } else if self.mime == gopher_client::MENU_MIME {
+            gopher_client::menu::parse(&self.body)
+        } else if self.mime == finger_client::MIME {
+            Document::preformatted(&self.body)
+        } else {
+            Document::plain(&self.body)
+        }
+    }
+
+    /// Stores the reading position of the open book, if any, and closes it.
+    pub fn close_book(&mut self, positions: &mut Positions) -> Result<()> {
+        if let Some(mut book) = self.book.take() {
+            book.position
+                .set_scroll(book.position.chapter, self.scroll.value);
+            positions.set(book.source, book.position)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+use crate::config::from_str_option;
+
+/// Styles used across the interface.
+pub struct Theme {
+    /// Title, focused borders and the address bar while editing.
+    pub accent: Color,
+    pub address_bar: Style,
+    pub body: Style,
+    pub headings: [Style; 3],
+    pub link: Style,
+    pub list_item: Style,
+    pub quote: Style,
+    pub preformatted: Style,
+    pub scrollbar: Style,
+    pub status_line: Style,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    /// Terminal colors with a blue accent.
+    #[default]
+    Default,
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub fn preset(preset: Preset) -> Self {
+        let bold = Style::new().add_modifier(Modifier::BOLD);
+
+        match preset {
+            Preset::Default => Self {
+                accent: Color::Blue,
+                address_bar: Style::new(),
+                body: Style::new(),
+                headings: [
+                    bold.add_modifier(Modifier::UNDERLINED),
+                    bold,
+                    bold.add_modifier(Modifier::ITALIC),
+                ],
+                link: Style::new().fg(Color::Blue),
+                list_item: Style::new(),
+                quote: Style::new().add_modifier(Modifier::ITALIC),
+                preformatted: Style::new(),
+                scrollbar: Style::new(),
+                status_line: Style::new(),
+            },
+            Preset::Dark => Self {
+                accent: Color::LightBlue,
+                address_bar: Style::new().fg(Color::White),
+                body: Style::new().fg(Color::Gray),
+                headings: [
+                    bold.fg(Color::LightYellow),
+                    bold.fg(Color::Yellow),
+                    bold.fg(Color::LightMagenta),
+                ],
+                link: Style::new().fg(Color::LightCyan),
+                list_item: Style::new().fg(Color::Gray),
+                quote: Style::new()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::ITALIC),
+                preformatted: Style::new().fg(Color::LightGreen),
+                scrollbar: Style::new().fg(Color::DarkGray),
+                status_line: Style::new().fg(Color::Gray),
+            },
+            Preset::Light => Self {
+                accent: Color::Blue,
+                address_bar: Style::new().fg(Color::Black),
+                body: Style::new().fg(Color::Black),
+                headings: [
+                    bold.fg(Color::Magenta),
+                    bold.fg(Color::Red),
+                    bold.fg(Color::DarkGray),
+                ],
+                link: Style::new().fg(Color::Blue),
+                list_item: Style::new().fg(Color::Black),
+                quote: Style::new()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::ITALIC),
+                preformatted: Style::new().fg(Color::Green),
+                scrollbar: Style::new().fg(Color::DarkGray),
+                status_line: Style::new().fg(Color::DarkGray),
+            },
+        }
+    }
+}
+
+/// Theme section of the config file: a preset plus optional foreground color
+/// overrides for each element.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub preset: Preset,
+    #[serde(deserialize_with = "from_str_option")]
+    pub accent: Option<Color>,
+    #[serde(deserialize_with = "from_str_option")]
+    pub address_bar: Option<Color>,
+    #[serde(deserialize_with = "from_str_option")]
+    pub body: Option<Color>,
+    #[serde(deserialize_with = "from_str_option")]
+    pub headings: Option<Color>,
+    #[serde(deserialize_with = "from_str_option")]
+    pub link: Option<Color>,
+    #[serde(deserialize_with = "from_str_option")]
+    pub list_item: Option<Color>,
+    #[serde(deserialize_with = "from_str_option")]
+    pub quote: Option<Color>,
+    #[serde(deserialize_with = "from_str_option")]
+    pub preformatted: Option<Color>,
+    #[serde(deserialize_with = "from_str_option")]
+    pub scrollbar: Option<Color>,
+    #[serde(deserialize_with = "from_str_option")]
+    pub status_line: Option<Color>,
+}
+
+impl From<&ThemeConfig> for Theme {
+    fn from(config: &ThemeConfig) -> Self {
+        let mut theme = Theme::preset(config.preset);
+        let apply = |style: &mut Style, color: Option<Color>| {
+            if let Some(color) = color {
+                *style = style.fg(color);
+            }
+        };
+
+        if let Some(accent) = config.accent {
+            theme.accent = accent;
+        }
+        apply(&mut theme.address_bar, config.address_bar);
+        apply(&mut theme.body, config.body);
+        for heading in &mut theme.headings {
+            apply(heading, config.headings);
+        }
+        apply(&mut theme.link, config.link);
+        apply(&mut theme.list_item, config.list_item);
+        apply(&mut theme.quote, config.quote);
+        apply(&mut theme.preformatted, config.preformatted);
+        apply(&mut theme.scrollbar, config.scrollbar);
+        apply(&mut theme.status_line, config.status_line);
+
+        theme
+    }
+}
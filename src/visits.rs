@@ -0,0 +1,175 @@
+use chrono::{DateTime, Local};
+use color_eyre::Result;
+
+use crate::{dates, dirs, navigation::encode};
+
+const VISITS_FILE: &str = "history";
+
+pub struct Visit {
+    pub url: String,
+    pub title: Option<String>,
+    /// Unix timestamp of the visit.
+    pub time: i64,
+}
+
+impl Visit {
+    fn local_time(&self) -> Option<DateTime<Local>> {
+        DateTime::from_timestamp(self.time, 0).map(|time| time.with_timezone(&Local))
+    }
+
+    fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+
+        self.url.to_lowercase().contains(&query)
+            || self
+                .title
+                .as_ref()
+                .is_some_and(|title| title.to_lowercase().contains(&query))
+    }
+}
+
+/// Every page visited, oldest first, persisted in the data directory as
+/// `{timestamp}\t{url}\t{title}` lines. New visits are appended to the file
+/// rather than rewriting it.
+#[derive(Default)]
+pub struct Visits {
+    visits: Vec<Visit>,
+}
+
+impl Visits {
+    pub fn load() -> Self {
+        let Some(text) = dirs::read_data(VISITS_FILE) else {
+            return Self::default();
+        };
+
+        let visits = text
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let time = fields.next()?.parse().ok()?;
+                let url = fields.next()?.to_owned();
+                let title = fields
+                    .next()
+                    .filter(|title| !title.is_empty())
+                    .map(str::to_owned);
+
+                Some(Visit { url, title, time })
+            })
+            .collect();
+
+        Self { visits }
+    }
+
+    /// Visits from the most recent one.
+    pub fn recent(&self) -> impl Iterator<Item = &Visit> {
+        self.visits.iter().rev()
+    }
+
+    pub fn record(&mut self, url: &str, title: Option<&str>) -> Result<()> {
+        let visit = Visit {
+            url: url.to_owned(),
+            title: title.map(|title| title.replace(['\t', '\n'], " ")),
+            time: Local::now().timestamp(),
+        };
+
+        dirs::append_data(
+            VISITS_FILE,
+            &format!(
+                "{}\t{}\t{}\n",
+                visit.time,
+                visit.url,
+                visit.title.as_deref().unwrap_or_default()
+            ),
+        )?;
+        self.visits.push(visit);
+
+        Ok(())
+    }
+
+    /// Forgets every visit to the URL.
+    pub fn remove(&mut self, url: &str) -> Result<()> {
+        self.visits.retain(|visit| visit.url != url);
+        self.save()
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        self.visits.clear();
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let text = self
+            .visits
+            .iter()
+            .map(|visit| {
+                format!(
+                    "{}\t{}\t{}\n",
+                    visit.time,
+                    visit.url,
+                    visit.title.as_deref().unwrap_or_default()
+                )
+            })
+            .collect::<String>();
+
+        dirs::write_data(VISITS_FILE, &text)
+    }
+
+    /// Gemtext page listing the visits matching the query, newest first and
+    /// grouped by day. Days are labelled with how long ago they were, unless
+    /// `absolute`.
+    pub fn to_gemtext(&self, query: Option<&str>, absolute: bool) -> String {
+        let mut page = match query {
+            Some(query) => format!("# History matching \"{}\"\n", query),
+            None => String::from("# History\n"),
+        };
+        page.push_str("\nFilter the visits by opening about:history?<terms>.\n");
+        if query.is_none() && !self.visits.is_empty() {
+            page.push_str("=> about:history?clear=all Clear the history\n");
+        }
+
+        let matching = self
+            .recent()
+            .filter(|visit| query.is_none_or(|query| visit.matches(query)))
+            .collect::<Vec<_>>();
+        let mut day = None;
+
+        for visit in &matching {
+            let Some(time) = visit.local_time() else {
+                continue;
+            };
+
+            let date = time.date_naive();
+            if day != Some(date) {
+                page.push_str(&format!("\n## {}\n", dates::day_heading(date, absolute)));
+                day = Some(date);
+            }
+
+            page.push_str(&format!(
+                "=> {} {} {}\n",
+                visit.url,
+                time.format("%H:%M"),
+                visit.title.as_deref().unwrap_or(&visit.url)
+            ));
+        }
+
+        if day.is_none() {
+            page.push_str("\nNo visits found.\n");
+        } else if query.is_some() {
+            // Filtered visits can be forgotten page by page
+            page.push_str("\n## Forget\n");
+
+            let mut urls = matching.iter().map(|visit| &visit.url).collect::<Vec<_>>();
+            urls.sort();
+            urls.dedup();
+            for url in urls {
+                page.push_str(&format!(
+                    "=> about:history?delete={} Forget the visits to {}\n",
+                    encode(url),
+                    url
+                ));
+            }
+        }
+
+        page
+    }
+}